@@ -1,6 +1,7 @@
 use crate::{
     models::cli::{AppCliArgs, AppCliFlagName, CliArgsReplication},
-    DEFAULT_LISTENING_PORT,
+    resp_parser::shared::RespProtocolLimits,
+    DEFAULT_DATABASES, DEFAULT_LISTENING_PORT,
 };
 
 use anyhow::{Error, Result};
@@ -9,6 +10,8 @@ pub fn parse_cli_args() -> Result<AppCliArgs, Error> {
     let mut flags = AppCliArgs {
         port: DEFAULT_LISTENING_PORT,
         replica_of: None,
+        protocol_limits: RespProtocolLimits::default(),
+        databases: DEFAULT_DATABASES,
     };
 
     let mut arg_iter = std::env::args().peekable();
@@ -63,6 +66,22 @@ pub fn parse_cli_args() -> Result<AppCliArgs, Error> {
                 });
             }
 
+            &mut AppCliFlagName::PROTO_MAX_BULK_LEN => {
+                flags.protocol_limits.max_bulk_len =
+                    parse_limit_arg(next_arg, AppCliFlagName::PROTO_MAX_BULK_LEN)?;
+                arg_iter.next();
+            }
+            &mut AppCliFlagName::PROTO_MAX_MULTIBULK_LEN => {
+                flags.protocol_limits.max_multibulk_len =
+                    parse_limit_arg(next_arg, AppCliFlagName::PROTO_MAX_MULTIBULK_LEN)?;
+                arg_iter.next();
+            }
+
+            &mut AppCliFlagName::DATABASES => {
+                flags.databases = parse_limit_arg(next_arg, AppCliFlagName::DATABASES)?;
+                arg_iter.next();
+            }
+
             _ => {}
         }
 
@@ -71,3 +90,13 @@ pub fn parse_cli_args() -> Result<AppCliArgs, Error> {
 
     Ok(flags)
 }
+
+fn parse_limit_arg(arg: Option<&String>, flag_name: &str) -> Result<usize, Error> {
+    match arg.and_then(|arg| arg.parse::<usize>().ok()) {
+        Some(limit) if limit > 0 => Ok(limit),
+        _ => Err(Error::msg(format!(
+            "The CLI could not parse {} - Not a valid positive number.",
+            flag_name
+        ))),
+    }
+}
@@ -7,11 +7,14 @@ mod utils;
 
 use models::db::{app_data::AppData, in_memory_db::InMemoryDb};
 
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::{Error, Result};
 
+/// The Redis version the server is compatible with, as replied by `HELLO`.
+const SERVER_VERSION: &str = "7.4.0";
 const DEFAULT_LISTENING_PORT: u16 = 6379;
+const DEFAULT_DATABASES: usize = 16;
 const TCP_RESPONSE_BUFFER_SIZE: usize = 1024;
 const TCP_READ_TIMEOUT: Duration = Duration::from_millis(1000);
 const TCP_READ_TIMEOUT_MAX_RETRIES: u8 = 3;
@@ -21,21 +24,24 @@ async fn main() -> Result<(), Error> {
     let cli_flags = cli::parse_cli_args()?;
     let is_replica = cli_flags.replica_of.is_some();
 
-    let app_data = if !is_replica {
+    let mut app_data = if !is_replica {
         AppData::new_master(cli_flags.port)?
     } else {
         AppData::new_replica(cli_flags.port, cli_flags.replica_of.unwrap().into())
     };
+    app_data.protocol_limits = cli_flags.protocol_limits;
+    app_data.databases = cli_flags.databases;
 
     let mem_db = InMemoryDb::new(app_data)?;
 
+    tokio::spawn(node::expiry::run(Arc::clone(&mem_db)));
+
     if is_replica {
         println!("Running server in replica mode.");
 
-        match node::replica::handshake(&mem_db).await {
-            Err(e) => println!("Error while performing handshake with master: {:?}", e),
-            Ok(_) => {}
-        };
+        if let Err(e) = node::replica::handshake(&mem_db).await {
+            println!("Error while performing handshake with master: {:?}", e);
+        }
     } else {
         println!("Running server in master mode.");
     }
@@ -1,9 +1,12 @@
 use super::db::app_data::AppDataReplication;
+use crate::resp_parser::shared::RespProtocolLimits;
 
 #[derive(Debug)]
 pub struct AppCliArgs {
     pub port: u16,
     pub replica_of: Option<CliArgsReplication>,
+    pub protocol_limits: RespProtocolLimits,
+    pub databases: usize,
 }
 
 #[derive(Debug)]
@@ -12,11 +15,11 @@ pub struct CliArgsReplication {
     pub master_port: u16,
 }
 
-impl Into<AppDataReplication> for CliArgsReplication {
-    fn into(self) -> AppDataReplication {
+impl From<CliArgsReplication> for AppDataReplication {
+    fn from(replication: CliArgsReplication) -> Self {
         AppDataReplication {
-            master_host: self.master_host,
-            master_port: self.master_port,
+            master_host: replication.master_host,
+            master_port: replication.master_port,
         }
     }
 }
@@ -28,4 +31,9 @@ impl AppCliFlagName {
     pub const PORT_SHORT: &'static str = "-p";
 
     pub const REPLICA_OF: &'static str = "--replicaof";
+
+    pub const PROTO_MAX_BULK_LEN: &'static str = "--proto-max-bulk-len";
+    pub const PROTO_MAX_MULTIBULK_LEN: &'static str = "--proto-max-multibulk-len";
+
+    pub const DATABASES: &'static str = "--databases";
 }
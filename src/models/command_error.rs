@@ -0,0 +1,122 @@
+use crate::resp_parser::shared::RespValue;
+
+use anyhow::Error;
+use bytes::Bytes;
+
+/// Errors that are replied to the client as RESP errors, with the same prefixes and messages as
+/// Redis, so that clients can tell them apart.
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("ERR unknown command '{name}', with args beginning with: {args}")]
+    UnknownCommand { name: String, args: String },
+
+    /// Holds the command name in lower case, as Redis replies it.
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+
+    #[error("ERR syntax error")]
+    Syntax,
+
+    #[error("ERR value is not an integer or out of range")]
+    NotAnInteger,
+
+    #[error("ERR value is not a valid float")]
+    NotAFloat,
+
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+
+    #[error("NOPROTO unsupported protocol version")]
+    UnsupportedProtocol,
+
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
+
+    /// Any other error, the message is prefixed with "ERR".
+    #[error("ERR {0}")]
+    Other(String),
+
+    /// The request could not be parsed, so the connection is closed after replying, since
+    /// there is no way of telling where the next command starts.
+    #[error("ERR Protocol error: {0}")]
+    Protocol(String),
+}
+
+impl CommandError {
+    pub fn unknown_command(name: &str, parameters: &[Bytes]) -> Self {
+        CommandError::UnknownCommand {
+            name: name.to_owned(),
+            args: parameters
+                .iter()
+                .map(|parameter| format!("'{}' ", String::from_utf8_lossy(parameter)))
+                .collect(),
+        }
+    }
+
+    pub fn wrong_arity(name: &str) -> Self {
+        CommandError::WrongArity(name.to_ascii_lowercase())
+    }
+
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, CommandError::Protocol(_))
+    }
+}
+
+/// Maps any error returned while handling a command into the RESP error replied to the client.
+/// <br/>
+/// Errors that are not a [`CommandError`] are replied as generic "ERR" errors.
+pub fn into_error_reply(error: &Error) -> RespValue {
+    RespValue::Error(match error.downcast_ref::<CommandError>() {
+        Some(command_error) => command_error.to_string(),
+        None => CommandError::Other(error.to_string()).to_string(),
+    })
+}
+
+/// Whether the connection has to be closed after replying to `error`.
+pub fn is_fatal_error(error: &Error) -> bool {
+    error
+        .downcast_ref::<CommandError>()
+        .is_some_and(|command_error| command_error.is_fatal())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{into_error_reply, is_fatal_error, CommandError};
+    use crate::resp_parser::shared::RespValue;
+
+    use anyhow::Error;
+    use bytes::Bytes;
+
+    #[test]
+    fn into_error_reply_uses_redis_prefixes() {
+        assert_eq!(
+            into_error_reply(&CommandError::NotAnInteger.into()),
+            RespValue::Error("ERR value is not an integer or out of range".to_owned())
+        );
+        assert_eq!(
+            into_error_reply(
+                &CommandError::unknown_command("FOO", &[Bytes::from("a"), Bytes::from("b")]).into()
+            ),
+            RespValue::Error(
+                "ERR unknown command 'FOO', with args beginning with: 'a' 'b' ".to_owned()
+            )
+        );
+        assert_eq!(
+            into_error_reply(&CommandError::wrong_arity("GET").into()),
+            RespValue::Error("ERR wrong number of arguments for 'get' command".to_owned())
+        );
+        assert_eq!(
+            into_error_reply(&Error::msg("something went wrong")),
+            RespValue::Error("ERR something went wrong".to_owned())
+        );
+    }
+
+    #[test]
+    fn is_fatal_error_passes() {
+        assert!(is_fatal_error(
+            &CommandError::Protocol("invalid bulk length".to_owned()).into()
+        ));
+        assert!(!is_fatal_error(&CommandError::Syntax.into()));
+        assert!(!is_fatal_error(&Error::msg("something went wrong")));
+    }
+}
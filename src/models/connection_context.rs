@@ -2,13 +2,29 @@ use super::{
     db::{app_data::AppData, in_memory_db::InMemoryDb},
     t_stream::TStream,
 };
-use crate::{resp_parser::shared::RespCommand, TCP_RESPONSE_BUFFER_SIZE};
+use crate::{
+    resp_parser::{
+        encode_resp_value,
+        shared::{RespCommand, RespProtocolLimits, RespProtocolVersion, RespValue},
+    },
+    TCP_RESPONSE_BUFFER_SIZE,
+};
 
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use anyhow::Error;
+use bytes::{Bytes, BytesMut};
 use tokio::sync::Mutex;
 
+/// Client ids are unique and never reused while the server runs, like in Redis.
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
 #[derive(Debug)]
 pub struct ConnectionContext<'a> {
     pub mem_db: &'a Arc<Mutex<InMemoryDb>>,
@@ -16,6 +32,21 @@ pub struct ConnectionContext<'a> {
 
     /// Each response value is written separably into the TCP stream.
     pub response: Vec<Response>,
+
+    pub client_id: u64,
+    /// Set with `HELLO ... SETNAME`.
+    pub client_name: Option<Bytes>,
+    /// The protocol the responses are encoded with, negotiated with `HELLO`.
+    pub protocol_version: RespProtocolVersion,
+    /// The index of the database commands run against, changed with `SELECT`.
+    pub selected_db: usize,
+
+    /// How a write command reaches the replicas, set by the handlers of write commands that
+    /// can't be propagated as they were received.
+    pub propagation: Propagation,
+    /// The commands replicas are sent for what the command served to blocked clients, with their
+    /// database index, propagated right after the command.
+    pub served_commands: Vec<(usize, RespCommand)>,
 }
 
 impl<'a> ConnectionContext<'a> {
@@ -27,14 +58,23 @@ impl<'a> ConnectionContext<'a> {
             mem_db,
             request: Request::new(tcp_stream),
             response: Vec::<Response>::new(),
+            client_id: NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            client_name: None,
+            protocol_version: RespProtocolVersion::default(),
+            selected_db: 0,
+            propagation: Propagation::Received,
+            served_commands: Vec::new(),
         })
     }
 
+    /// Clears the state of the last handled command. <br/>
+    /// The request buffer is kept, since it may already hold (part of) the next command.
     pub fn reset(&mut self) -> &Self {
-        self.request.buffer = [0; TCP_RESPONSE_BUFFER_SIZE];
-        self.request.byte_count = 0;
+        self.request.raw_command = Bytes::new();
         self.request.resp_command = None;
         self.response = Vec::new();
+        self.propagation = Propagation::Received;
+        self.served_commands = Vec::new();
 
         self
     }
@@ -53,10 +93,6 @@ impl<'a> ConnectionContext<'a> {
         )
     }
 
-    pub fn get_request_ref(&self) -> &Request {
-        &self.request
-    }
-
     pub fn set_request_resp_command(&mut self, resp_command: RespCommand) -> &Self {
         if self.request.resp_command.is_none() {
             self.request.resp_command = Some(resp_command);
@@ -96,17 +132,26 @@ impl<'a> ConnectionContext<'a> {
     }
 }
 
-#[derive(Debug)]
-pub struct InternalRequest {
-    pub buffer: [u8; TCP_RESPONSE_BUFFER_SIZE],
-    pub byte_count: usize,
+#[derive(Debug, Clone, PartialEq)]
+pub enum Propagation {
+    /// The command is propagated exactly as it was received.
+    Received,
+    /// The command is propagated as these commands instead, e.g. because the received one is not
+    /// deterministic (INCRBYFLOAT is propagated as a SET, so float rounding can't differ on
+    /// replicas). <br/>
+    /// No commands means the command did not change the keyspace, so nothing is propagated.
+    Rewritten(Vec<RespCommand>),
 }
 
 #[derive(Debug)]
 pub struct Request<'a> {
-    pub buffer: [u8; TCP_RESPONSE_BUFFER_SIZE],
-    pub byte_count: usize,
+    /// Bytes read from the stream that were not parsed into a command yet. <br/>
+    /// It grows as needed, so a command can span as many reads as it takes to receive it.
+    pub buffer: BytesMut,
+    /// The bytes of the command being handled, exactly as they were received.
+    pub raw_command: Bytes,
     pub resp_command: Option<RespCommand>,
+    pub protocol_limits: RespProtocolLimits,
     pub tcp_stream: &'a Arc<Mutex<dyn TStream>>,
     pub handshake: Handshake,
 }
@@ -120,42 +165,37 @@ pub enum Handshake {
 impl<'a> Request<'a> {
     pub fn new(tcp_stream: &'a Arc<Mutex<dyn TStream>>) -> Self {
         Request {
-            buffer: [0; TCP_RESPONSE_BUFFER_SIZE],
-            byte_count: 0,
+            buffer: BytesMut::with_capacity(TCP_RESPONSE_BUFFER_SIZE),
+            raw_command: Bytes::new(),
             resp_command: None,
+            protocol_limits: RespProtocolLimits::default(),
             tcp_stream,
             handshake: Handshake::None,
         }
     }
 }
 
-#[derive(Debug)]
-pub struct Response {
-    pub command_response: String,
-    pub command_byte_response: Option<Vec<u8>>,
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    /// A RESP reply, encoded when it is written into the stream.
+    Value(RespValue),
+    /// Bytes written as they are, for payloads that are not RESP replies
+    /// (e.g. the RDB file sent after a FULLRESYNC).
+    Raw(Vec<u8>),
 }
 
 impl Response {
-    pub fn new_string(response: String) -> Self {
-        Self {
-            command_response: response,
-            command_byte_response: None,
-        }
-    }
-
-    pub fn new_byte(response: Vec<u8>) -> Self {
-        Self {
-            command_response: "".to_string(),
-            command_byte_response: Some(response),
+    /// The bytes to write into a stream talking `protocol_version`.
+    pub fn to_bytes(&self, protocol_version: RespProtocolVersion) -> Vec<u8> {
+        match self {
+            Response::Value(value) => encode_resp_value(value, protocol_version),
+            Response::Raw(bytes) => bytes.to_owned(),
         }
     }
 }
 
-impl Clone for Response {
-    fn clone(&self) -> Self {
-        Self {
-            command_response: self.command_response.clone(),
-            command_byte_response: self.command_byte_response.clone(),
-        }
+impl From<RespValue> for Response {
+    fn from(value: RespValue) -> Self {
+        Response::Value(value)
     }
 }
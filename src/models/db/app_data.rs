@@ -3,11 +3,17 @@ use std::{collections::HashMap, sync::Arc};
 use anyhow::Error;
 use tokio::sync::Mutex;
 
-use crate::{models::t_stream::TStream, utils::pseudo_random_ascii_alphanumeric};
+use crate::{
+    models::t_stream::TStream, resp_parser::shared::RespProtocolLimits,
+    utils::pseudo_random_ascii_alphanumeric, DEFAULT_DATABASES,
+};
 
 #[derive(Debug)]
 pub struct AppData {
     pub listening_port: u16,
+    pub protocol_limits: RespProtocolLimits,
+    /// The number of logical databases.
+    pub databases: usize,
     master: Option<AppDataMaster>,
     replication: Option<AppDataReplication>,
 }
@@ -16,11 +22,14 @@ impl AppData {
     pub fn new_master(listening_port: u16) -> Result<Self, Error> {
         Ok(AppData {
             listening_port,
+            protocol_limits: RespProtocolLimits::default(),
+            databases: DEFAULT_DATABASES,
             master: Some(AppDataMaster {
                 // replid: "8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb".to_owned(),
                 replid: pseudo_random_ascii_alphanumeric(40)?,
                 repl_offset: 0,
                 slaves: HashMap::new(),
+                propagated_db: None,
             }),
             replication: None,
         })
@@ -29,6 +38,8 @@ impl AppData {
     pub fn new_replica(listening_port: u16, replica_config: AppDataReplication) -> Self {
         AppData {
             listening_port,
+            protocol_limits: RespProtocolLimits::default(),
+            databases: DEFAULT_DATABASES,
             master: None,
             replication: Some(replica_config),
         }
@@ -53,6 +64,9 @@ pub struct AppDataMaster {
     pub replid: String,
     pub repl_offset: u32,
     pub slaves: HashMap<u16, AppDataSlave>,
+    /// The database the replicas run the propagated commands against, i.e. the one of the last
+    /// SELECT sent to them, or `None` if a SELECT must be sent before the next command.
+    pub propagated_db: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -72,7 +86,7 @@ impl Clone for AppDataReplication {
     fn clone(&self) -> Self {
         AppDataReplication {
             master_host: self.master_host.clone(),
-            master_port: self.master_port.clone(),
+            master_port: self.master_port,
         }
     }
 }
@@ -0,0 +1,189 @@
+use super::in_memory_record::ListEnd;
+
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::Error;
+use bytes::Bytes;
+use tokio::sync::oneshot;
+
+/// The clients parked by blocking list commands (e.g. BLPOP) until a list they wait for gets
+/// elements. <br/>
+/// The commands that give a key elements record it as ready, and serve the clients blocked on
+/// the ready keys before they release the DB lock, in the order the clients blocked. That command
+/// also propagates what they were served.
+#[derive(Debug, Default)]
+pub struct BlockedClients {
+    next_id: u64,
+    clients: HashMap<u64, BlockedClient>,
+    /// The ids of the clients blocked on each key of each database, in the order they blocked.
+    queues: HashMap<(usize, Bytes), VecDeque<u64>>,
+    /// The keys clients are blocked on that may have elements now, in the order they got them.
+    ready_keys: Vec<(usize, Bytes)>,
+}
+
+#[derive(Debug)]
+pub struct BlockedClient {
+    pub db_index: usize,
+    pub keys: Vec<Bytes>,
+    pub operation: BlockedListOperation,
+    /// Wakes the client up with the elements it was served, or with the error it has to reply.
+    pub sender: oneshot::Sender<Result<ServedList, Error>>,
+}
+
+/// What a blocked client does with the first of its lists that gets elements.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockedListOperation {
+    /// BLPOP, BRPOP and BLMPOP: pops up to `count` elements.
+    Pop { end: ListEnd, count: usize },
+    /// BLMOVE: pops an element and pushes it to `destination`.
+    Move {
+        from: ListEnd,
+        destination: Bytes,
+        to: ListEnd,
+    },
+}
+
+/// The elements a blocked client was served, and the key they were popped from.
+#[derive(Debug, PartialEq)]
+pub struct ServedList {
+    pub key: Bytes,
+    pub elements: Vec<Bytes>,
+}
+
+impl BlockedClients {
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Parks a client on all of its keys, behind the clients already blocked on them, and returns
+    /// the id it can be unblocked with.
+    pub fn block(&mut self, client: BlockedClient) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        for key in &client.keys {
+            self.queues
+                .entry((client.db_index, key.clone()))
+                .or_default()
+                .push_back(id);
+        }
+        self.clients.insert(id, client);
+
+        id
+    }
+
+    /// Removes a client from the queues of all of its keys, e.g. because it timed out or it is
+    /// being served.
+    pub fn unblock(&mut self, id: u64) -> Option<BlockedClient> {
+        let client = self.clients.remove(&id)?;
+
+        for key in &client.keys {
+            let queue_key = (client.db_index, key.clone());
+
+            if let Some(queue) = self.queues.get_mut(&queue_key) {
+                queue.retain(|queued_id| *queued_id != id);
+
+                if queue.is_empty() {
+                    self.queues.remove(&queue_key);
+                }
+            }
+        }
+
+        Some(client)
+    }
+
+    /// The id of the client that blocked first on `key`, if any.
+    pub fn get_first_blocked_on(&self, db_index: usize, key: &Bytes) -> Option<u64> {
+        self.queues
+            .get(&(db_index, key.clone()))
+            .and_then(|queue| queue.front().copied())
+    }
+
+    /// Records that `key` may have elements now, e.g. after a push, if clients are blocked on it,
+    /// like `signalKeyAsReady` in Redis.
+    pub fn signal_ready(&mut self, db_index: usize, key: &Bytes) {
+        // Most pushes find no blocked client.
+        if self.is_empty() {
+            return;
+        }
+
+        let ready_key = (db_index, key.clone());
+
+        if self.queues.contains_key(&ready_key) && !self.ready_keys.contains(&ready_key) {
+            self.ready_keys.push(ready_key);
+        }
+    }
+
+    /// Records every key clients are blocked on in a database as ready, e.g. after SWAPDB.
+    pub fn signal_db_ready(&mut self, db_index: usize) {
+        let keys: Vec<Bytes> = self
+            .queues
+            .keys()
+            .filter(|(queue_db_index, _)| *queue_db_index == db_index)
+            .map(|(_, key)| key.clone())
+            .collect();
+
+        for key in &keys {
+            self.signal_ready(db_index, key);
+        }
+    }
+
+    /// Takes the keys recorded as ready, with their database index, to serve their clients.
+    pub fn take_ready_keys(&mut self) -> Vec<(usize, Bytes)> {
+        std::mem::take(&mut self.ready_keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockedClient, BlockedClients, BlockedListOperation};
+    use crate::models::db::in_memory_record::ListEnd;
+
+    use bytes::Bytes;
+    use tokio::sync::oneshot;
+
+    fn blocked_client(keys: &[&'static str]) -> BlockedClient {
+        BlockedClient {
+            db_index: 0,
+            keys: keys.iter().map(|key| Bytes::from(*key)).collect(),
+            operation: BlockedListOperation::Pop {
+                end: ListEnd::Left,
+                count: 1,
+            },
+            sender: oneshot::channel().0,
+        }
+    }
+
+    #[test]
+    fn blocked_clients_are_queued_per_key_in_order() {
+        let mut blocked_clients = BlockedClients::default();
+        let (a, b) = (Bytes::from("a"), Bytes::from("b"));
+
+        let first_id = blocked_clients.block(blocked_client(&["a", "b"]));
+        let second_id = blocked_clients.block(blocked_client(&["b"]));
+
+        assert_eq!(blocked_clients.get_first_blocked_on(0, &a), Some(first_id));
+        assert_eq!(blocked_clients.get_first_blocked_on(0, &b), Some(first_id));
+        assert_eq!(blocked_clients.get_first_blocked_on(1, &b), None);
+
+        // Unblocking a client removes it from the queues of all of its keys.
+        assert!(blocked_clients.unblock(first_id).is_some());
+        assert!(blocked_clients.unblock(first_id).is_none());
+        assert_eq!(blocked_clients.get_first_blocked_on(0, &a), None);
+        assert_eq!(blocked_clients.get_first_blocked_on(0, &b), Some(second_id));
+
+        // Only keys that clients are blocked on are recorded as ready, once.
+        blocked_clients.signal_ready(0, &a);
+        blocked_clients.signal_ready(0, &b);
+        blocked_clients.signal_db_ready(0);
+        assert_eq!(blocked_clients.take_ready_keys(), vec![(0, b)]);
+        assert!(blocked_clients.take_ready_keys().is_empty());
+
+        blocked_clients.unblock(second_id);
+        assert!(blocked_clients.is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+use super::scan_map::ScanMap;
+
+use bytes::Bytes;
+
+/// The value of a hash, whose fields can expire on their own, like in Redis 7.4.
+#[derive(Debug, Clone, Default)]
+pub struct HashValue {
+    fields: ScanMap<Bytes, HashField>,
+    /// No field expires before this time. It may be sooner than the soonest expiry, e.g. once the
+    /// field that had it is deleted, so it only tells when to look for expired fields.
+    next_expire_at_milli: Option<u128>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashField {
+    pub value: Bytes,
+    /// Unix time in milliseconds at which the field expires, if it does.
+    pub expire_at_milli: Option<u128>,
+}
+
+impl HashValue {
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, field: &Bytes) -> Option<&HashField> {
+        self.fields.get(field)
+    }
+
+    /// Sets the value of a field, removing its expiry like HSET does, and returns whether the
+    /// field is new.
+    pub fn insert(&mut self, field: Bytes, value: Bytes) -> bool {
+        self.fields
+            .insert(
+                field,
+                HashField {
+                    value,
+                    expire_at_milli: None,
+                },
+            )
+            .is_none()
+    }
+
+    /// Sets the value of an existing field, keeping its expiry, e.g. for HINCRBY.
+    pub fn set_value(&mut self, field: &Bytes, value: Bytes) {
+        if let Some(hash_field) = self.fields.get_mut(field) {
+            hash_field.value = value;
+        }
+    }
+
+    pub fn remove(&mut self, field: &Bytes) -> Option<HashField> {
+        self.fields.remove(field)
+    }
+
+    /// Sets or removes the expiry of an existing field.
+    pub fn set_expire_at(&mut self, field: &Bytes, expire_at_milli: Option<u128>) {
+        if let Some(hash_field) = self.fields.get_mut(field) {
+            hash_field.expire_at_milli = expire_at_milli;
+        }
+
+        if let Some(expire_at_milli) = expire_at_milli {
+            self.next_expire_at_milli = Some(
+                self.next_expire_at_milli
+                    .map_or(expire_at_milli, |next| next.min(expire_at_milli)),
+            );
+        }
+    }
+
+    /// Whether some field may expire, so that the active expiry cycle looks at the hash.
+    pub fn has_expiring_fields(&self) -> bool {
+        self.next_expire_at_milli.is_some()
+    }
+
+    /// Removes the fields that expired at `now`, and returns how many there were. <br/>
+    /// The fields are only walked once the soonest expiry is reached.
+    pub fn remove_expired_fields(&mut self, now: u128) -> usize {
+        if self.next_expire_at_milli.is_none_or(|next| next > now) {
+            return 0;
+        }
+
+        let expired_fields: Vec<Bytes> = self
+            .fields
+            .iter()
+            .filter(|(_, hash_field)| hash_field.expire_at_milli.is_some_and(|at| at <= now))
+            .map(|(field, _)| field.clone())
+            .collect();
+
+        for field in &expired_fields {
+            self.fields.remove(field);
+        }
+
+        self.next_expire_at_milli = self
+            .fields
+            .iter()
+            .filter_map(|(_, hash_field)| hash_field.expire_at_milli)
+            .min();
+
+        expired_fields.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &HashField)> {
+        self.fields.iter()
+    }
+
+    /// See [`ScanMap::scan`].
+    pub fn scan(
+        &self,
+        cursor: usize,
+        count: usize,
+        visit: impl FnMut(&Bytes, &HashField),
+    ) -> usize {
+        self.fields.scan(cursor, count, visit)
+    }
+
+    pub fn get_random(&self) -> Option<(&Bytes, &HashField)> {
+        self.fields.get_random()
+    }
+}
+
+impl PartialEq for HashValue {
+    /// Hashes are equal if they have the same fields, whatever slots they are in.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(field, hash_field)| other.get(field) == Some(hash_field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashValue;
+
+    use bytes::Bytes;
+
+    #[test]
+    fn remove_expired_fields_passes() {
+        let mut hash = HashValue::default();
+        let (a, b, c) = (Bytes::from("a"), Bytes::from("b"), Bytes::from("c"));
+
+        for field in [&a, &b, &c] {
+            assert!(hash.insert(field.clone(), Bytes::from("v")));
+        }
+        assert!(!hash.insert(c.clone(), Bytes::from("v2")));
+
+        hash.set_expire_at(&a, Some(100));
+        hash.set_expire_at(&b, Some(200));
+
+        assert_eq!(hash.remove_expired_fields(99), 0);
+        assert_eq!(hash.remove_expired_fields(100), 1);
+        assert_eq!(hash.get(&a), None);
+
+        // Setting a field again removes its expiry.
+        hash.insert(b.clone(), Bytes::from("v3"));
+        assert_eq!(hash.remove_expired_fields(300), 0);
+        assert_eq!(hash.len(), 2);
+        assert_eq!(hash.get(&b).unwrap().value, Bytes::from("v3"));
+    }
+}
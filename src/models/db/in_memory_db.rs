@@ -1,28 +1,209 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
 use anyhow::Error;
+use bytes::Bytes;
 use tokio::sync::Mutex;
 
-use super::{app_data::AppData, in_memory_record::InMemoryRecord};
+use super::{
+    app_data::AppData, blocked_clients::BlockedClients, in_memory_record::InMemoryRecord,
+    scan_map::ScanMap,
+};
+use crate::utils::unix_time_millis;
 
 pub(crate) const EMPTY_RDB_HEX_FILE: &[u8] = b"524544495330303131fa0972656469732d76657205372e322e30fa0a72656469732d62697473c040fa056374696d65c26d08bc65fa08757365642d6d656dc2b0c41000fa08616f662d62617365c000fff06e3bfec0ff5aa2";
 
 #[derive(Debug)]
 pub struct InMemoryDb {
-    records: HashMap<String, InMemoryRecord>,
+    /// The logical databases, selected by index with SELECT.
+    dbs: Vec<Keyspace>,
     app_data: AppData,
+    pub expiry_stats: ExpiryStats,
+    /// The clients waiting for lists to get elements. They are kept under the same lock as the
+    /// records, so that an element is handed over to them in the same step it is pushed.
+    pub blocked_clients: BlockedClients,
+}
+
+#[derive(Debug, Default)]
+struct Keyspace {
+    records: ScanMap<Bytes, InMemoryRecord>,
+    /// The slot the next active expiry step starts sampling from.
+    expire_cursor: usize,
+}
+
+/// Expiry stats, as replied in the "Stats" section of INFO.
+#[derive(Debug, Default)]
+pub struct ExpiryStats {
+    /// Keys removed because they expired, either when found by a command or by the active
+    /// expiry cycle.
+    pub expired_keys: u64,
+    /// Hash fields removed because they expired, like the `expired_subkeys` of Redis 7.4.
+    pub expired_subkeys: u64,
+    /// Running estimate of the percentage of keys with a TTL that have expired but are not
+    /// removed yet.
+    pub expired_stale_perc: f64,
+    /// Active expiry cycles stopped by their time limit rather than by finding few expired keys.
+    pub expired_time_cap_reached_count: u64,
+    /// Total time spent in active expiry cycles.
+    pub expire_cycle_cpu_milliseconds: u64,
+}
+
+/// The keys with a TTL checked by one step of the active expiry cycle.
+#[derive(Debug, Default, PartialEq)]
+pub struct ExpireSample {
+    pub sampled: usize,
+    pub expired: usize,
 }
 
 impl InMemoryDb {
     pub fn new(app_data: AppData) -> Result<Arc<Mutex<Self>>, Error> {
         Ok(Arc::new(Mutex::new(InMemoryDb {
-            records: HashMap::<String, InMemoryRecord>::new(),
+            dbs: (0..app_data.databases)
+                .map(|_| Keyspace::default())
+                .collect(),
             app_data,
+            expiry_stats: ExpiryStats::default(),
+            blocked_clients: BlockedClients::default(),
         })))
     }
 
-    pub fn get_records_ref_mut(&mut self) -> &mut HashMap<String, InMemoryRecord> {
-        &mut self.records
+    pub fn get_db_count(&self) -> usize {
+        self.dbs.len()
+    }
+
+    /// `db_index` is expected to be below [`InMemoryDb::get_db_count`], as checked by SELECT.
+    pub fn get_records_ref(&self, db_index: usize) -> &ScanMap<Bytes, InMemoryRecord> {
+        &self.dbs[db_index].records
+    }
+
+    pub fn get_records_ref_mut(&mut self, db_index: usize) -> &mut ScanMap<Bytes, InMemoryRecord> {
+        &mut self.dbs[db_index].records
+    }
+
+    /// Returns the record of `key` if it has not expired. <br/>
+    /// Expired records are removed when they are found, so they are never returned.
+    pub fn get_live_record_mut(
+        &mut self,
+        db_index: usize,
+        key: &Bytes,
+    ) -> Result<Option<&mut InMemoryRecord>, Error> {
+        let records = &mut self.dbs[db_index].records;
+
+        let has_expired = match records.get(key) {
+            None => return Ok(None),
+            Some(record) => record.has_expired()?,
+        };
+
+        if has_expired {
+            records.remove(key);
+            self.expiry_stats.expired_keys += 1;
+
+            return Ok(None);
+        }
+
+        Ok(records.get_mut(key))
+    }
+
+    /// Removes the record of `key` and returns it, if it has not expired.
+    pub fn remove_live_record(
+        &mut self,
+        db_index: usize,
+        key: &Bytes,
+    ) -> Result<Option<InMemoryRecord>, Error> {
+        if self.get_live_record_mut(db_index, key)?.is_none() {
+            return Ok(None);
+        }
+
+        Ok(self.dbs[db_index].records.remove(key))
+    }
+
+    /// Removes the record of `key` if its list, hash, set or sorted set has no elements
+    /// left.
+    pub fn remove_if_empty(&mut self, db_index: usize, key: &Bytes) {
+        let records = &mut self.dbs[db_index].records;
+
+        if records
+            .get(key)
+            .is_some_and(InMemoryRecord::has_no_elements)
+        {
+            records.remove(key);
+        }
+    }
+
+    /// Empties a database and returns its records, e.g. to drop them on a background task.
+    pub fn take_records(&mut self, db_index: usize) -> ScanMap<Bytes, InMemoryRecord> {
+        std::mem::take(&mut self.dbs[db_index]).records
+    }
+
+    /// Swaps the records of two databases, so that clients that selected one see the other's.
+    pub fn swap_dbs(&mut self, first_db_index: usize, second_db_index: usize) {
+        self.dbs.swap(first_db_index, second_db_index);
+    }
+
+    /// Walks the records of a database from where the previous call stopped, until `max_keys`
+    /// keys with a TTL or `max_slots` slots were checked, and removes the expired ones. <br/>
+    /// Hashes with fields that may expire are checked like keys with a TTL, and lose their expired
+    /// fields, so that fields of a hash that is never read again are removed too. <br/>
+    /// Bounding the slots keeps a step short when only few keys have a TTL.
+    pub fn expire_sample(
+        &mut self,
+        db_index: usize,
+        max_keys: usize,
+        max_slots: usize,
+    ) -> Result<ExpireSample, Error> {
+        let now = unix_time_millis()?;
+        let keyspace = &mut self.dbs[db_index];
+        let mut sample = ExpireSample::default();
+
+        for _ in 0..max_slots.min(keyspace.records.slot_count()) {
+            if sample.sampled == max_keys {
+                break;
+            }
+
+            if keyspace.expire_cursor >= keyspace.records.slot_count() {
+                keyspace.expire_cursor = 0;
+            }
+
+            let slot = keyspace.expire_cursor;
+            keyspace.expire_cursor += 1;
+
+            let (key, expire_at_milli) = match keyspace.records.get_slot(slot) {
+                Some((key, record))
+                    if record.expire_at_milli.is_some() || record.has_expiring_fields() =>
+                {
+                    (key.clone(), record.expire_at_milli)
+                }
+                _ => continue,
+            };
+            sample.sampled += 1;
+
+            if expire_at_milli.is_some_and(|expire_at_milli| expire_at_milli <= now) {
+                keyspace.records.remove(&key);
+                self.expiry_stats.expired_keys += 1;
+                sample.expired += 1;
+
+                continue;
+            }
+
+            let (expired_field_count, has_no_fields) = match keyspace.records.get_mut(&key) {
+                Some(record) if record.has_expiring_fields() => {
+                    let hash = record.get_hash_mut()?;
+
+                    (hash.remove_expired_fields(now), hash.is_empty())
+                }
+                _ => (0, false),
+            };
+
+            if expired_field_count > 0 {
+                self.expiry_stats.expired_subkeys += expired_field_count as u64;
+                sample.expired += 1;
+            }
+            // Like in Redis, the key is deleted with its last field.
+            if has_no_fields {
+                keyspace.records.remove(&key);
+            }
+        }
+
+        Ok(sample)
     }
 
     pub fn get_app_data_ref(&self) -> &AppData {
@@ -1,48 +1,217 @@
-use std::time::SystemTime;
+use super::{hash_value::HashValue, set_value::SetValue, sorted_set_value::SortedSetValue};
+use crate::{
+    models::command_error::CommandError,
+    utils::{parse_strict_i64, unix_time_millis},
+};
+
+use std::collections::VecDeque;
 
 use anyhow::Error;
+use bytes::Bytes;
+
+/// The longest string Redis stores in a single allocation with its header, which OBJECT ENCODING
+/// replies as "embstr".
+const EMBSTR_MAX_LENGTH: usize = 44;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InMemoryRecord {
-    pub value: String,
-    pub last_update_time: SystemTime,
-    pub expire_milli: Option<u128>,
+    pub value: RecordValue,
+    /// Unix time in milliseconds at which the record expires, if it does. <br/>
+    /// It is absolute rather than relative to when it was set, so that it can be replied by
+    /// EXPIRETIME and sent to replicas as it is, without drifting.
+    pub expire_at_milli: Option<u128>,
+}
+
+/// The value of a record, of one of the types TYPE replies.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordValue {
+    String(Bytes),
+    /// A deque, so that pushing and popping at both ends is O(1).
+    List(VecDeque<Bytes>),
+    Hash(HashValue),
+    Set(SetValue),
+    SortedSet(SortedSetValue),
+}
+
+/// The end of a list that elements are pushed to or popped from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl ListEnd {
+    pub fn push(&self, list: &mut VecDeque<Bytes>, element: Bytes) {
+        match self {
+            ListEnd::Left => list.push_front(element),
+            ListEnd::Right => list.push_back(element),
+        }
+    }
+
+    pub fn pop(&self, list: &mut VecDeque<Bytes>) -> Option<Bytes> {
+        match self {
+            ListEnd::Left => list.pop_front(),
+            ListEnd::Right => list.pop_back(),
+        }
+    }
+}
+
+impl From<Bytes> for RecordValue {
+    fn from(value: Bytes) -> Self {
+        RecordValue::String(value)
+    }
 }
 
 impl InMemoryRecord {
-    pub fn new(value: String, expire_milli: Option<u128>) -> Self {
+    pub fn new(value: impl Into<RecordValue>, expire_at_milli: Option<u128>) -> Self {
         InMemoryRecord {
-            value,
-            // No need for UTC. This is just an internal date.
-            last_update_time: SystemTime::now(),
-            expire_milli,
+            value: value.into(),
+            expire_at_milli,
         }
     }
 
     pub fn has_expired(&self) -> Result<bool, Error> {
-        Ok(self.expire_milli.is_some()
-            && SystemTime::now()
-                .duration_since(self.last_update_time)?
-                .as_millis()
-                > self.expire_milli.unwrap())
+        match self.expire_at_milli {
+            Some(expire_at_milli) => Ok(expire_at_milli <= unix_time_millis()?),
+            None => Ok(false),
+        }
+    }
+
+    /// Whether the value is a hash with fields that may expire.
+    pub fn has_expiring_fields(&self) -> bool {
+        matches!(&self.value, RecordValue::Hash(hash) if hash.has_expiring_fields())
+    }
+
+    /// The value, if it is a string, or a WRONGTYPE error otherwise.
+    pub fn get_string_ref(&self) -> Result<&Bytes, Error> {
+        match &self.value {
+            RecordValue::String(value) => Ok(value),
+            _ => Err(CommandError::WrongType.into()),
+        }
+    }
+
+    /// The value, if it is a list, or a WRONGTYPE error otherwise.
+    pub fn get_list_mut(&mut self) -> Result<&mut VecDeque<Bytes>, Error> {
+        match &mut self.value {
+            RecordValue::List(list) => Ok(list),
+            _ => Err(CommandError::WrongType.into()),
+        }
+    }
+
+    /// The value, if it is a hash, or a WRONGTYPE error otherwise.
+    pub fn get_hash_mut(&mut self) -> Result<&mut HashValue, Error> {
+        match &mut self.value {
+            RecordValue::Hash(hash) => Ok(hash),
+            _ => Err(CommandError::WrongType.into()),
+        }
+    }
+
+    /// The value, if it is a set, or a WRONGTYPE error otherwise.
+    pub fn get_set_ref(&self) -> Result<&SetValue, Error> {
+        match &self.value {
+            RecordValue::Set(set) => Ok(set),
+            _ => Err(CommandError::WrongType.into()),
+        }
+    }
+
+    /// The value, if it is a set, or a WRONGTYPE error otherwise.
+    pub fn get_set_mut(&mut self) -> Result<&mut SetValue, Error> {
+        match &mut self.value {
+            RecordValue::Set(set) => Ok(set),
+            _ => Err(CommandError::WrongType.into()),
+        }
+    }
+
+    /// The value, if it is a sorted set, or a WRONGTYPE error otherwise.
+    pub fn get_sorted_set_mut(&mut self) -> Result<&mut SortedSetValue, Error> {
+        match &mut self.value {
+            RecordValue::SortedSet(sorted_set) => Ok(sorted_set),
+            _ => Err(CommandError::WrongType.into()),
+        }
+    }
+
+    /// Whether the value is a list, a hash, a set or a sorted set without elements, which like in
+    /// Redis is never kept: the key is deleted with its last element.
+    pub fn has_no_elements(&self) -> bool {
+        match &self.value {
+            RecordValue::String(_) => false,
+            RecordValue::List(list) => list.is_empty(),
+            RecordValue::Hash(hash) => hash.is_empty(),
+            RecordValue::Set(set) => set.is_empty(),
+            RecordValue::SortedSet(sorted_set) => sorted_set.is_empty(),
+        }
+    }
+
+    /// The type of the value, as replied by TYPE.
+    pub fn get_type_name(&self) -> &'static str {
+        match self.value {
+            RecordValue::String(_) => "string",
+            RecordValue::List(_) => "list",
+            RecordValue::Hash(_) => "hash",
+            RecordValue::Set(_) => "set",
+            RecordValue::SortedSet(_) => "zset",
+        }
+    }
+
+    /// The encoding of the value, as replied by OBJECT ENCODING. <br/>
+    /// Strings are named like in Redis, after the length and content of the value. Lists, hashes
+    /// and sorted sets don't have the compact encodings of small values, so they are named after
+    /// the one Redis uses for large values.
+    pub fn get_encoding_name(&self) -> &'static str {
+        match &self.value {
+            RecordValue::String(value) if parse_strict_i64(value).is_some() => "int",
+            RecordValue::String(value) if value.len() <= EMBSTR_MAX_LENGTH => "embstr",
+            RecordValue::String(_) => "raw",
+            RecordValue::List(_) => "quicklist",
+            RecordValue::Hash(_) => "hashtable",
+            RecordValue::Set(set) => set.get_encoding_name(),
+            RecordValue::SortedSet(_) => "skiplist",
+        }
+    }
+
+    /// Roughly how many allocations dropping the value frees, which tells whether it is worth
+    /// dropping it on a background task, like Redis does for UNLINK.
+    pub fn get_free_effort(&self) -> usize {
+        match &self.value {
+            RecordValue::String(_) => 1,
+            RecordValue::List(list) => list.len(),
+            RecordValue::Hash(hash) => hash.len(),
+            RecordValue::Set(set) => set.get_free_effort(),
+            RecordValue::SortedSet(sorted_set) => sorted_set.len(),
+        }
+    }
+
+    /// Milliseconds until the record expires, or `None` if it never does.
+    pub fn get_ttl_milli(&self) -> Result<Option<u128>, Error> {
+        let now = unix_time_millis()?;
+
+        Ok(self
+            .expire_at_milli
+            .map(|expire_at_milli| expire_at_milli.saturating_sub(now)))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::InMemoryRecord;
+    use crate::utils::unix_time_millis;
 
-    use std::{thread, time::Duration};
+    use bytes::Bytes;
 
     #[test]
     fn has_expired_passes() -> Result<(), anyhow::Error> {
-        let expires = InMemoryRecord::new("".to_owned(), Some(1));
-        let does_not_expire = InMemoryRecord::new("".to_owned(), Some(3));
+        let now = unix_time_millis()?;
+
+        let expired = InMemoryRecord::new(Bytes::new(), Some(now - 1));
+        let does_not_expire_yet = InMemoryRecord::new(Bytes::new(), Some(now + 60_000));
+        let never_expires = InMemoryRecord::new(Bytes::new(), None);
 
-        thread::sleep(Duration::from_millis(2));
+        assert!(expired.has_expired()?);
+        assert!(!does_not_expire_yet.has_expired()?);
+        assert!(!never_expires.has_expired()?);
 
-        assert_eq!(expires.has_expired()?, true);
-        assert_eq!(does_not_expire.has_expired()?, false);
+        assert!(does_not_expire_yet.get_ttl_milli()?.unwrap() <= 60_000);
+        assert_eq!(never_expires.get_ttl_milli()?, None);
 
         Ok(())
     }
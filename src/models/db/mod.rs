@@ -1,3 +1,9 @@
 pub mod app_data;
+pub mod blocked_clients;
+pub mod hash_value;
 pub mod in_memory_db;
 pub mod in_memory_record;
+pub mod scan_map;
+pub mod set_value;
+pub mod skip_list;
+pub mod sorted_set_value;
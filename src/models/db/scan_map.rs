@@ -0,0 +1,223 @@
+use crate::utils::random_below;
+
+use std::{collections::HashMap, hash::Hash};
+
+/// Slots tried at random by [`ScanMap::get_random`] before it walks the entries instead.
+const RANDOM_SLOT_ATTEMPTS: usize = 16;
+
+/// A map whose entries keep the slot they were inserted in until they are removed, so that they
+/// can be walked with a cursor that stays valid while the map changes between two steps. <br/>
+/// Freed slots are reused by later inserts, so the map does not grow past its peak size.
+#[derive(Debug, Clone)]
+pub struct ScanMap<K, V> {
+    slots: Vec<Option<(K, V)>>,
+    index: HashMap<K, usize>,
+    free_slots: Vec<usize>,
+}
+
+impl<K: Hash + Eq + Clone, V> ScanMap<K, V> {
+    pub fn new() -> Self {
+        ScanMap {
+            slots: Vec::new(),
+            index: HashMap::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let slot = *self.index.get(key)?;
+
+        self.slots[slot].as_ref().map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let slot = *self.index.get(key)?;
+
+        self.slots[slot].as_mut().map(|(_, value)| value)
+    }
+
+    /// Inserts `value`, keeping the slot of `key` if it is already in the map, and returns the
+    /// value it replaced.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(slot) = self.index.get(&key) {
+            return self.slots[*slot]
+                .replace((key, value))
+                .map(|(_, old_value)| old_value);
+        }
+
+        let slot = match self.free_slots.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some((key.clone(), value));
+                slot
+            }
+            None => {
+                self.slots.push(Some((key.clone(), value)));
+                self.slots.len() - 1
+            }
+        };
+        self.index.insert(key, slot);
+
+        None
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let slot = self.index.remove(key)?;
+        let (_, value) = self.slots[slot].take()?;
+
+        if slot == self.slots.len() - 1 {
+            self.slots.pop();
+            self.trim_free_slots();
+        } else {
+            self.free_slots.push(slot);
+        }
+
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.as_ref().map(|(key, value)| (key, value)))
+    }
+
+    /// The number of slots, including free ones, i.e. the bound of a cursor.
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The entry in `slot`, if the slot is in use.
+    pub fn get_slot(&self, slot: usize) -> Option<(&K, &V)> {
+        self.slots
+            .get(slot)?
+            .as_ref()
+            .map(|(key, value)| (key, value))
+    }
+
+    /// A random entry, e.g. for HRANDFIELD. <br/>
+    /// Slots are picked at random until one is in use, which is uniform over the entries, and
+    /// the entries are walked instead when most slots are free.
+    pub fn get_random(&self) -> Option<(&K, &V)> {
+        if self.index.is_empty() {
+            return None;
+        }
+
+        for _ in 0..RANDOM_SLOT_ATTEMPTS {
+            if let Some(entry) = self.get_slot(random_below(self.slots.len())) {
+                return Some(entry);
+            }
+        }
+
+        self.iter().nth(random_below(self.len()))
+    }
+
+    /// Calls `visit` with the entries from slot `cursor` on, until `count` entries or `count * 10`
+    /// slots were visited, and returns the cursor to continue from, or 0 once every slot was
+    /// visited. <br/>
+    /// Since entries keep their slot, every entry in the map for the whole scan is visited once,
+    /// however the map changes in between calls.
+    pub fn scan(&self, cursor: usize, count: usize, mut visit: impl FnMut(&K, &V)) -> usize {
+        let mut slot = cursor;
+        let mut visited_entries = 0;
+        let max_slot = cursor.saturating_add(count.saturating_mul(10));
+
+        while slot < self.slots.len() && slot < max_slot && visited_entries < count {
+            if let Some((key, value)) = &self.slots[slot] {
+                visit(key, value);
+                visited_entries += 1;
+            }
+
+            slot += 1;
+        }
+
+        if slot >= self.slots.len() {
+            0
+        } else {
+            slot
+        }
+    }
+
+    /// Drops the trailing free slots, so that cursors do not walk over them.
+    fn trim_free_slots(&mut self) {
+        while let Some(None) = self.slots.last() {
+            self.slots.pop();
+        }
+
+        let slot_count = self.slots.len();
+        self.free_slots.retain(|slot| *slot < slot_count);
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Default for ScanMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScanMap;
+
+    #[test]
+    fn scan_map_keeps_slots_until_removed() {
+        let mut scan_map = ScanMap::new();
+
+        for key in ["a", "b", "c", "d"] {
+            assert_eq!(scan_map.insert(key, key.len()), None);
+        }
+        assert_eq!(scan_map.insert("b", 20), Some(1));
+        assert_eq!(scan_map.get_slot(1), Some((&"b", &20)));
+
+        assert_eq!(scan_map.remove(&"a"), Some(1));
+        assert_eq!(scan_map.remove(&"a"), None);
+        assert_eq!(scan_map.get_slot(0), None);
+        assert_eq!(scan_map.get_slot(3), Some((&"d", &1)));
+
+        // The freed slot is reused instead of growing the map.
+        scan_map.insert("e", 1);
+        assert_eq!(scan_map.get_slot(0), Some((&"e", &1)));
+        assert_eq!(scan_map.slot_count(), 4);
+
+        // Trailing free slots are dropped.
+        scan_map.remove(&"c");
+        scan_map.remove(&"d");
+        assert_eq!(scan_map.slot_count(), 2);
+        scan_map.insert("f", 1);
+        assert_eq!(scan_map.slot_count(), 3);
+
+        assert_eq!(scan_map.len(), 3);
+        assert_eq!(
+            scan_map.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            vec!["e", "b", "f"]
+        );
+    }
+
+    #[test]
+    fn scan_visits_entries_present_for_the_whole_scan_once() {
+        let mut scan_map = ScanMap::new();
+        for key in 0..100 {
+            scan_map.insert(key, ());
+        }
+
+        let mut visited = Vec::new();
+        let mut cursor = scan_map.scan(0, 7, |key, _| visited.push(*key));
+
+        while cursor != 0 {
+            // Changes in between calls: removes some keys and adds others in the freed slots.
+            for key in [cursor as i32 - 1, cursor as i32 + 3] {
+                if key % 2 == 1 && scan_map.remove(&key).is_some() {
+                    scan_map.insert(key + 1000, ());
+                }
+            }
+
+            cursor = scan_map.scan(cursor, 7, |key, _| visited.push(*key));
+        }
+
+        for key in (0..100).filter(|key| key % 2 == 0) {
+            assert_eq!(visited.iter().filter(|visited| **visited == key).count(), 1);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use super::scan_map::ScanMap;
+use crate::utils::{parse_strict_i64, random_below};
+
+use bytes::Bytes;
+
+/// The most members a set keeps as an intset, like the default `set-max-intset-entries` of Redis.
+pub const SET_MAX_INTSET_ENTRIES: usize = 512;
+
+/// The members of a set, in one of the two encodings OBJECT ENCODING replies, like in Redis: <br/>
+/// - `IntSet` while all the members are integers and there are at most
+///   [`SET_MAX_INTSET_ENTRIES`] of them: a sorted vector of `i64`, which takes a fraction of the
+///   memory of a hashtable and is searched with a binary search. <br/>
+/// - `HashTable` otherwise. A set never goes back to being an intset.
+#[derive(Debug, Clone)]
+pub enum SetValue {
+    IntSet(Vec<i64>),
+    HashTable(ScanMap<Bytes, ()>),
+}
+
+impl Default for SetValue {
+    fn default() -> Self {
+        SetValue::IntSet(Vec::new())
+    }
+}
+
+impl SetValue {
+    pub fn len(&self) -> usize {
+        match self {
+            SetValue::IntSet(integers) => integers.len(),
+            SetValue::HashTable(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, member: &Bytes) -> bool {
+        match self {
+            SetValue::IntSet(integers) => parse_strict_i64(member)
+                .is_some_and(|integer| integers.binary_search(&integer).is_ok()),
+            SetValue::HashTable(members) => members.get(member).is_some(),
+        }
+    }
+
+    /// Adds a member, and returns whether it is new. <br/>
+    /// An intset becomes a hashtable once a member is not an integer, or once it is full.
+    pub fn insert(&mut self, member: Bytes) -> bool {
+        if let SetValue::IntSet(integers) = self {
+            if let Some(integer) = parse_strict_i64(&member) {
+                match integers.binary_search(&integer) {
+                    Ok(_) => return false,
+                    Err(index) if integers.len() < SET_MAX_INTSET_ENTRIES => {
+                        integers.insert(index, integer);
+
+                        return true;
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+
+        self.get_hash_table_mut().insert(member, ()).is_none()
+    }
+
+    /// Removes a member, and returns whether it was in the set.
+    pub fn remove(&mut self, member: &Bytes) -> bool {
+        match self {
+            SetValue::IntSet(integers) => {
+                match parse_strict_i64(member).map(|integer| integers.binary_search(&integer)) {
+                    Some(Ok(index)) => {
+                        integers.remove(index);
+
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            SetValue::HashTable(members) => members.remove(member).is_some(),
+        }
+    }
+
+    /// The members, which are in ascending order for an intset.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = Bytes> + '_> {
+        match self {
+            SetValue::IntSet(integers) => Box::new(
+                integers
+                    .iter()
+                    .map(|integer| Bytes::from(integer.to_string())),
+            ),
+            SetValue::HashTable(members) => {
+                Box::new(members.iter().map(|(member, _)| member.clone()))
+            }
+        }
+    }
+
+    /// See [`ScanMap::scan`]. <br/>
+    /// Like in Redis, an intset is small enough to be walked in a single step, so its cursor is
+    /// ignored and 0 is always returned.
+    pub fn scan(&self, cursor: usize, count: usize, mut visit: impl FnMut(&Bytes)) -> usize {
+        match self {
+            SetValue::IntSet(_) => {
+                self.iter().for_each(|member| visit(&member));
+
+                0
+            }
+            SetValue::HashTable(members) => members.scan(cursor, count, |member, _| visit(member)),
+        }
+    }
+
+    pub fn get_random(&self) -> Option<Bytes> {
+        match self {
+            SetValue::IntSet(integers) if integers.is_empty() => None,
+            SetValue::IntSet(integers) => Some(Bytes::from(
+                integers[random_below(integers.len())].to_string(),
+            )),
+            SetValue::HashTable(members) => members.get_random().map(|(member, _)| member.clone()),
+        }
+    }
+
+    /// Removes a random member and returns it, e.g. for SPOP.
+    pub fn pop_random(&mut self) -> Option<Bytes> {
+        let member = self.get_random()?;
+        self.remove(&member);
+
+        Some(member)
+    }
+
+    /// The encoding of the set, as replied by OBJECT ENCODING.
+    pub fn get_encoding_name(&self) -> &'static str {
+        match self {
+            SetValue::IntSet(_) => "intset",
+            SetValue::HashTable(_) => "hashtable",
+        }
+    }
+
+    /// Roughly how many allocations dropping the set frees, see
+    /// [`super::in_memory_record::InMemoryRecord::get_free_effort`].
+    pub fn get_free_effort(&self) -> usize {
+        match self {
+            SetValue::IntSet(_) => 1,
+            SetValue::HashTable(members) => members.len(),
+        }
+    }
+
+    /// The members as a hashtable, into which an intset is converted first.
+    fn get_hash_table_mut(&mut self) -> &mut ScanMap<Bytes, ()> {
+        if let SetValue::IntSet(integers) = self {
+            let mut members = ScanMap::new();
+            for integer in integers.iter() {
+                members.insert(Bytes::from(integer.to_string()), ());
+            }
+
+            *self = SetValue::HashTable(members);
+        }
+
+        match self {
+            SetValue::HashTable(members) => members,
+            SetValue::IntSet(_) => unreachable!("the intset was just converted"),
+        }
+    }
+}
+
+impl FromIterator<Bytes> for SetValue {
+    /// Builds a set in the most compact encoding its members fit in, e.g. for SINTERSTORE.
+    fn from_iter<I: IntoIterator<Item = Bytes>>(members: I) -> Self {
+        let mut set = SetValue::default();
+        for member in members {
+            set.insert(member);
+        }
+
+        set
+    }
+}
+
+impl PartialEq for SetValue {
+    /// Sets are equal if they have the same members, whatever their encoding.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|member| other.contains(&member))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SetValue, SET_MAX_INTSET_ENTRIES};
+
+    use bytes::Bytes;
+
+    #[test]
+    fn set_encoding_passes() {
+        let mut set = SetValue::default();
+
+        for member in ["3", "-1", "2", "3"] {
+            set.insert(Bytes::from(member));
+        }
+        assert_eq!(set.get_encoding_name(), "intset");
+        assert_eq!(set.iter().collect::<Vec<_>>(), ["-1", "2", "3"]);
+
+        // Only canonical integers are kept in an intset, so that members are replied as added.
+        assert!(!set.contains(&Bytes::from("03")));
+        assert!(set.insert(Bytes::from("03")));
+        assert_eq!(set.get_encoding_name(), "hashtable");
+        assert_eq!(set.len(), 4);
+        assert!(set.remove(&Bytes::from("-1")));
+        assert!(set.contains(&Bytes::from("2")));
+        assert!(!set.contains(&Bytes::from("-1")));
+
+        let full_set: SetValue = (0..=SET_MAX_INTSET_ENTRIES)
+            .map(|integer| Bytes::from(integer.to_string()))
+            .collect();
+        assert_eq!(full_set.get_encoding_name(), "hashtable");
+
+        let int_set: SetValue = (0..SET_MAX_INTSET_ENTRIES)
+            .rev()
+            .map(|integer| Bytes::from(integer.to_string()))
+            .collect();
+        assert_eq!(int_set.get_encoding_name(), "intset");
+        assert_ne!(int_set, full_set);
+    }
+}
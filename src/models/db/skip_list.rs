@@ -0,0 +1,417 @@
+use crate::utils::random_below;
+
+use std::ops::Range;
+
+/// The most levels a node has, like `ZSKIPLIST_MAXLEVEL` in Redis.
+const MAX_LEVEL: usize = 32;
+
+/// The head node, which holds no item and starts every level.
+const HEAD: usize = 0;
+
+/// An ordered list of distinct items, like the skiplist Redis keeps sorted sets in. <br/>
+/// Every link of a node counts how many items it skips over (its span), so that the position of
+/// an item, or the item at a position, is found in logarithmic time like an item is. <br/>
+/// The nodes are kept in a vector, and freed ones are reused by later inserts like in
+/// [`super::scan_map::ScanMap`].
+#[derive(Debug, Clone)]
+pub struct SkipList<T> {
+    nodes: Vec<Node<T>>,
+    free_nodes: Vec<usize>,
+    tail: Option<usize>,
+    len: usize,
+    /// How many levels are in use, which is at least 1.
+    level: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    /// `None` for the head and for freed nodes.
+    item: Option<T>,
+    levels: Vec<Link>,
+    /// The previous node, or `None` for the first one.
+    backward: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Link {
+    forward: Option<usize>,
+    span: usize,
+}
+
+impl<T: Ord + Clone> SkipList<T> {
+    pub fn new() -> Self {
+        SkipList {
+            nodes: vec![Node {
+                item: None,
+                levels: vec![Link::default(); MAX_LEVEL],
+                backward: None,
+            }],
+            free_nodes: Vec::new(),
+            tail: None,
+            len: 0,
+            level: 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Inserts an item, which must not be in the list yet.
+    pub fn insert(&mut self, item: T) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            rank[level] = if level == self.level - 1 {
+                0
+            } else {
+                rank[level + 1]
+            };
+
+            while let Some(next) = self.nodes[node].levels[level].forward {
+                if self.get_item(next) >= &item {
+                    break;
+                }
+
+                rank[level] += self.nodes[node].levels[level].span;
+                node = next;
+            }
+            update[level] = node;
+        }
+
+        let new_level = random_level();
+        if new_level > self.level {
+            for level in self.level..new_level {
+                self.nodes[HEAD].levels[level].span = self.len;
+            }
+            self.level = new_level;
+        }
+
+        let new_node = self.allocate_node(item, new_level);
+        for level in 0..new_level {
+            let previous_link = self.nodes[update[level]].levels[level];
+            let skipped = rank[0] - rank[level];
+
+            self.nodes[new_node].levels[level] = Link {
+                forward: previous_link.forward,
+                span: previous_link.span - skipped,
+            };
+            self.nodes[update[level]].levels[level] = Link {
+                forward: Some(new_node),
+                span: skipped + 1,
+            };
+        }
+        // The links above the new node now skip over it too.
+        for (level, previous) in update.iter().enumerate().take(self.level).skip(new_level) {
+            self.nodes[*previous].levels[level].span += 1;
+        }
+
+        self.nodes[new_node].backward = (update[0] != HEAD).then_some(update[0]);
+        match self.nodes[new_node].levels[0].forward {
+            Some(next) => self.nodes[next].backward = Some(new_node),
+            None => self.tail = Some(new_node),
+        }
+
+        self.len += 1;
+    }
+
+    /// Removes an item, and returns whether it was in the list.
+    pub fn remove(&mut self, item: &T) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.nodes[node].levels[level].forward {
+                if self.get_item(next) >= item {
+                    break;
+                }
+
+                node = next;
+            }
+            update[level] = node;
+        }
+
+        match self.nodes[node].levels[0].forward {
+            Some(next) if self.get_item(next) == item => {
+                self.remove_node(next, &update);
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The position of an item in the list, if it is in it.
+    pub fn get_rank(&self, item: &T) -> Option<usize> {
+        let mut rank = 0;
+
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.nodes[node].levels[level].forward {
+                if self.get_item(next) > item {
+                    break;
+                }
+
+                rank += self.nodes[node].levels[level].span;
+                node = next;
+            }
+
+            if node != HEAD && self.get_item(node) == item {
+                return Some(rank - 1);
+            }
+        }
+
+        None
+    }
+
+    /// How many items there are before the first one for which `is_before` is false, like
+    /// [`slice::partition_point`]. `is_before` must hold for the first items only.
+    pub fn partition_point(&self, is_before: impl Fn(&T) -> bool) -> usize {
+        let mut rank = 0;
+
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.nodes[node].levels[level].forward {
+                if !is_before(self.get_item(next)) {
+                    break;
+                }
+
+                rank += self.nodes[node].levels[level].span;
+                node = next;
+            }
+        }
+
+        rank
+    }
+
+    /// The items from the lowest one.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.range(0..self.len)
+    }
+
+    /// The items whose position is in `ranks`, which may go past the end of the list.
+    pub fn range(&self, ranks: Range<usize>) -> Iter<'_, T> {
+        let end = ranks.end.min(self.len);
+        if ranks.start >= end {
+            return Iter {
+                list: self,
+                front: None,
+                back: None,
+                remaining: 0,
+            };
+        }
+
+        Iter {
+            list: self,
+            front: self.get_node_by_rank(ranks.start),
+            back: self.get_node_by_rank(end - 1),
+            remaining: end - ranks.start,
+        }
+    }
+
+    /// Removes the lowest item, or the highest one if `highest`, and returns it.
+    pub fn pop(&mut self, highest: bool) -> Option<T> {
+        let node = if highest {
+            self.tail?
+        } else {
+            self.nodes[HEAD].levels[0].forward?
+        };
+
+        let item = self.get_item(node).clone();
+        self.remove(&item);
+
+        Some(item)
+    }
+
+    fn get_item(&self, node: usize) -> &T {
+        self.nodes[node]
+            .item
+            .as_ref()
+            .expect("only the head and freed nodes have no item")
+    }
+
+    fn get_node_by_rank(&self, rank: usize) -> Option<usize> {
+        // Ranks count from 1 here, since the head is at 0.
+        let rank = rank + 1;
+        let mut traversed = 0;
+
+        let mut node = HEAD;
+        for level in (0..self.level).rev() {
+            while let Some(next) = self.nodes[node].levels[level].forward {
+                let span = self.nodes[node].levels[level].span;
+                if traversed + span > rank {
+                    break;
+                }
+
+                traversed += span;
+                node = next;
+            }
+
+            if traversed == rank {
+                return Some(node);
+            }
+        }
+
+        None
+    }
+
+    fn allocate_node(&mut self, item: T, level: usize) -> usize {
+        let node = Node {
+            item: Some(item),
+            levels: vec![Link::default(); level],
+            backward: None,
+        };
+
+        match self.free_nodes.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Unlinks `node`, where `update` holds the last node before it on every level.
+    fn remove_node(&mut self, node: usize, update: &[usize; MAX_LEVEL]) {
+        for (level, &previous) in update.iter().enumerate().take(self.level) {
+            if self.nodes[previous].levels[level].forward == Some(node) {
+                let removed_link = self.nodes[node].levels[level];
+
+                self.nodes[previous].levels[level] = Link {
+                    forward: removed_link.forward,
+                    span: self.nodes[previous].levels[level].span + removed_link.span - 1,
+                };
+            } else {
+                self.nodes[previous].levels[level].span -= 1;
+            }
+        }
+
+        let backward = self.nodes[node].backward;
+        match self.nodes[node].levels[0].forward {
+            Some(next) => self.nodes[next].backward = backward,
+            None => self.tail = backward,
+        }
+
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+
+        self.nodes[node] = Node {
+            item: None,
+            levels: Vec::new(),
+            backward: None,
+        };
+        if node == self.nodes.len() - 1 {
+            self.nodes.pop();
+        } else {
+            self.free_nodes.push(node);
+        }
+        self.len -= 1;
+    }
+}
+
+impl<T: Ord + Clone> Default for SkipList<T> {
+    fn default() -> Self {
+        SkipList::new()
+    }
+}
+
+/// Like in Redis, each level is kept by a quarter of the nodes of the level below.
+fn random_level() -> usize {
+    let mut level = 1;
+    while level < MAX_LEVEL && random_below(4) == 0 {
+        level += 1;
+    }
+
+    level
+}
+
+/// Walks a range of a [`SkipList`] from either end.
+pub struct Iter<'a, T> {
+    list: &'a SkipList<T>,
+    front: Option<usize>,
+    back: Option<usize>,
+    remaining: usize,
+}
+
+impl<'a, T: Ord + Clone> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let node = self.front?;
+        self.front = self.list.nodes[node].levels[0].forward;
+        self.remaining -= 1;
+
+        Some(self.list.get_item(node))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T: Ord + Clone> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let node = self.back?;
+        self.back = self.list.nodes[node].backward;
+        self.remaining -= 1;
+
+        Some(self.list.get_item(node))
+    }
+}
+
+impl<T: Ord + Clone> ExactSizeIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipList;
+
+    #[test]
+    fn skip_list_ranks_pass() {
+        let mut list = SkipList::new();
+
+        // Enough items for several levels, inserted out of order.
+        for item in (0..1000).map(|item| item * 7 % 1000) {
+            list.insert(item);
+        }
+        for item in (0..1000).filter(|item| item % 3 == 0) {
+            assert!(list.remove(&item));
+        }
+        assert!(!list.remove(&3));
+
+        let items: Vec<i32> = (0..1000).filter(|item| item % 3 != 0).collect();
+        assert_eq!(list.len(), items.len());
+        assert!(list.iter().eq(items.iter()));
+        assert!(list.iter().rev().eq(items.iter().rev()));
+
+        for (rank, item) in items.iter().enumerate() {
+            assert_eq!(list.get_rank(item), Some(rank));
+        }
+        assert_eq!(list.get_rank(&999), None);
+
+        assert!(list.range(10..20).eq(items[10..20].iter()));
+        assert!(list.range(10..20).rev().eq(items[10..20].iter().rev()));
+        assert_eq!(list.range(660..700).len(), 6);
+        assert_eq!(list.range(700..800).len(), 0);
+
+        assert_eq!(list.partition_point(|item| *item < 500), 333);
+        assert_eq!(list.partition_point(|_| true), items.len());
+
+        assert_eq!(list.pop(false), Some(1));
+        assert_eq!(list.pop(true), Some(998));
+        assert_eq!(list.get_rank(&2), Some(0));
+    }
+}
@@ -0,0 +1,281 @@
+use super::skip_list::SkipList;
+
+use std::{cmp::Ordering, collections::HashMap, ops::Range};
+
+use bytes::Bytes;
+
+/// The value of a sorted set: its members ordered by score, then lexicographically like in Redis.
+/// <br/>
+/// Like in Redis, the members are kept both in a map to their score, to find a score in constant
+/// time, and in a skiplist ordered by score, to find a rank or the start of a range in logarithmic
+/// time.
+#[derive(Debug, Clone, Default)]
+pub struct SortedSetValue {
+    scores: HashMap<Bytes, f64>,
+    ordered: SkipList<(Score, Bytes)>,
+}
+
+/// A score with a total order, which it has since scores are never NaN. <br/>
+/// Like in Redis, -0 and 0 are the same score.
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).expect("scores are never NaN")
+    }
+}
+
+/// The end of a range of scores, e.g. "(1.5" for an exclusive minimum of 1.5.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreBound {
+    Inclusive(f64),
+    Exclusive(f64),
+}
+
+impl ScoreBound {
+    /// Whether `score` is not below this minimum.
+    fn is_above_min(&self, score: f64) -> bool {
+        match self {
+            ScoreBound::Inclusive(min) => score >= *min,
+            ScoreBound::Exclusive(min) => score > *min,
+        }
+    }
+
+    /// Whether `score` is not above this maximum.
+    fn is_below_max(&self, score: f64) -> bool {
+        match self {
+            ScoreBound::Inclusive(max) => score <= *max,
+            ScoreBound::Exclusive(max) => score < *max,
+        }
+    }
+}
+
+/// The end of a range of members, e.g. "[a" for an inclusive minimum of "a", or "-" for no
+/// minimum. <br/>
+/// Like in Redis, ranges of members only make sense when all the members have the same score.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexBound {
+    Inclusive(Bytes),
+    Exclusive(Bytes),
+    /// "-", which is below every member.
+    Lowest,
+    /// "+", which is above every member.
+    Highest,
+}
+
+impl LexBound {
+    fn is_above_min(&self, member: &Bytes) -> bool {
+        match self {
+            LexBound::Inclusive(min) => member >= min,
+            LexBound::Exclusive(min) => member > min,
+            LexBound::Lowest => true,
+            LexBound::Highest => false,
+        }
+    }
+
+    fn is_below_max(&self, member: &Bytes) -> bool {
+        match self {
+            LexBound::Inclusive(max) => member <= max,
+            LexBound::Exclusive(max) => member < max,
+            LexBound::Lowest => false,
+            LexBound::Highest => true,
+        }
+    }
+}
+
+impl SortedSetValue {
+    pub fn len(&self) -> usize {
+        self.ordered.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn get_score(&self, member: &Bytes) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Sets the score of a member, and returns its previous score if it was in the set.
+    pub fn insert(&mut self, member: Bytes, score: f64) -> Option<f64> {
+        let previous_score = self.scores.insert(member.clone(), score);
+
+        if let Some(previous_score) = previous_score {
+            self.ordered
+                .remove(&(Score(previous_score), member.clone()));
+        }
+        self.ordered.insert((Score(score), member));
+
+        previous_score
+    }
+
+    /// Removes a member, and returns its score if it was in the set.
+    pub fn remove(&mut self, member: &Bytes) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.ordered.remove(&(Score(score), member.clone()));
+
+        Some(score)
+    }
+
+    /// The position of a member in the set, from the lowest score, if it is in the set.
+    pub fn get_rank(&self, member: &Bytes) -> Option<usize> {
+        let score = self.get_score(member)?;
+
+        self.ordered.get_rank(&(Score(score), member.clone()))
+    }
+
+    /// The members with their score, from the lowest score.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> + ExactSizeIterator {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// The members whose rank is in `ranks`, from the lowest score.
+    pub fn range_by_rank(
+        &self,
+        ranks: Range<usize>,
+    ) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> + ExactSizeIterator {
+        self.ordered
+            .range(ranks)
+            .map(|(score, member)| (member, score.0))
+    }
+
+    /// The members whose score is between `min` and `max`, from the lowest score.
+    pub fn range_by_score(
+        &self,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> + ExactSizeIterator {
+        let start = self
+            .ordered
+            .partition_point(|(score, _)| !min.is_above_min(score.0));
+        let end = self
+            .ordered
+            .partition_point(|(score, _)| max.is_below_max(score.0));
+
+        self.range_by_rank(start..end)
+    }
+
+    /// The members between `min` and `max`, from the lowest score.
+    pub fn range_by_lex(
+        &self,
+        min: &LexBound,
+        max: &LexBound,
+    ) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> + ExactSizeIterator {
+        let start = self
+            .ordered
+            .partition_point(|(_, member)| !min.is_above_min(member));
+        let end = self
+            .ordered
+            .partition_point(|(_, member)| max.is_below_max(member));
+
+        self.range_by_rank(start..end)
+    }
+
+    /// Removes the member with the lowest score, or the highest one if `highest`, and returns it
+    /// with its score.
+    pub fn pop(&mut self, highest: bool) -> Option<(Bytes, f64)> {
+        let (score, member) = self.ordered.pop(highest)?;
+        self.scores.remove(&member);
+
+        Some((member, score.0))
+    }
+}
+
+impl PartialEq for SortedSetValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LexBound, ScoreBound, SortedSetValue};
+
+    use bytes::Bytes;
+
+    fn members<'a>(iterator: impl Iterator<Item = (&'a Bytes, f64)>) -> Vec<(&'a str, f64)> {
+        iterator
+            .map(|(member, score)| (std::str::from_utf8(member).unwrap(), score))
+            .collect()
+    }
+
+    #[test]
+    fn sorted_set_ranges_pass() {
+        let mut sorted_set = SortedSetValue::default();
+
+        for (member, score) in [("c", 1.0), ("b", 1.0), ("a", 2.0), ("d", 0.0), ("e", 3.0)] {
+            assert_eq!(sorted_set.insert(Bytes::from(member), score), None);
+        }
+        assert_eq!(sorted_set.insert(Bytes::from("e"), -0.0), Some(3.0));
+
+        // Members with the same score are ordered lexicographically.
+        assert_eq!(
+            members(sorted_set.iter()),
+            [("d", 0.0), ("e", -0.0), ("b", 1.0), ("c", 1.0), ("a", 2.0)]
+        );
+        assert_eq!(sorted_set.get_rank(&Bytes::from("c")), Some(3));
+        assert_eq!(sorted_set.get_rank(&Bytes::from("z")), None);
+
+        assert_eq!(
+            members(
+                sorted_set.range_by_score(ScoreBound::Exclusive(0.0), ScoreBound::Inclusive(2.0))
+            ),
+            [("b", 1.0), ("c", 1.0), ("a", 2.0)]
+        );
+        assert_eq!(
+            members(sorted_set.range_by_score(
+                ScoreBound::Inclusive(f64::NEG_INFINITY),
+                ScoreBound::Exclusive(1.0)
+            )),
+            [("d", 0.0), ("e", -0.0)]
+        );
+        assert_eq!(
+            members(
+                sorted_set
+                    .range_by_score(ScoreBound::Inclusive(1.0), ScoreBound::Inclusive(1.0))
+                    .rev()
+            ),
+            [("c", 1.0), ("b", 1.0)]
+        );
+        assert_eq!(
+            members(sorted_set.range_by_rank(1..3)),
+            [("e", -0.0), ("b", 1.0)]
+        );
+
+        // Ranges of members are only meaningful when all the members have the same score.
+        let mut names = SortedSetValue::default();
+        for member in ["d", "b", "a", "c"] {
+            names.insert(Bytes::from(member), 0.0);
+        }
+        assert_eq!(
+            members(names.range_by_lex(&LexBound::Exclusive(Bytes::from("a")), &LexBound::Highest)),
+            [("b", 0.0), ("c", 0.0), ("d", 0.0)]
+        );
+        assert_eq!(
+            names
+                .range_by_lex(&LexBound::Highest, &LexBound::Highest)
+                .len(),
+            0
+        );
+
+        assert_eq!(sorted_set.pop(true), Some((Bytes::from("a"), 2.0)));
+        assert_eq!(sorted_set.remove(&Bytes::from("d")), Some(0.0));
+        assert_eq!(sorted_set.pop(false), Some((Bytes::from("e"), -0.0)));
+        assert_eq!(sorted_set.len(), 2);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod cli;
+pub mod command_error;
 pub mod connection_context;
 pub mod db;
 pub mod t_stream;
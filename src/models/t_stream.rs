@@ -1,4 +1,5 @@
 use super::connection_context::Response;
+use crate::resp_parser::shared::RespProtocolVersion;
 
 use std::{fmt::Debug, net::SocketAddr};
 
@@ -9,8 +10,10 @@ use tokio::{
 };
 
 pub trait TStream: AsyncRead + AsyncWrite + Send + Unpin + Debug {
+    #[allow(dead_code)]
     fn local_addr(&self) -> io::Result<SocketAddr>;
 
+    #[allow(dead_code)]
     fn peer_addr(&self) -> io::Result<SocketAddr>;
 }
 
@@ -25,22 +28,21 @@ impl TStream for TcpStream {
 }
 
 impl dyn TStream {
-    pub async fn write_all_responses(&mut self, responses: &Vec<Response>) -> Result<(), Error> {
+    /// Writes all responses in order, encoded for `protocol_version`, and flushes the stream once
+    /// they are all written.
+    pub async fn write_all_responses(
+        &mut self,
+        responses: &[Response],
+        protocol_version: RespProtocolVersion,
+    ) -> Result<(), Error> {
         for response in responses {
             println!("sending response - {:?}", response);
 
-            let is_raw_response = response.command_byte_response.is_some();
-
-            self.write_all(if !is_raw_response {
-                response.command_response.as_bytes()
-            } else {
-                response.command_byte_response.as_ref().unwrap().as_slice()
-            })
-            .await?;
-
-            self.flush().await?;
+            self.write_all(&response.to_bytes(protocol_version)).await?;
         }
 
+        self.flush().await?;
+
         Ok(())
     }
 }
@@ -0,0 +1,137 @@
+use crate::{
+    models::{command_error::CommandError, connection_context::ConnectionContext},
+    node::command_handlers::keys,
+    resp_parser::shared::{
+        RespCommandHelloOptions, RespCommandResponseNames, RespProtocolVersion, RespValue,
+    },
+    SERVER_VERSION,
+};
+
+use anyhow::{Error, Ok};
+use bytes::Bytes;
+
+pub(crate) fn handle_command_ping(context: &mut ConnectionContext<'_>) -> Result<(), Error> {
+    context.set_response(RespValue::SimpleString(RespCommandResponseNames::PONG.to_owned()).into());
+
+    Ok(())
+}
+
+pub(crate) fn handle_command_echo(context: &mut ConnectionContext<'_>) -> Result<(), Error> {
+    let message = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    context.set_response(RespValue::BulkString(message.clone()).into());
+
+    Ok(())
+}
+
+/// Switches the connection to another protocol version and replies the server info, e.g.:
+/// "HELLO 3 AUTH default secret SETNAME my-client"
+///
+/// There are no users or passwords, so authenticating is only accepted for the default user,
+/// whatever the password is, like a Redis server without `requirepass`.
+pub(crate) async fn handle_command_hello<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let parameters = &resp_command.parameters;
+
+    let protocol_version = match parameters.first() {
+        None => context.protocol_version,
+        Some(_) => match resp_command.get_parameter_str(0)?.parse::<i64>() {
+            Err(_) => {
+                return Err(CommandError::Other(
+                    "Protocol version is not an integer or out of range".to_owned(),
+                )
+                .into())
+            }
+            Result::Ok(number) => match RespProtocolVersion::from_number(number) {
+                None => return Err(CommandError::UnsupportedProtocol.into()),
+                Some(protocol_version) => protocol_version,
+            },
+        },
+    };
+
+    let mut client_name = None;
+    let mut position = 1;
+
+    while position < parameters.len() {
+        let option = resp_command.get_parameter_str(position)?;
+        let remaining = parameters.len() - position - 1;
+
+        if option.eq_ignore_ascii_case(RespCommandHelloOptions::AUTH) && remaining >= 2 {
+            if !parameters[position + 1].eq_ignore_ascii_case(b"default") {
+                return Err(CommandError::WrongPass.into());
+            }
+
+            position += 3;
+        } else if option.eq_ignore_ascii_case(RespCommandHelloOptions::SETNAME) && remaining >= 1 {
+            let name = &parameters[position + 1];
+
+            if name.iter().any(|byte| !(b'!'..=b'~').contains(byte)) {
+                return Err(CommandError::Other(
+                    "Client names cannot contain spaces, newlines or special characters."
+                        .to_owned(),
+                )
+                .into());
+            }
+
+            client_name = Some(name.clone());
+            position += 2;
+        } else {
+            return Err(
+                CommandError::Other(format!("Syntax error in HELLO option '{}'", option)).into(),
+            );
+        }
+    }
+
+    let is_replica = context
+        .mem_db
+        .lock()
+        .await
+        .get_app_data_ref()
+        .get_replication_data_ref()
+        .is_some();
+
+    if client_name.is_some() {
+        context.client_name = client_name;
+    }
+    context.protocol_version = protocol_version;
+
+    let field = |name: &'static str| RespValue::BulkString(Bytes::from_static(name.as_bytes()));
+
+    context.set_response(
+        RespValue::Map(vec![
+            (field("server"), field("redis")),
+            (field("version"), field(SERVER_VERSION)),
+            (
+                field("proto"),
+                RespValue::Integer(protocol_version.as_number()),
+            ),
+            (field("id"), RespValue::Integer(context.client_id as i64)),
+            (field("mode"), field("standalone")),
+            (
+                field("role"),
+                field(if is_replica { "replica" } else { "master" }),
+            ),
+            (field("modules"), RespValue::Array(Vec::new())),
+        ])
+        .into(),
+    );
+
+    Ok(())
+}
+
+/// Changes the database the commands of the connection run against, e.g.: "redis-cli select 1"
+pub(crate) async fn handle_command_select_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let db_index = context
+        .get_request_resp_command_ref()
+        .unwrap()
+        .get_parameter_i64(0)?;
+
+    context.selected_db = keys::check_db_index(&*context.mem_db.lock().await, db_index)?;
+    context.set_response(RespValue::ok().into());
+
+    Ok(())
+}
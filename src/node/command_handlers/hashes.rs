@@ -0,0 +1,1040 @@
+use crate::{
+    models::{
+        command_error::CommandError,
+        connection_context::{ConnectionContext, Propagation},
+        db::{
+            hash_value::HashValue,
+            in_memory_db::InMemoryDb,
+            in_memory_record::{InMemoryRecord, RecordValue},
+        },
+    },
+    node::command_handlers::keys::{
+        parse_expire_at_milli, parse_expire_condition, parse_scan_cursor, parse_scan_options,
+        ExpireCondition, ScanTarget,
+    },
+    resp_parser::shared::{
+        RespCommand, RespCommandHRandFieldOptions, RespCommandHashFieldsOptions, RespCommandNames,
+        RespProtocolVersion, RespValue,
+    },
+    utils::{
+        format_float_value, glob_match, parse_strict_f64, parse_strict_i64, random_below,
+        unix_time_millis,
+    },
+};
+
+use anyhow::{Error, Ok};
+use bytes::Bytes;
+
+/// The hash of `key` if it exists, or a WRONGTYPE error if the key holds another type. <br/>
+/// Expired fields are removed first, and so is the key if no field is left.
+pub(crate) fn get_live_hash_mut<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+) -> Result<Option<&'a mut HashValue>, Error> {
+    let now = unix_time_millis()?;
+
+    let expired_field_count = match db.get_live_record_mut(db_index, key)? {
+        None => return Ok(None),
+        Some(record) => record.get_hash_mut()?.remove_expired_fields(now),
+    };
+    db.expiry_stats.expired_subkeys += expired_field_count as u64;
+    db.remove_if_empty(db_index, key);
+
+    match db.get_live_record_mut(db_index, key)? {
+        None => Ok(None),
+        Some(record) => record.get_hash_mut().map(Some),
+    }
+}
+
+/// The hash of `key`, which is created empty if the key does not exist.
+fn get_or_create_hash_mut<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+) -> Result<&'a mut HashValue, Error> {
+    if get_live_hash_mut(db, db_index, key)?.is_none() {
+        db.get_records_ref_mut(db_index).insert(
+            key.clone(),
+            InMemoryRecord::new(RecordValue::Hash(HashValue::default()), None),
+        );
+    }
+
+    // Not through get_live_hash_mut, which would delete the hash while it is still empty.
+    db.get_live_record_mut(db_index, key)?
+        .unwrap()
+        .get_hash_mut()
+}
+
+/// Parses the `FIELDS numfields field [field ...]` that ends the commands on the expiry of hash
+/// fields, from `position` on.
+fn parse_fields(resp_command: &RespCommand, position: usize) -> Result<&[Bytes], Error> {
+    let has_fields_argument = resp_command
+        .parameters
+        .get(position)
+        .is_some_and(|argument| {
+            argument.eq_ignore_ascii_case(RespCommandHashFieldsOptions::FIELDS.as_bytes())
+        });
+    if !has_fields_argument {
+        return Err(CommandError::Other(
+            "Mandatory argument FIELDS is missing or not at the right position".to_owned(),
+        )
+        .into());
+    }
+
+    let field_count = resp_command
+        .get_parameter_i64(position + 1)
+        .ok()
+        .and_then(|field_count| usize::try_from(field_count).ok())
+        .filter(|field_count| *field_count > 0)
+        .ok_or_else(|| {
+            CommandError::Other("Parameter `numFields` should be greater than 0".to_owned())
+        })?;
+
+    let fields = &resp_command.parameters[position + 2..];
+    if fields.len() != field_count {
+        return Err(CommandError::Other(
+            "The `numfields` parameter must match the number of arguments".to_owned(),
+        )
+        .into());
+    }
+
+    Ok(fields)
+}
+
+/// Sets fields of a hash, e.g.: "redis-cli hset session:1 user alice theme dark" <br/>
+/// Replies how many fields are new.
+pub(crate) async fn handle_command_hset_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    if resp_command.parameters.len().is_multiple_of(2) {
+        return Err(CommandError::wrong_arity(&resp_command.name).into());
+    }
+
+    let mut db_lock = context.mem_db.lock().await;
+    let hash = get_or_create_hash_mut(&mut db_lock, context.selected_db, key)?;
+
+    let mut new_field_count = 0;
+    for field_value in resp_command.parameters[1..].chunks(2) {
+        if hash.insert(field_value[0].clone(), field_value[1].clone()) {
+            new_field_count += 1;
+        }
+    }
+
+    context.set_response(RespValue::Integer(new_field_count).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli hget session:1 user" <br/>
+/// Replies a null if the field or the key does not exist.
+pub(crate) async fn handle_command_hget_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (key, field) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let value = get_live_hash_mut(&mut db_lock, context.selected_db, key)?
+        .and_then(|hash| hash.get(field))
+        .map(|hash_field| hash_field.value.clone());
+
+    context.set_response(
+        value
+            .map_or(RespValue::NullBulkString, RespValue::BulkString)
+            .into(),
+    );
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli hmget session:1 user theme" <br/>
+/// Replies a null for every field that does not exist.
+pub(crate) async fn handle_command_hmget_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+    let hash = get_live_hash_mut(&mut db_lock, context.selected_db, key)?;
+
+    let values = resp_command.parameters[1..]
+        .iter()
+        .map(|field| {
+            hash.as_ref()
+                .and_then(|hash| hash.get(field))
+                .map_or(RespValue::NullBulkString, |hash_field| {
+                    RespValue::BulkString(hash_field.value.clone())
+                })
+        })
+        .collect();
+
+    context.set_response(RespValue::Array(values).into());
+
+    Ok(())
+}
+
+/// Replies the fields and values of a hash, e.g.: "redis-cli hgetall session:1"
+pub(crate) async fn handle_command_hgetall_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let fields =
+        get_live_hash_mut(&mut db_lock, context.selected_db, key)?.map_or(Vec::new(), |hash| {
+            hash.iter()
+                .map(|(field, hash_field)| {
+                    (
+                        RespValue::BulkString(field.clone()),
+                        RespValue::BulkString(hash_field.value.clone()),
+                    )
+                })
+                .collect()
+        });
+
+    context.set_response(RespValue::Map(fields).into());
+
+    Ok(())
+}
+
+/// Deletes fields of a hash, e.g.: "redis-cli hdel session:1 theme" <br/>
+/// Replies how many fields were deleted. The key is deleted with its last field.
+pub(crate) async fn handle_command_hdel_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let removed_count = match get_live_hash_mut(&mut db_lock, context.selected_db, key)? {
+        None => 0,
+        Some(hash) => resp_command.parameters[1..]
+            .iter()
+            .filter(|field| hash.remove(field).is_some())
+            .count(),
+    };
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    if removed_count == 0 {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(removed_count as i64).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli hexists session:1 user"
+pub(crate) async fn handle_command_hexists_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (key, field) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let exists = get_live_hash_mut(&mut db_lock, context.selected_db, key)?
+        .is_some_and(|hash| hash.get(field).is_some());
+
+    context.set_response(RespValue::Integer(exists as i64).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli hincrby session:1 visits 1" <br/>
+/// A missing field counts as 0, and the expiry of an existing field is kept.
+pub(crate) async fn handle_command_hincrby_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (key, field) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+    let increment = resp_command.get_parameter_i64(2)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let current = match get_live_hash_mut(&mut db_lock, context.selected_db, key)?
+        .and_then(|hash| hash.get(field))
+    {
+        None => 0,
+        Some(hash_field) => parse_strict_i64(&hash_field.value)
+            .ok_or_else(|| CommandError::Other("hash value is not an integer".to_owned()))?,
+    };
+
+    let new_value = current
+        .checked_add(increment)
+        .ok_or_else(|| CommandError::Other("increment or decrement would overflow".to_owned()))?;
+
+    set_field_value(
+        get_or_create_hash_mut(&mut db_lock, context.selected_db, key)?,
+        field,
+        Bytes::from(new_value.to_string()),
+    );
+
+    context.set_response(RespValue::Integer(new_value).into());
+
+    Ok(())
+}
+
+/// Sets the value of a field, keeping its expiry if it exists.
+fn set_field_value(hash: &mut HashValue, field: &Bytes, value: Bytes) {
+    if hash.get(field).is_some() {
+        hash.set_value(field, value);
+    } else {
+        hash.insert(field.clone(), value);
+    }
+}
+
+/// E.g.: "redis-cli hincrbyfloat cart:1 total 9.99"
+///
+/// It is propagated as an HSET of the resulting value, so replicas don't depend on float
+/// rounding, followed by the expiry of the field if it has one.
+pub(crate) async fn handle_command_hincrbyfloat_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (key, field) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+    let increment = resp_command.get_parameter_f64(2)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let current = match get_live_hash_mut(&mut db_lock, context.selected_db, key)?
+        .and_then(|hash| hash.get(field))
+    {
+        None => 0.0,
+        Some(hash_field) => parse_strict_f64(&hash_field.value)
+            .ok_or_else(|| CommandError::Other("hash value is not a float".to_owned()))?,
+    };
+
+    let new_value = current + increment;
+
+    if !new_value.is_finite() {
+        return Err(
+            CommandError::Other("increment would produce NaN or Infinity".to_owned()).into(),
+        );
+    }
+
+    let new_value_bytes = Bytes::from(format_float_value(new_value));
+    let hash = get_or_create_hash_mut(&mut db_lock, context.selected_db, key)?;
+    set_field_value(hash, field, new_value_bytes.clone());
+
+    let mut propagated_commands = vec![RespCommand::new(
+        RespCommandNames::HSET,
+        vec![key.clone(), field.clone(), new_value_bytes.clone()],
+    )];
+    if let Some(expire_at_milli) = hash.get(field).unwrap().expire_at_milli {
+        propagated_commands.push(hpexpireat_command(
+            key,
+            expire_at_milli,
+            std::slice::from_ref(field),
+        ));
+    }
+
+    context.propagation = Propagation::Rewritten(propagated_commands);
+    context.set_response(RespValue::BulkString(new_value_bytes).into());
+
+    Ok(())
+}
+
+/// Handles HKEYS, HVALS and HLEN, e.g.: "redis-cli hkeys session:1"
+pub(crate) async fn handle_command_hkeys_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+    let hash = get_live_hash_mut(&mut db_lock, context.selected_db, key)?;
+
+    let response = match resp_command.name.as_str() {
+        RespCommandNames::HLEN => RespValue::Integer(hash.map_or(0, |hash| hash.len()) as i64),
+        RespCommandNames::HKEYS => RespValue::Array(hash.map_or(Vec::new(), |hash| {
+            hash.iter()
+                .map(|(field, _)| RespValue::BulkString(field.clone()))
+                .collect()
+        })),
+        _ => RespValue::Array(hash.map_or(Vec::new(), |hash| {
+            hash.iter()
+                .map(|(_, hash_field)| RespValue::BulkString(hash_field.value.clone()))
+                .collect()
+        })),
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Walks the fields of a hash a few at a time, like SCAN does with keys, e.g.: <br/>
+/// "redis-cli hscan session:1 0 match 'pref:*' count 100" <br/>
+/// "redis-cli hscan session:1 0 novalues" (replies the fields only)
+pub(crate) async fn handle_command_hscan_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let cursor = parse_scan_cursor(resp_command, 1)?;
+    let options = parse_scan_options(resp_command, 2, ScanTarget::Hash)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut items = Vec::new();
+    let next_cursor = match get_live_hash_mut(&mut db_lock, context.selected_db, key)? {
+        None => 0,
+        Some(hash) => hash.scan(cursor, options.count, |field, hash_field| {
+            let is_match = options
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| glob_match(pattern, field));
+
+            if is_match {
+                items.push(RespValue::BulkString(field.clone()));
+
+                if !options.no_values {
+                    items.push(RespValue::BulkString(hash_field.value.clone()));
+                }
+            }
+        }),
+    };
+
+    context.set_response(
+        RespValue::Array(vec![
+            RespValue::BulkString(Bytes::from(next_cursor.to_string())),
+            RespValue::Array(items),
+        ])
+        .into(),
+    );
+
+    Ok(())
+}
+
+/// Replies random fields of a hash, e.g.: <br/>
+/// "redis-cli hrandfield session:1" (a single field) <br/>
+/// "redis-cli hrandfield session:1 3 withvalues" (up to 3 distinct fields, with their values)
+/// <br/>
+/// "redis-cli hrandfield session:1 -3" (3 fields that may repeat)
+pub(crate) async fn handle_command_hrandfield_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let count = match resp_command.parameters.len() {
+        1 => None,
+        _ => Some(resp_command.get_parameter_i64(1)?),
+    };
+    let with_values = match &resp_command.parameters[1..] {
+        [] | [_] => false,
+        [_, option]
+            if option.eq_ignore_ascii_case(RespCommandHRandFieldOptions::WITHVALUES.as_bytes()) =>
+        {
+            true
+        }
+        _ => return Err(CommandError::Syntax.into()),
+    };
+    if count.is_some_and(|count| count.unsigned_abs() > i64::MAX as u64 / 2) {
+        return Err(CommandError::Other("value is out of range".to_owned()).into());
+    }
+
+    let mut db_lock = context.mem_db.lock().await;
+    let hash = get_live_hash_mut(&mut db_lock, context.selected_db, key)?;
+
+    let (count, hash) = match (count, hash) {
+        (None, hash) => {
+            let field = hash.and_then(|hash| hash.get_random());
+            context.set_response(
+                field
+                    .map_or(RespValue::NullBulkString, |(field, _)| {
+                        RespValue::BulkString(field.clone())
+                    })
+                    .into(),
+            );
+
+            return Ok(());
+        }
+        (Some(_), None) => {
+            context.set_response(RespValue::Array(Vec::new()).into());
+
+            return Ok(());
+        }
+        (Some(count), Some(hash)) => (count, hash),
+    };
+
+    let fields: Vec<(&Bytes, &Bytes)> = if count < 0 {
+        (0..count.unsigned_abs())
+            .filter_map(|_| hash.get_random())
+            .map(|(field, hash_field)| (field, &hash_field.value))
+            .collect()
+    } else {
+        let mut fields: Vec<(&Bytes, &Bytes)> = hash
+            .iter()
+            .map(|(field, hash_field)| (field, &hash_field.value))
+            .collect();
+
+        // A partial shuffle, which picks `count` distinct fields.
+        let count = (count as usize).min(fields.len());
+        for index in 0..count {
+            let picked = index + random_below(fields.len() - index);
+            fields.swap(index, picked);
+        }
+        fields.truncate(count);
+
+        fields
+    };
+
+    let response = if !with_values {
+        fields
+            .into_iter()
+            .map(|(field, _)| RespValue::BulkString(field.clone()))
+            .collect()
+    } else if context.protocol_version == RespProtocolVersion::Resp3 {
+        fields
+            .into_iter()
+            .map(|(field, value)| {
+                RespValue::Array(vec![
+                    RespValue::BulkString(field.clone()),
+                    RespValue::BulkString(value.clone()),
+                ])
+            })
+            .collect()
+    } else {
+        fields
+            .into_iter()
+            .flat_map(|(field, value)| {
+                [
+                    RespValue::BulkString(field.clone()),
+                    RespValue::BulkString(value.clone()),
+                ]
+            })
+            .collect()
+    };
+
+    context.set_response(RespValue::Array(response).into());
+
+    Ok(())
+}
+
+/// The command propagated to replicas when a write sets the expiry of hash fields, which is
+/// always absolute, so that their copy expires at the same time.
+fn hpexpireat_command(key: &Bytes, expire_at_milli: u128, fields: &[Bytes]) -> RespCommand {
+    let mut parameters = vec![
+        key.clone(),
+        Bytes::from(expire_at_milli.to_string()),
+        Bytes::from_static(RespCommandHashFieldsOptions::FIELDS.as_bytes()),
+        Bytes::from(fields.len().to_string()),
+    ];
+    parameters.extend_from_slice(fields);
+
+    RespCommand::new(RespCommandNames::HPEXPIREAT, parameters)
+}
+
+/// Handles HEXPIRE, HPEXPIRE, HEXPIREAT and HPEXPIREAT, which set the expiry of hash fields,
+/// e.g.: <br/>
+/// "redis-cli hexpire session:1 60 fields 2 token csrf" <br/>
+/// "redis-cli hpexpireat session:1 1700000000000 gt fields 1 token" (only if later)
+///
+/// Replies for each field -2 if it does not exist, 0 if the condition is not met, 1 if the
+/// expiry was set, or 2 if the field was deleted because the expiry is in the past.
+pub(crate) async fn handle_command_hexpire_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    // The condition, if any, comes before FIELDS.
+    let fields_position = if resp_command.parameters[2]
+        .eq_ignore_ascii_case(RespCommandHashFieldsOptions::FIELDS.as_bytes())
+    {
+        2
+    } else {
+        3
+    };
+    let condition = parse_expire_condition(resp_command, 2..fields_position)?;
+    let fields = parse_fields(resp_command, fields_position)?;
+
+    if resp_command.get_parameter_i64(1)? < 0 {
+        return Err(CommandError::Other("invalid expire time, must be >= 0".to_owned()).into());
+    }
+
+    let now = unix_time_millis()?;
+    let expire_at_milli = parse_expire_at_milli(
+        resp_command,
+        1,
+        matches!(
+            resp_command.name.as_str(),
+            RespCommandNames::HEXPIRE | RespCommandNames::HEXPIREAT
+        ),
+        matches!(
+            resp_command.name.as_str(),
+            RespCommandNames::HEXPIRE | RespCommandNames::HPEXPIRE
+        ),
+        now,
+    )?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut responses = Vec::with_capacity(fields.len());
+    let mut expiring_fields = Vec::new();
+    let mut deleted_fields = Vec::new();
+
+    if let Some(hash) = get_live_hash_mut(&mut db_lock, context.selected_db, key)? {
+        for field in fields {
+            let response = match hash.get(field) {
+                None => -2,
+                Some(hash_field) => {
+                    let current = hash_field.expire_at_milli.map(|current| current as i64);
+
+                    if !ExpireCondition::is_met(condition, current, expire_at_milli) {
+                        0
+                    } else if expire_at_milli <= now as i64 {
+                        hash.remove(field);
+                        deleted_fields.push(field.clone());
+
+                        2
+                    } else {
+                        hash.set_expire_at(field, Some(expire_at_milli as u128));
+                        expiring_fields.push(field.clone());
+
+                        1
+                    }
+                }
+            };
+
+            responses.push(RespValue::Integer(response));
+        }
+    } else {
+        responses.resize(fields.len(), RespValue::Integer(-2));
+    }
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    let mut propagated_commands = Vec::new();
+    if !expiring_fields.is_empty() {
+        propagated_commands.push(hpexpireat_command(
+            key,
+            expire_at_milli as u128,
+            &expiring_fields,
+        ));
+    }
+    if !deleted_fields.is_empty() {
+        let mut parameters = vec![key.clone()];
+        parameters.extend(deleted_fields);
+        propagated_commands.push(RespCommand::new(RespCommandNames::HDEL, parameters));
+    }
+
+    context.propagation = Propagation::Rewritten(propagated_commands);
+    context.set_response(RespValue::Array(responses).into());
+
+    Ok(())
+}
+
+/// Handles HTTL, HPTTL, HEXPIRETIME and HPEXPIRETIME, e.g.: <br/>
+/// "redis-cli httl session:1 fields 2 token csrf" (seconds left)
+///
+/// Replies for each field -2 if it does not exist, and -1 if it has no expiry.
+pub(crate) async fn handle_command_httl_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let fields = parse_fields(resp_command, 1)?;
+    let now = unix_time_millis()?;
+
+    let mut db_lock = context.mem_db.lock().await;
+    let hash = get_live_hash_mut(&mut db_lock, context.selected_db, key)?;
+
+    let responses = fields
+        .iter()
+        .map(|field| {
+            let response = match hash.as_ref().and_then(|hash| hash.get(field)) {
+                None => -2,
+                Some(hash_field) => match hash_field.expire_at_milli {
+                    None => -1,
+                    Some(expire_at_milli) => {
+                        let ttl_milli = expire_at_milli.saturating_sub(now) as i64;
+
+                        match resp_command.name.as_str() {
+                            // Rounded to the closest second, like TTL.
+                            RespCommandNames::HTTL => (ttl_milli + 500) / 1000,
+                            RespCommandNames::HPTTL => ttl_milli,
+                            RespCommandNames::HEXPIRETIME => (expire_at_milli / 1000) as i64,
+                            _ => expire_at_milli as i64,
+                        }
+                    }
+                },
+            };
+
+            RespValue::Integer(response)
+        })
+        .collect();
+
+    context.set_response(RespValue::Array(responses).into());
+
+    Ok(())
+}
+
+/// Removes the expiry of hash fields, e.g.: "redis-cli hpersist session:1 fields 1 token"
+///
+/// Replies for each field -2 if it does not exist, -1 if it has no expiry, or 1 if its expiry
+/// was removed.
+pub(crate) async fn handle_command_hpersist_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let fields = parse_fields(resp_command, 1)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+    let mut hash = get_live_hash_mut(&mut db_lock, context.selected_db, key)?;
+
+    let responses: Vec<i64> = fields
+        .iter()
+        .map(|field| match hash.as_mut() {
+            None => -2,
+            Some(hash) => match hash.get(field).map(|hash_field| hash_field.expire_at_milli) {
+                None => -2,
+                Some(None) => -1,
+                Some(Some(_)) => {
+                    hash.set_expire_at(field, None);
+
+                    1
+                }
+            },
+        })
+        .collect();
+
+    if !responses.contains(&1) {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(
+        RespValue::Array(responses.into_iter().map(RespValue::Integer).collect()).into(),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        resp_parser::shared::RespValue,
+        test_helpers::utils::{
+            bulk_string, bulk_strings, create_test_mem_db, error, integers, send_test_request,
+        },
+    };
+
+    #[tokio::test]
+    async fn hash_commands_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"HSET h a 1 b 2\r\nHSET h b 3 c 4\r\nHGET h b\r\nHGET h nope\r\nHMGET h a nope c\r\nHGETALL h\r\nHLEN h\r\nHKEYS h\r\nHVALS h\r\nHEXISTS h a\r\nHEXISTS nope a\r\nHDEL h a nope\r\nHDEL h b c\r\nEXISTS h\r\nHGETALL h\r\nHSET h a\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(2),
+                RespValue::Integer(1),
+                bulk_string("3"),
+                RespValue::NullBulkString,
+                RespValue::Array(vec![
+                    bulk_string("1"),
+                    RespValue::NullBulkString,
+                    bulk_string("4")
+                ]),
+                RespValue::Map(vec![
+                    (bulk_string("a"), bulk_string("1")),
+                    (bulk_string("b"), bulk_string("3")),
+                    (bulk_string("c"), bulk_string("4")),
+                ]),
+                RespValue::Integer(3),
+                bulk_strings(&["a", "b", "c"]),
+                bulk_strings(&["1", "3", "4"]),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                RespValue::Integer(1),
+                RespValue::Integer(2),
+                RespValue::Integer(0),
+                RespValue::Map(vec![]),
+                error("ERR wrong number of arguments for 'hset' command"),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET s v\r\nHSET s a 1\r\nHGET s a\r\nHSET h a 1\r\nGET h\r\nLPUSH h x\r\nTYPE h\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                RespValue::Integer(1),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                RespValue::SimpleString("hash".to_owned()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hincrby_and_hincrbyfloat_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"HINCRBY h n 5\r\nHINCRBY h n -7\r\nHINCRBYFLOAT h f 10.5\r\nHINCRBYFLOAT h f 0.1\r\nHSET h s abc\r\nHINCRBY h s 1\r\nHINCRBYFLOAT h s 1\r\nHINCRBY h n 9223372036854775807\r\nHINCRBYFLOAT nope f inf\r\nEXISTS nope\r\nHGETALL h\r\nHSET g f 0.2\r\nHINCRBYFLOAT g f 0.1\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(5),
+                RespValue::Integer(-2),
+                bulk_string("10.5"),
+                bulk_string("10.6"),
+                RespValue::Integer(1),
+                error("ERR hash value is not an integer"),
+                error("ERR hash value is not a float"),
+                RespValue::Integer(9223372036854775805),
+                error("ERR increment would produce NaN or Infinity"),
+                RespValue::Integer(0),
+                RespValue::Map(vec![
+                    (bulk_string("n"), bulk_string("9223372036854775805")),
+                    (bulk_string("f"), bulk_string("10.6")),
+                    (bulk_string("s"), bulk_string("abc")),
+                ]),
+                RespValue::Integer(1),
+                bulk_string("0.3"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_hscan_async_replies_every_field_once() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut request = b"HSET h".to_vec();
+        for i in 0..100 {
+            request.extend_from_slice(format!(" f:{} {}", i, i).as_bytes());
+        }
+        request.extend_from_slice(b"\r\n");
+        send_test_request(&fake_mem_db, &request).await?;
+
+        let mut scanned_items = Vec::new();
+        let mut cursor = "0".to_owned();
+
+        loop {
+            let replies = send_test_request(
+                &fake_mem_db,
+                format!("HSCAN h {} MATCH f:1* COUNT 7\r\n", cursor).as_bytes(),
+            )
+            .await?;
+
+            match &replies[0] {
+                RespValue::Array(reply) => match &reply[..] {
+                    [RespValue::BulkString(next_cursor), RespValue::Array(items)] => {
+                        cursor = String::from_utf8(next_cursor.to_vec())?;
+                        scanned_items.extend(items.iter().cloned());
+                    }
+                    _ => panic!("unexpected reply: {:?}", reply),
+                },
+                reply => panic!("unexpected reply: {:?}", reply),
+            }
+
+            if cursor == "0" {
+                break;
+            }
+        }
+
+        // f:1 and f:10 to f:19, with their values.
+        assert_eq!(scanned_items.len(), 22);
+        assert!(scanned_items.chunks(2).all(|item| item[0]
+            == bulk_string(&format!(
+                "f:{}",
+                match &item[1] {
+                    RespValue::BulkString(value) => String::from_utf8(value.to_vec()).unwrap(),
+                    value => panic!("unexpected value: {:?}", value),
+                }
+            ))));
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"HSCAN h 0 MATCH f:5 NOVALUES COUNT 100\r\nHSCAN nope 0\r\nHSCAN h 0 TYPE hash\r\nSCAN 0 NOVALUES\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Array(vec![bulk_string("0"), bulk_strings(&["f:5"])]),
+                RespValue::Array(vec![bulk_string("0"), bulk_strings(&[])]),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_hrandfield_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let replies = send_test_request(
+            &fake_mem_db,
+            b"HSET h a 1 b 2 c 3\r\nHRANDFIELD h\r\nHRANDFIELD h 2\r\nHRANDFIELD h 10 WITHVALUES\r\nHRANDFIELD h -5\r\nHRANDFIELD nope\r\nHRANDFIELD nope 3\r\nHRANDFIELD h 1 VALUES\r\n",
+        )
+        .await?;
+
+        let fields = [bulk_string("a"), bulk_string("b"), bulk_string("c")];
+        assert!(fields.contains(&replies[1]));
+
+        // A positive count replies distinct fields.
+        match &replies[2] {
+            RespValue::Array(items) => {
+                assert_eq!(items.len(), 2);
+                assert_ne!(items[0], items[1]);
+                assert!(items.iter().all(|field| fields.contains(field)));
+            }
+            reply => panic!("unexpected reply: {:?}", reply),
+        }
+
+        // A count larger than the hash replies all of it.
+        match &replies[3] {
+            RespValue::Array(items) => {
+                let mut pairs: Vec<_> = items.chunks(2).map(|pair| format!("{:?}", pair)).collect();
+                pairs.sort();
+                assert_eq!(
+                    pairs,
+                    vec![
+                        format!("{:?}", [bulk_string("a"), bulk_string("1")]),
+                        format!("{:?}", [bulk_string("b"), bulk_string("2")]),
+                        format!("{:?}", [bulk_string("c"), bulk_string("3")]),
+                    ]
+                );
+            }
+            reply => panic!("unexpected reply: {:?}", reply),
+        }
+
+        // A negative count may repeat fields.
+        match &replies[4] {
+            RespValue::Array(items) => {
+                assert_eq!(items.len(), 5);
+                assert!(items.iter().all(|field| fields.contains(field)));
+            }
+            reply => panic!("unexpected reply: {:?}", reply),
+        }
+
+        assert_eq!(
+            replies[5..],
+            [
+                RespValue::NullBulkString,
+                RespValue::Array(vec![]),
+                error("ERR syntax error"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hash_fields_expire_on_their_own() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"HSET h a 1 b 2 c 3\r\nHEXPIRE h 100 FIELDS 2 a nope\r\nHEXPIRE h 50 GT FIELDS 2 a b\r\nHEXPIRE h 50 LT FIELDS 2 a b\r\nHTTL h FIELDS 3 a b nope\r\nHPEXPIRE h 1 NX FIELDS 1 c\r\nHEXPIRE h 0 FIELDS 1 b\r\nHPERSIST h FIELDS 3 a b nope\r\nHTTL h FIELDS 1 a\r\nHTTL nope FIELDS 1 a\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(3),
+                integers(&[1, -2]),
+                integers(&[0, 0]),
+                integers(&[1, 1]),
+                integers(&[50, 50, -2]),
+                integers(&[1]),
+                integers(&[2]),
+                integers(&[1, -2, -2]),
+                integers(&[-1]),
+                integers(&[-2]),
+            ]
+        );
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        // c expired, and the key is deleted with its last field.
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"HGET h c\r\nHLEN h\r\nHPEXPIRE h 1 FIELDS 1 a\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::NullBulkString,
+                RespValue::Integer(1),
+                integers(&[1]),
+            ]
+        );
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"HGETALL h\r\nEXISTS h\r\nHSET h a 1\r\nHEXPIRE h 10 FIELDS 2 a\r\nHEXPIRE h 10 FIELDS 0 a\r\nHEXPIRE h 10 FIELD 1 a\r\nHEXPIRE h -1 FIELDS 1 a\r\nHEXPIRE h 10 XX GT LT FIELDS 1 a\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Map(vec![]),
+                RespValue::Integer(0),
+                RespValue::Integer(1),
+                error("ERR The `numfields` parameter must match the number of arguments"),
+                error("ERR Parameter `numFields` should be greater than 0"),
+                error("ERR Unsupported option FIELD"),
+                error("ERR invalid expire time, must be >= 0"),
+                error("ERR Mandatory argument FIELDS is missing or not at the right position"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_hexpire_async_takes_a_single_condition() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        // Like in Redis, XX and LT can't be combined, so a field without an expiry keeps none.
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"HSET h a 1 b 2\r\nHEXPIRE h 10 XX LT FIELDS 1 a\r\nHEXPIRE h 10 LT XX FIELDS 1 a\r\nHEXPIRE h 10 XX FIELDS 1 a\r\nHTTL h FIELDS 1 a\r\nHEXPIRE h 10 LT FIELDS 2 a b\r\nHEXPIRE h 20 XX FIELDS 1 a\r\nHTTL h FIELDS 2 a b\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(2),
+                error("ERR Mandatory argument FIELDS is missing or not at the right position"),
+                error("ERR Mandatory argument FIELDS is missing or not at the right position"),
+                integers(&[0]),
+                integers(&[-1]),
+                integers(&[1, 1]),
+                integers(&[1]),
+                integers(&[20, 10]),
+            ]
+        );
+
+        Ok(())
+    }
+}
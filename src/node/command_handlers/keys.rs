@@ -0,0 +1,1207 @@
+use crate::{
+    models::{
+        command_error::CommandError,
+        connection_context::{ConnectionContext, Propagation},
+        db::{in_memory_db::InMemoryDb, in_memory_record::InMemoryRecord},
+    },
+    node::command_handlers::lists,
+    resp_parser::shared::{
+        RespCommand, RespCommandCopyOptions, RespCommandExpireOptions, RespCommandNames,
+        RespCommandObjectSubcommands, RespCommandScanOptions, RespValue,
+    },
+    utils::{glob_match, unix_time_millis},
+};
+
+use std::ops::Range;
+
+use anyhow::{Error, Ok};
+use bytes::Bytes;
+
+/// The command propagated to replicas when a write deletes a key, e.g. because the expiry it
+/// set is already in the past.
+pub(crate) fn del_command(key: &Bytes) -> RespCommand {
+    RespCommand::new(RespCommandNames::DEL, vec![key.clone()])
+}
+
+/// The command propagated to replicas when a write sets the expiry of a key, which is always
+/// absolute, so that their copy expires at the same time.
+pub(crate) fn pexpireat_command(key: &Bytes, expire_at_milli: u128) -> RespCommand {
+    RespCommand::new(
+        RespCommandNames::PEXPIREAT,
+        vec![key.clone(), Bytes::from(expire_at_milli.to_string())],
+    )
+}
+
+/// The command propagated to replicas when a write removes the expiry of a key.
+pub(crate) fn persist_command(key: &Bytes) -> RespCommand {
+    RespCommand::new(RespCommandNames::PERSIST, vec![key.clone()])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ExpireCondition {
+    /// NX: only if the key has no expiry.
+    HasNoExpiry,
+    /// XX: only if the key has an expiry.
+    HasExpiry,
+    /// GT: only if the new expiry is later than the current one, where no expiry counts as
+    /// never expiring.
+    IsLater,
+    /// LT: only if the new expiry is sooner than the current one.
+    IsSooner,
+    /// XX and LT: only if there is an expiry, and the new one is sooner.
+    HasExpiryAndIsSooner,
+}
+
+impl ExpireCondition {
+    /// Whether an expiry can be replaced by `expire_at_milli` under `condition`, where `current`
+    /// is the expiry, if any, of the key or hash field.
+    pub(crate) fn is_met(
+        condition: Option<ExpireCondition>,
+        current: Option<i64>,
+        expire_at_milli: i64,
+    ) -> bool {
+        match condition {
+            None => true,
+            Some(ExpireCondition::HasNoExpiry) => current.is_none(),
+            Some(ExpireCondition::HasExpiry) => current.is_some(),
+            Some(ExpireCondition::IsLater) => {
+                current.is_some_and(|current| expire_at_milli > current)
+            }
+            Some(ExpireCondition::IsSooner) => {
+                current.is_none_or(|current| expire_at_milli < current)
+            }
+            Some(ExpireCondition::HasExpiryAndIsSooner) => {
+                current.is_some_and(|current| expire_at_milli < current)
+            }
+        }
+    }
+}
+
+/// Parses the NX, XX, GT and LT options at `positions`.
+pub(crate) fn parse_expire_condition(
+    resp_command: &RespCommand,
+    positions: Range<usize>,
+) -> Result<Option<ExpireCondition>, Error> {
+    let mut condition = None;
+
+    for position in positions {
+        let option = resp_command
+            .get_parameter_str(position)?
+            .to_ascii_uppercase();
+
+        let new_condition = match option.as_str() {
+            RespCommandExpireOptions::NX => ExpireCondition::HasNoExpiry,
+            RespCommandExpireOptions::XX => ExpireCondition::HasExpiry,
+            RespCommandExpireOptions::GT => ExpireCondition::IsLater,
+            RespCommandExpireOptions::LT => ExpireCondition::IsSooner,
+            _ => return Err(CommandError::Other(format!("Unsupported option {}", option)).into()),
+        };
+
+        condition = match (condition, new_condition) {
+            (None, _) => Some(new_condition),
+            (Some(current), _) if current == new_condition => condition,
+            (Some(ExpireCondition::HasNoExpiry), _) | (_, ExpireCondition::HasNoExpiry) => {
+                return Err(CommandError::Other(
+                    "NX and XX, GT or LT options at the same time are not compatible".to_owned(),
+                )
+                .into())
+            }
+            (Some(ExpireCondition::IsLater), ExpireCondition::IsSooner)
+            | (
+                Some(ExpireCondition::IsSooner | ExpireCondition::HasExpiryAndIsSooner),
+                ExpireCondition::IsLater,
+            ) => {
+                return Err(CommandError::Other(
+                    "GT and LT options at the same time are not compatible".to_owned(),
+                )
+                .into())
+            }
+            // XX can be combined with GT or LT, and is implied by GT.
+            (Some(ExpireCondition::HasExpiry), ExpireCondition::IsSooner)
+            | (Some(ExpireCondition::IsSooner), ExpireCondition::HasExpiry) => {
+                Some(ExpireCondition::HasExpiryAndIsSooner)
+            }
+            (Some(ExpireCondition::HasExpiry), _) => Some(new_condition),
+            (Some(_), _) => condition,
+        };
+    }
+
+    Ok(condition)
+}
+
+/// Parses the amount at `position` of an expire command into a Unix time in milliseconds, from
+/// seconds or milliseconds, either from `now` or absolute, e.g. the seconds of EXPIRE.
+pub(crate) fn parse_expire_at_milli(
+    resp_command: &RespCommand,
+    position: usize,
+    is_seconds: bool,
+    is_relative: bool,
+    now: u128,
+) -> Result<i64, Error> {
+    let amount = resp_command.get_parameter_i64(position)?;
+
+    let milliseconds = if is_seconds {
+        amount.checked_mul(1000)
+    } else {
+        Some(amount)
+    };
+    let expire_at_milli = if is_relative {
+        milliseconds.and_then(|milliseconds| milliseconds.checked_add(now as i64))
+    } else {
+        milliseconds
+    };
+
+    expire_at_milli.ok_or_else(|| {
+        CommandError::Other(format!(
+            "invalid expire time in '{}' command",
+            resp_command.name.to_ascii_lowercase()
+        ))
+        .into()
+    })
+}
+
+/// Handles EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT, e.g.: <br/>
+/// "redis-cli expire session 60" <br/>
+/// "redis-cli pexpireat session 1700000000000 gt" (only if later than the current expiry)
+///
+/// Replies 1 if the expiry was set, or 0 if the key does not exist or the condition is not met.
+/// An expiry in the past deletes the key.
+pub(crate) async fn handle_command_expire_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let condition = parse_expire_condition(resp_command, 2..resp_command.parameters.len())?;
+
+    let now = unix_time_millis()?;
+    let expire_at_milli = parse_expire_at_milli(
+        resp_command,
+        1,
+        matches!(
+            resp_command.name.as_str(),
+            RespCommandNames::EXPIRE | RespCommandNames::EXPIREAT
+        ),
+        matches!(
+            resp_command.name.as_str(),
+            RespCommandNames::EXPIRE | RespCommandNames::PEXPIRE
+        ),
+        now,
+    )?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let record = match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => None,
+        Some(record) => {
+            let current = record.expire_at_milli.map(|current| current as i64);
+
+            Some(record).filter(|_| ExpireCondition::is_met(condition, current, expire_at_milli))
+        }
+    };
+
+    let response = match record {
+        None => {
+            context.propagation = Propagation::Rewritten(Vec::new());
+
+            0
+        }
+        Some(record) if expire_at_milli > now as i64 => {
+            record.expire_at_milli = Some(expire_at_milli as u128);
+            context.propagation =
+                Propagation::Rewritten(vec![pexpireat_command(key, expire_at_milli as u128)]);
+
+            1
+        }
+        Some(_) => {
+            db_lock.get_records_ref_mut(context.selected_db).remove(key);
+            context.propagation = Propagation::Rewritten(vec![del_command(key)]);
+
+            1
+        }
+    };
+
+    context.set_response(RespValue::Integer(response).into());
+
+    Ok(())
+}
+
+/// Handles TTL, PTTL, EXPIRETIME and PEXPIRETIME, e.g.: <br/>
+/// "redis-cli ttl session" (seconds left) <br/>
+/// "redis-cli pexpiretime session" (Unix time in milliseconds at which it expires)
+///
+/// Replies -2 if the key does not exist, and -1 if it has no expiry.
+pub(crate) async fn handle_command_ttl_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let response = match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => -2,
+        Some(record) => match (resp_command.name.as_str(), record.expire_at_milli) {
+            (_, None) => -1,
+            (RespCommandNames::TTL, Some(_)) => {
+                // Rounded to the closest second, like Redis does.
+                (record.get_ttl_milli()?.unwrap_or(0) as i64 + 500) / 1000
+            }
+            (RespCommandNames::PTTL, Some(_)) => record.get_ttl_milli()?.unwrap_or(0) as i64,
+            (RespCommandNames::EXPIRETIME, Some(expire_at_milli)) => {
+                (expire_at_milli / 1000) as i64
+            }
+            (_, Some(expire_at_milli)) => expire_at_milli as i64,
+        },
+    };
+
+    context.set_response(RespValue::Integer(response).into());
+
+    Ok(())
+}
+
+/// Removes the expiry of a key, e.g.: "redis-cli persist session" <br/>
+/// Replies 1 if it had one, or 0 otherwise.
+pub(crate) async fn handle_command_persist_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let had_expiry = match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => false,
+        Some(record) => record.expire_at_milli.take().is_some(),
+    };
+
+    if !had_expiry {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(had_expiry as i64).into());
+
+    Ok(())
+}
+
+/// Values that take more than this many allocations to drop are dropped on a background task by
+/// UNLINK, like the lazyfree threshold of Redis.
+const LAZYFREE_THRESHOLD: usize = 64;
+
+/// Drops `value` on a background task if freeing it takes more than a few allocations, so that
+/// callers do not spend the time it takes. <br/>
+/// The DB lock should be released before calling this, since small values are dropped right away.
+pub(crate) fn free_lazily<T: Send + 'static>(value: T, free_effort: usize) {
+    if free_effort > LAZYFREE_THRESHOLD {
+        tokio::task::spawn_blocking(move || drop(value));
+    }
+}
+
+/// Checks that `db_index` is the index of one of the databases, and returns it.
+pub(crate) fn check_db_index(db: &InMemoryDb, db_index: i64) -> Result<usize, Error> {
+    usize::try_from(db_index)
+        .ok()
+        .filter(|db_index| *db_index < db.get_db_count())
+        .ok_or_else(|| CommandError::Other("DB index is out of range".to_owned()).into())
+}
+
+/// Handles DEL and UNLINK, e.g.: "redis-cli del foo bar" <br/>
+/// Replies the number of keys that were deleted.
+///
+/// UNLINK removes the keys right away too, but drops large values after the DB lock is released,
+/// on a background task.
+pub(crate) async fn handle_command_del_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let is_unlink = resp_command.name == RespCommandNames::UNLINK;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut removed_records = Vec::new();
+    for key in &resp_command.parameters {
+        if let Some(record) = db_lock.remove_live_record(context.selected_db, key)? {
+            removed_records.push(record);
+        }
+    }
+
+    drop(db_lock);
+
+    let removed_count = removed_records.len();
+    if is_unlink {
+        let free_effort = removed_records
+            .iter()
+            .map(InMemoryRecord::get_free_effort)
+            .sum();
+        free_lazily(removed_records, free_effort);
+    }
+
+    if removed_count == 0 {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(removed_count as i64).into());
+
+    Ok(())
+}
+
+/// Handles EXISTS and TOUCH, e.g.: "redis-cli exists foo bar" <br/>
+/// Replies how many of the keys exist, counting a key as many times as it is given.
+pub(crate) async fn handle_command_exists_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let keys = &context.get_request_resp_command_ref().unwrap().parameters;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut existing_count = 0;
+    for key in keys {
+        if db_lock
+            .get_live_record_mut(context.selected_db, key)?
+            .is_some()
+        {
+            existing_count += 1;
+        }
+    }
+
+    context.set_response(RespValue::Integer(existing_count).into());
+
+    Ok(())
+}
+
+/// Replies the type of the value of a key, or "none" if it does not exist. <br/>
+/// E.g.: "redis-cli type foo"
+pub(crate) async fn handle_command_type_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let type_name = db_lock
+        .get_live_record_mut(context.selected_db, key)?
+        .map_or("none", |record| record.get_type_name());
+
+    context.set_response(RespValue::SimpleString(type_name.to_owned()).into());
+
+    Ok(())
+}
+
+/// Inspects the value of a key, e.g.: "redis-cli object encoding tags:1" <br/>
+/// Only the ENCODING subcommand is supported, which replies a null if the key does not exist.
+pub(crate) async fn handle_command_object_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let subcommand = &resp_command.parameters[0];
+
+    if !subcommand.eq_ignore_ascii_case(RespCommandObjectSubcommands::ENCODING.as_bytes()) {
+        return Err(CommandError::Other(format!(
+            "unknown subcommand '{}'. Try OBJECT HELP.",
+            String::from_utf8_lossy(subcommand)
+        ))
+        .into());
+    }
+    if resp_command.parameters.len() != 2 {
+        return Err(CommandError::wrong_arity("object|encoding").into());
+    }
+
+    let key = &resp_command.parameters[1];
+    let mut db_lock = context.mem_db.lock().await;
+
+    let encoding_name = db_lock
+        .get_live_record_mut(context.selected_db, key)?
+        .map(|record| record.get_encoding_name());
+
+    context.set_response(
+        encoding_name
+            .map_or(RespValue::NullBulkString, |encoding_name| {
+                RespValue::BulkString(Bytes::from_static(encoding_name.as_bytes()))
+            })
+            .into(),
+    );
+
+    Ok(())
+}
+
+/// Handles RENAME and RENAMENX, e.g.: "redis-cli rename foo bar" <br/>
+/// The key keeps its value and expiry under the new name, replacing the key that had it, if any.
+///
+/// RENAMENX does not replace an existing key, and replies whether the key was renamed.
+pub(crate) async fn handle_command_rename_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let is_renamenx = resp_command.name == RespCommandNames::RENAMENX;
+    let (key, new_key) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    if db_lock
+        .get_live_record_mut(context.selected_db, key)?
+        .is_none()
+    {
+        return Err(CommandError::Other("no such key".to_owned()).into());
+    }
+
+    let is_renamed = if is_renamenx
+        && db_lock
+            .get_live_record_mut(context.selected_db, new_key)?
+            .is_some()
+    {
+        false
+    } else {
+        if key != new_key {
+            let record = db_lock
+                .get_records_ref_mut(context.selected_db)
+                .remove(key)
+                .unwrap();
+            db_lock
+                .get_records_ref_mut(context.selected_db)
+                .insert(new_key.clone(), record);
+            db_lock
+                .blocked_clients
+                .signal_ready(context.selected_db, new_key);
+        }
+
+        // Like Redis, renaming a key to itself succeeds, but RENAMENX replies it was not renamed.
+        !is_renamenx || key != new_key
+    };
+    context.served_commands = lists::serve_blocked_clients(&mut db_lock);
+
+    if !is_renamed {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(if is_renamenx {
+        RespValue::Integer(is_renamed as i64).into()
+    } else {
+        RespValue::ok().into()
+    });
+
+    Ok(())
+}
+
+/// Copies the value and expiry of a key to another key, e.g.: <br/>
+/// "redis-cli copy foo bar" <br/>
+/// "redis-cli copy foo bar replace" (even if bar exists) <br/>
+/// "redis-cli copy foo foo db 1" (to another database)
+///
+/// Replies whether the key was copied.
+pub(crate) async fn handle_command_copy_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (source, destination) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+
+    let mut replace = false;
+    let mut destination_db = None;
+    let mut position = 2;
+    while position < resp_command.parameters.len() {
+        match resp_command
+            .get_parameter_str(position)?
+            .to_ascii_uppercase()
+            .as_str()
+        {
+            RespCommandCopyOptions::REPLACE => replace = true,
+            RespCommandCopyOptions::DB if position + 1 < resp_command.parameters.len() => {
+                position += 1;
+                destination_db = Some(resp_command.get_parameter_i64(position)?);
+            }
+            _ => return Err(CommandError::Syntax.into()),
+        }
+
+        position += 1;
+    }
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let destination_db = match destination_db {
+        None => context.selected_db,
+        Some(destination_db) => check_db_index(&db_lock, destination_db)?,
+    };
+
+    if source == destination && destination_db == context.selected_db {
+        return Err(
+            CommandError::Other("source and destination objects are the same".to_owned()).into(),
+        );
+    }
+
+    let record = db_lock
+        .get_live_record_mut(context.selected_db, source)?
+        .cloned();
+
+    let is_copied = match record {
+        None => false,
+        Some(_)
+            if !replace
+                && db_lock
+                    .get_live_record_mut(destination_db, destination)?
+                    .is_some() =>
+        {
+            false
+        }
+        Some(record) => {
+            db_lock
+                .get_records_ref_mut(destination_db)
+                .insert(destination.clone(), record);
+            db_lock
+                .blocked_clients
+                .signal_ready(destination_db, destination);
+            context.served_commands = lists::serve_blocked_clients(&mut db_lock);
+
+            true
+        }
+    };
+
+    if !is_copied {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(is_copied as i64).into());
+
+    Ok(())
+}
+
+/// Moves a key to another database, with its expiry, e.g.: "redis-cli move foo 1" <br/>
+/// Replies whether the key was moved, which it is not if the other database has the key already.
+pub(crate) async fn handle_command_move_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let destination_db = resp_command.get_parameter_i64(1)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+    let destination_db = check_db_index(&db_lock, destination_db)?;
+
+    if destination_db == context.selected_db {
+        return Err(
+            CommandError::Other("source and destination objects are the same".to_owned()).into(),
+        );
+    }
+
+    let is_moved = db_lock
+        .get_live_record_mut(context.selected_db, key)?
+        .is_some()
+        && db_lock.get_live_record_mut(destination_db, key)?.is_none();
+
+    if is_moved {
+        let record = db_lock
+            .get_records_ref_mut(context.selected_db)
+            .remove(key)
+            .unwrap();
+        db_lock
+            .get_records_ref_mut(destination_db)
+            .insert(key.clone(), record);
+        db_lock.blocked_clients.signal_ready(destination_db, key);
+        context.served_commands = lists::serve_blocked_clients(&mut db_lock);
+    } else {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(is_moved as i64).into());
+
+    Ok(())
+}
+
+/// Replies all keys matching a glob-style pattern, e.g.: "redis-cli keys 'user:*'" <br/>
+/// It walks the whole DB under its lock, so SCAN should be preferred on large DBs.
+pub(crate) async fn handle_command_keys_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let pattern = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let db_lock = context.mem_db.lock().await;
+    let now = unix_time_millis()?;
+
+    let keys = db_lock
+        .get_records_ref(context.selected_db)
+        .iter()
+        .filter(|(key, record)| {
+            record
+                .expire_at_milli
+                .is_none_or(|expire_at_milli| expire_at_milli > now)
+                && glob_match(pattern, key)
+        })
+        .map(|(key, _)| RespValue::BulkString(key.clone()))
+        .collect();
+
+    context.set_response(RespValue::Array(keys).into());
+
+    Ok(())
+}
+
+/// The options of SCAN, and of the commands that scan the elements of a key.
+#[derive(Debug)]
+pub(crate) struct ScanOptions {
+    pub pattern: Option<Bytes>,
+    pub count: usize,
+    /// Only allowed by SCAN, in lower case.
+    pub type_name: Option<String>,
+    /// Only allowed by HSCAN, which then replies the fields without their values.
+    pub no_values: bool,
+}
+
+/// What a cursor walks, which tells the options it accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ScanTarget {
+    /// SCAN, which accepts TYPE.
+    Keys,
+    /// HSCAN, which accepts NOVALUES.
+    Hash,
+    /// SSCAN, which only accepts MATCH and COUNT.
+    Set,
+}
+
+/// The types TYPE can reply, which are the ones SCAN accepts to filter keys by.
+const TYPE_NAMES: [&str; 6] = ["string", "list", "set", "zset", "hash", "stream"];
+
+/// Parses the `numkeys key [key ...]` of commands whose keys follow their count, e.g.
+/// SINTERCARD, with the count at `position`, and returns the keys.
+pub(crate) fn parse_counted_keys(
+    resp_command: &RespCommand,
+    position: usize,
+) -> Result<&[Bytes], Error> {
+    let key_count = resp_command
+        .get_parameter_i64(position)
+        .ok()
+        .and_then(|key_count| usize::try_from(key_count).ok())
+        .filter(|key_count| *key_count > 0)
+        .ok_or_else(|| CommandError::Other("numkeys should be greater than 0".to_owned()))?;
+
+    if key_count > resp_command.parameters.len() - position - 1 {
+        return Err(CommandError::Other(
+            "Number of keys can't be greater than number of args".to_owned(),
+        )
+        .into());
+    }
+
+    Ok(&resp_command.parameters[position + 1..position + 1 + key_count])
+}
+
+/// Parses the cursor at `position`, which is an unsigned 64 bit integer like in Redis.
+pub(crate) fn parse_scan_cursor(
+    resp_command: &RespCommand,
+    position: usize,
+) -> Result<usize, Error> {
+    std::str::from_utf8(&resp_command.parameters[position])
+        .ok()
+        .and_then(|cursor| cursor.parse::<u64>().ok())
+        .map(|cursor| usize::try_from(cursor).unwrap_or(usize::MAX))
+        .ok_or_else(|| CommandError::Other("invalid cursor".to_owned()).into())
+}
+
+/// Parses the MATCH and COUNT options from `position` on, and the ones only `target` accepts.
+pub(crate) fn parse_scan_options(
+    resp_command: &RespCommand,
+    position: usize,
+    target: ScanTarget,
+) -> Result<ScanOptions, Error> {
+    let mut options = ScanOptions {
+        pattern: None,
+        count: 10,
+        type_name: None,
+        no_values: false,
+    };
+
+    let mut position = position;
+    while position < resp_command.parameters.len() {
+        let option = resp_command
+            .get_parameter_str(position)?
+            .to_ascii_uppercase();
+
+        if option == RespCommandScanOptions::NOVALUES && target == ScanTarget::Hash {
+            options.no_values = true;
+            position += 1;
+
+            continue;
+        }
+
+        if position + 1 == resp_command.parameters.len() {
+            return Err(CommandError::Syntax.into());
+        }
+
+        match option.as_str() {
+            RespCommandScanOptions::MATCH => {
+                options.pattern = Some(resp_command.parameters[position + 1].clone())
+            }
+            RespCommandScanOptions::COUNT => {
+                options.count = usize::try_from(resp_command.get_parameter_i64(position + 1)?)
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or(CommandError::Syntax)?
+            }
+            RespCommandScanOptions::TYPE if target == ScanTarget::Keys => {
+                let type_name = resp_command
+                    .get_parameter_str(position + 1)?
+                    .to_ascii_lowercase();
+
+                if !TYPE_NAMES.contains(&type_name.as_str()) {
+                    return Err(
+                        CommandError::Other(format!("unknown type name '{}'", type_name)).into(),
+                    );
+                }
+
+                options.type_name = Some(type_name);
+            }
+            _ => return Err(CommandError::Syntax.into()),
+        }
+
+        position += 2;
+    }
+
+    Ok(options)
+}
+
+/// Walks the keys a few at a time, e.g.: <br/>
+/// "redis-cli scan 0 match 'user:*' count 100" <br/>
+/// "redis-cli scan 0 type string"
+///
+/// Replies the cursor to pass to the next call, which is 0 once the scan is over, and the keys
+/// found in this step. Every key that exists for the whole scan is replied once, but keys added
+/// or removed during it may be replied or not, and a step may reply no keys at all.
+pub(crate) async fn handle_command_scan_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let cursor = parse_scan_cursor(resp_command, 0)?;
+    let options = parse_scan_options(resp_command, 1, ScanTarget::Keys)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut scanned_keys = Vec::new();
+    let next_cursor =
+        db_lock
+            .get_records_ref(context.selected_db)
+            .scan(cursor, options.count, |key, record| {
+                let is_match = options
+                    .pattern
+                    .as_ref()
+                    .is_none_or(|pattern| glob_match(pattern, key))
+                    && options
+                        .type_name
+                        .as_ref()
+                        .is_none_or(|type_name| type_name == record.get_type_name());
+
+                if is_match {
+                    scanned_keys.push(key.clone());
+                }
+            });
+
+    // Expired keys are removed rather than replied.
+    let mut keys = Vec::with_capacity(scanned_keys.len());
+    for key in scanned_keys {
+        if db_lock
+            .get_live_record_mut(context.selected_db, &key)?
+            .is_some()
+        {
+            keys.push(RespValue::BulkString(key));
+        }
+    }
+
+    context.set_response(
+        RespValue::Array(vec![
+            RespValue::BulkString(Bytes::from(next_cursor.to_string())),
+            RespValue::Array(keys),
+        ])
+        .into(),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::db::in_memory_record::InMemoryRecord,
+        resp_parser::shared::RespValue,
+        test_helpers::utils::{
+            bulk_string, bulk_strings, create_test_mem_db, error, integers, send_test_request,
+        },
+        utils::unix_time_millis,
+    };
+
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn handle_command_expire_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let in_an_hour = unix_time_millis()? + 3_600_000;
+
+        let replies = send_test_request(
+            &fake_mem_db,
+            format!(
+                "SET a 1\r\nEXPIRE a 100\r\nEXPIRE nope 100\r\nPEXPIRE a 200000\r\nTTL a\r\nPTTL a\r\nEXPIREAT a {}\r\nEXPIRETIME a\r\nPEXPIREAT a {}\r\nPEXPIRETIME a\r\nPERSIST a\r\nPERSIST a\r\nTTL a\r\nTTL nope\r\nEXPIRE a -1\r\nGET a\r\n",
+                in_an_hour / 1000,
+                in_an_hour
+            )
+            .as_bytes(),
+        )
+        .await?;
+
+        assert_eq!(replies[0], RespValue::ok());
+        assert_eq!(
+            RespValue::Array(replies[1..4].to_vec()),
+            integers(&[1, 0, 1])
+        );
+        assert_eq!(replies[4], RespValue::Integer(200));
+        assert!(
+            matches!(replies[5], RespValue::Integer(pttl) if pttl > 199_000 && pttl <= 200_000)
+        );
+        assert_eq!(replies[6], RespValue::Integer(1));
+        assert_eq!(replies[7], RespValue::Integer((in_an_hour / 1000) as i64));
+        assert_eq!(replies[8], RespValue::Integer(1));
+        assert_eq!(replies[9], RespValue::Integer(in_an_hour as i64));
+        assert_eq!(
+            RespValue::Array(replies[10..15].to_vec()),
+            integers(&[1, 0, -1, -2, 1])
+        );
+        assert_eq!(replies[15], RespValue::NullBulkString);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_expire_async_handles_conditions() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a 1\r\nEXPIRE a 100 XX\r\nEXPIRE a 100 GT\r\nEXPIRE a 100 LT\r\nEXPIRE a 50 NX\r\nEXPIRE a 200 lt\r\nEXPIRE a 200 GT\r\nEXPIRE a 300 XX GT\r\nEXPIRE a 50 LT\r\nTTL a\r\n",
+            )
+            .await?[1..],
+            [0, 0, 1, 0, 0, 1, 1, 1, 50].map(RespValue::Integer)
+        );
+
+        // XX still requires an expiry when combined with LT, in either order.
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET p 1\r\nEXPIRE p 10 XX LT\r\nEXPIRE p 10 LT XX\r\nTTL p\r\nEXPIRE a 40 XX LT\r\nEXPIRE a 45 LT XX\r\nTTL a\r\n",
+            )
+            .await?[1..],
+            [0, 0, -1, 1, 0, 40].map(RespValue::Integer)
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"EXPIRE a 10 NX XX\r\nEXPIRE a 10 GT LT\r\nEXPIRE a 10 XX LT GT\r\nEXPIRE a 10 FOO\r\nEXPIRE a 1.5\r\nEXPIRE a 9223372036854775807\r\n",
+            )
+            .await?,
+            vec![
+                error("ERR NX and XX, GT or LT options at the same time are not compatible"),
+                error("ERR GT and LT options at the same time are not compatible"),
+                error("ERR GT and LT options at the same time are not compatible"),
+                error("ERR Unsupported option FOO"),
+                error("ERR value is not an integer or out of range"),
+                error("ERR invalid expire time in 'expire' command"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn writes_keep_or_clear_the_expiry_like_redis() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a 1 EX 100\r\nINCR a\r\nAPPEND a 0\r\nTTL a\r\nSET a 2 KEEPTTL\r\nTTL a\r\nGETEX a PERSIST\r\nTTL a\r\nGETEX a PX 5000\r\nTTL a\r\nSET a 3\r\nTTL a\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::Integer(2),
+                RespValue::Integer(2),
+                RespValue::Integer(100),
+                RespValue::ok(),
+                RespValue::Integer(100),
+                RespValue::BulkString(Bytes::from("2")),
+                RespValue::Integer(-1),
+                RespValue::BulkString(Bytes::from("2")),
+                RespValue::Integer(5),
+                RespValue::ok(),
+                RespValue::Integer(-1),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_del_async_and_exists_async_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        {
+            let mut db_lock = fake_mem_db.lock().await;
+            db_lock.get_records_ref_mut(0).insert(
+                Bytes::from("expired"),
+                InMemoryRecord::new(Bytes::from("v"), Some(unix_time_millis()? - 1)),
+            );
+        }
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a 1\r\nSET b 2\r\nEXISTS a b a c expired\r\nTOUCH a c\r\nDEL a c expired\r\nUNLINK b a\r\nEXISTS a b\r\nDEL\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::ok(),
+                RespValue::Integer(3),
+                RespValue::Integer(1),
+                RespValue::Integer(1),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                error("ERR wrong number of arguments for 'del' command"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_type_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(&fake_mem_db, b"SET a 1\r\nTYPE a\r\nTYPE nope\r\n").await?,
+            vec![
+                RespValue::ok(),
+                RespValue::SimpleString("string".to_owned()),
+                RespValue::SimpleString("none".to_owned()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_object_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let long_value = "x".repeat(45);
+
+        let encoding = |name: &str| RespValue::BulkString(Bytes::from(name.to_owned()));
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                format!(
+                    "SET i -12\r\nSET e 012\r\nSET r {}\r\nRPUSH l a\r\nHSET h f v\r\nOBJECT ENCODING i\r\nOBJECT encoding e\r\nOBJECT ENCODING r\r\nOBJECT ENCODING l\r\nOBJECT ENCODING h\r\nOBJECT ENCODING nope\r\nOBJECT ENCODING i e\r\nOBJECT FREQ i\r\n",
+                    long_value
+                )
+                .as_bytes(),
+            )
+            .await?[5..],
+            [
+                encoding("int"),
+                encoding("embstr"),
+                encoding("raw"),
+                encoding("quicklist"),
+                encoding("hashtable"),
+                RespValue::NullBulkString,
+                error("ERR wrong number of arguments for 'object|encoding' command"),
+                error("ERR unknown subcommand 'FREQ'. Try OBJECT HELP."),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_rename_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a 1 EX 100\r\nRENAME a b\r\nGET a\r\nGET b\r\nTTL b\r\nRENAME nope c\r\nRENAME b b\r\nRENAMENX b b\r\nSET c 3\r\nRENAMENX b c\r\nRENAMENX b d\r\nRENAME d c\r\nGET c\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::ok(),
+                RespValue::NullBulkString,
+                RespValue::BulkString(Bytes::from("1")),
+                RespValue::Integer(100),
+                error("ERR no such key"),
+                RespValue::ok(),
+                RespValue::Integer(0),
+                RespValue::ok(),
+                RespValue::Integer(0),
+                RespValue::Integer(1),
+                RespValue::ok(),
+                RespValue::BulkString(Bytes::from("1")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_copy_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a 1 EX 100\r\nCOPY a b\r\nCOPY a b\r\nTTL b\r\nSET a 2\r\nCOPY a b replace\r\nGET b\r\nTTL b\r\nCOPY nope c\r\nCOPY a c DB 0\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                RespValue::Integer(100),
+                RespValue::ok(),
+                RespValue::Integer(1),
+                bulk_string("2"),
+                RespValue::Integer(-1),
+                RespValue::Integer(0),
+                RespValue::Integer(1),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"COPY a a\r\nCOPY a b DB 16\r\nCOPY a b DB\r\nCOPY a b FOO\r\n",
+            )
+            .await?,
+            vec![
+                error("ERR source and destination objects are the same"),
+                error("ERR DB index is out of range"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_keys_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let replies = send_test_request(
+            &fake_mem_db,
+            b"MSET user:1 a user:2 b other c\r\nSET user:3 d PX 1\r\nKEYS user:[12]\r\nKEYS nope*\r\n",
+        )
+        .await?;
+
+        let mut keys = match &replies[2] {
+            RespValue::Array(keys) => keys.clone(),
+            reply => panic!("unexpected reply: {:?}", reply),
+        };
+        keys.sort_by_key(|key| format!("{:?}", key));
+
+        assert_eq!(RespValue::Array(keys), bulk_strings(&["user:1", "user:2"]));
+        assert_eq!(replies[3], RespValue::Array(vec![]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_scan_async_replies_every_key_once() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut request = Vec::new();
+        for i in 0..200 {
+            request.extend_from_slice(format!("SET key:{} v\r\n", i).as_bytes());
+        }
+        send_test_request(&fake_mem_db, &request).await?;
+
+        let mut scanned_keys = Vec::new();
+        let mut cursor = "0".to_owned();
+
+        loop {
+            let replies = send_test_request(
+                &fake_mem_db,
+                format!("SCAN {} MATCH key:1* COUNT 15 TYPE STRING\r\n", cursor).as_bytes(),
+            )
+            .await?;
+
+            match &replies[0] {
+                RespValue::Array(reply) => match &reply[..] {
+                    [RespValue::BulkString(next_cursor), RespValue::Array(keys)] => {
+                        cursor = String::from_utf8(next_cursor.to_vec())?;
+                        scanned_keys.extend(keys.iter().cloned());
+                    }
+                    _ => panic!("unexpected reply: {:?}", reply),
+                },
+                reply => panic!("unexpected reply: {:?}", reply),
+            }
+
+            if cursor == "0" {
+                break;
+            }
+        }
+
+        // key:1, key:10 to key:19 and key:100 to key:199.
+        assert_eq!(scanned_keys.len(), 111);
+        scanned_keys.sort_by_key(|key| format!("{:?}", key));
+        scanned_keys.dedup();
+        assert_eq!(scanned_keys.len(), 111);
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SCAN 0 TYPE list\r\nSCAN abc\r\nSCAN 0 COUNT 0\r\nSCAN 0 MATCH\r\nSCAN 0 TYPE foo\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Array(vec![
+                    RespValue::BulkString(Bytes::from("10")),
+                    RespValue::Array(vec![]),
+                ]),
+                error("ERR invalid cursor"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR unknown type name 'foo'"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_move_async_and_copy_to_another_db_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a 1 EX 100\r\nMOVE a 1\r\nMOVE a 1\r\nSELECT 1\r\nTTL a\r\nCOPY a a DB 0\r\nSET b 1\r\nSELECT 0\r\nSET b 0\r\nMOVE b 1\r\nMOVE b 0\r\nMOVE b 16\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                RespValue::ok(),
+                RespValue::Integer(100),
+                RespValue::Integer(1),
+                RespValue::ok(),
+                RespValue::ok(),
+                RespValue::ok(),
+                RespValue::Integer(0),
+                error("ERR source and destination objects are the same"),
+                error("ERR DB index is out of range"),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(&fake_mem_db, b"TTL a\r\nGET b\r\n").await?,
+            vec![
+                RespValue::Integer(100),
+                RespValue::BulkString(Bytes::from("0"))
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1412 @@
+use crate::{
+    models::{
+        command_error::CommandError,
+        connection_context::{ConnectionContext, Propagation},
+        db::{
+            blocked_clients::{BlockedClient, BlockedListOperation, ServedList},
+            in_memory_db::InMemoryDb,
+            in_memory_record::{InMemoryRecord, ListEnd, RecordValue},
+        },
+    },
+    resp_parser::shared::{
+        RespCommand, RespCommandLInsertOptions, RespCommandLMPopOptions, RespCommandLPosOptions,
+        RespCommandListDirections, RespCommandNames, RespValue,
+    },
+    TCP_READ_TIMEOUT, TCP_RESPONSE_BUFFER_SIZE,
+};
+
+use std::{collections::VecDeque, time::Duration};
+
+use anyhow::{Error, Ok};
+use bytes::Bytes;
+use tokio::{io::AsyncReadExt, sync::oneshot, time::Instant};
+
+/// Parses LEFT or RIGHT, e.g. the directions of LMOVE.
+pub(crate) fn parse_list_end(
+    resp_command: &RespCommand,
+    position: usize,
+) -> Result<ListEnd, Error> {
+    match resp_command
+        .get_parameter_str(position)?
+        .to_ascii_uppercase()
+        .as_str()
+    {
+        RespCommandListDirections::LEFT => Ok(ListEnd::Left),
+        RespCommandListDirections::RIGHT => Ok(ListEnd::Right),
+        _ => Err(CommandError::Syntax.into()),
+    }
+}
+
+/// The list of `key` if it exists, or a WRONGTYPE error if the key holds another type.
+pub(crate) fn get_live_list_mut<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+) -> Result<Option<&'a mut VecDeque<Bytes>>, Error> {
+    match db.get_live_record_mut(db_index, key)? {
+        None => Ok(None),
+        Some(record) => record.get_list_mut().map(Some),
+    }
+}
+
+/// The list of `key`, which is created empty if the key does not exist.
+fn get_or_create_list_mut<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+) -> Result<&'a mut VecDeque<Bytes>, Error> {
+    if get_live_list_mut(db, db_index, key)?.is_none() {
+        db.get_records_ref_mut(db_index).insert(
+            key.clone(),
+            InMemoryRecord::new(RecordValue::List(VecDeque::new()), None),
+        );
+    }
+
+    Ok(get_live_list_mut(db, db_index, key)?.unwrap())
+}
+
+/// Pops an element from one end of the list of `source` and pushes it to one end of the list of
+/// `destination`, which may be the same list to rotate it. <br/>
+/// Returns the element, or `None` if `source` does not exist.
+pub(crate) fn move_list_element(
+    db: &mut InMemoryDb,
+    db_index: usize,
+    (source, from): (&Bytes, ListEnd),
+    (destination, to): (&Bytes, ListEnd),
+) -> Result<Option<Bytes>, Error> {
+    // The destination is checked first, so that no element is popped if it can't be pushed.
+    get_live_list_mut(db, db_index, destination)?;
+
+    let element = match get_live_list_mut(db, db_index, source)? {
+        None => return Ok(None),
+        Some(list) => from.pop(list).unwrap(),
+    };
+    db.remove_if_empty(db_index, source);
+
+    to.push(
+        get_or_create_list_mut(db, db_index, destination)?,
+        element.clone(),
+    );
+    db.blocked_clients.signal_ready(db_index, destination);
+
+    Ok(Some(element))
+}
+
+/// Converts the inclusive indexes of LRANGE and LTRIM, where negative indexes count from the end
+/// (-1 is the last element), to the bounds of the elements they cover, if any.
+fn get_list_range(start: i64, stop: i64, length: usize) -> Option<(usize, usize)> {
+    let length = length as i64;
+    let start = if start < 0 {
+        (length + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 { length + stop } else { stop };
+
+    if start > stop || start >= length {
+        return None;
+    }
+
+    Some((start as usize, stop.min(length - 1) as usize))
+}
+
+/// Handles LPUSH and RPUSH, e.g.: "redis-cli rpush jobs a b c" <br/>
+/// Replies the length of the list after the push.
+pub(crate) async fn handle_command_push_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let end = if resp_command.name == RespCommandNames::LPUSH {
+        ListEnd::Left
+    } else {
+        ListEnd::Right
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+    let list = get_or_create_list_mut(&mut db_lock, context.selected_db, key)?;
+
+    for element in &resp_command.parameters[1..] {
+        end.push(list, element.clone());
+    }
+    let length = list.len();
+
+    db_lock
+        .blocked_clients
+        .signal_ready(context.selected_db, key);
+    context.served_commands = serve_blocked_clients(&mut db_lock);
+
+    context.set_response(RespValue::Integer(length as i64).into());
+
+    Ok(())
+}
+
+/// Handles LPOP and RPOP, e.g.: <br/>
+/// "redis-cli lpop jobs" (replies an element) <br/>
+/// "redis-cli lpop jobs 2" (replies an array of up to 2 elements)
+pub(crate) async fn handle_command_pop_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let end = if resp_command.name == RespCommandNames::LPOP {
+        ListEnd::Left
+    } else {
+        ListEnd::Right
+    };
+
+    let count = match resp_command.parameters.len() {
+        1 => None,
+        2 => Some(
+            usize::try_from(resp_command.get_parameter_i64(1)?).map_err(|_| {
+                CommandError::Other("value is out of range, must be positive".to_owned())
+            })?,
+        ),
+        _ => return Err(CommandError::wrong_arity(&resp_command.name).into()),
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let list = match get_live_list_mut(&mut db_lock, context.selected_db, key)? {
+        None => {
+            context.propagation = Propagation::Rewritten(Vec::new());
+            context.set_response(match count {
+                None => RespValue::NullBulkString.into(),
+                Some(_) => RespValue::NullArray.into(),
+            });
+
+            return Ok(());
+        }
+        Some(list) => list,
+    };
+
+    let response = match count {
+        None => RespValue::BulkString(end.pop(list).unwrap()),
+        Some(count) => RespValue::Array(
+            (0..count.min(list.len()))
+                .map(|_| RespValue::BulkString(end.pop(list).unwrap()))
+                .collect(),
+        ),
+    };
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    if count == Some(0) {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Replies the elements between two inclusive indexes, where negative indexes count from the end.
+/// <br/>
+/// E.g.: "redis-cli lrange jobs 0 -1" (the whole list)
+pub(crate) async fn handle_command_lrange_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let start = resp_command.get_parameter_i64(1)?;
+    let stop = resp_command.get_parameter_i64(2)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let elements = match get_live_list_mut(&mut db_lock, context.selected_db, key)? {
+        None => Vec::new(),
+        Some(list) => match get_list_range(start, stop, list.len()) {
+            None => Vec::new(),
+            Some((start, stop)) => list
+                .range(start..=stop)
+                .map(|element| RespValue::BulkString(element.clone()))
+                .collect(),
+        },
+    };
+
+    context.set_response(RespValue::Array(elements).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli llen jobs" <br/>
+/// Replies 0 if the key does not exist.
+pub(crate) async fn handle_command_llen_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let length =
+        get_live_list_mut(&mut db_lock, context.selected_db, key)?.map_or(0, |list| list.len());
+
+    context.set_response(RespValue::Integer(length as i64).into());
+
+    Ok(())
+}
+
+/// Replies the element at an index, where negative indexes count from the end, or a null if it
+/// is out of range. <br/>
+/// E.g.: "redis-cli lindex jobs -1" (the last element)
+pub(crate) async fn handle_command_lindex_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let index = resp_command.get_parameter_i64(1)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let element = get_live_list_mut(&mut db_lock, context.selected_db, key)?.and_then(|list| {
+        let index = if index < 0 {
+            list.len() as i64 + index
+        } else {
+            index
+        };
+
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| list.get(index))
+            .cloned()
+    });
+
+    context.set_response(
+        match element {
+            None => RespValue::NullBulkString,
+            Some(element) => RespValue::BulkString(element),
+        }
+        .into(),
+    );
+
+    Ok(())
+}
+
+/// Replaces the element at an index, where negative indexes count from the end. <br/>
+/// E.g.: "redis-cli lset jobs 0 first"
+pub(crate) async fn handle_command_lset_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let index = resp_command.get_parameter_i64(1)?;
+    let element = &resp_command.parameters[2];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let list = match get_live_list_mut(&mut db_lock, context.selected_db, key)? {
+        None => return Err(CommandError::Other("no such key".to_owned()).into()),
+        Some(list) => list,
+    };
+
+    let index = if index < 0 {
+        list.len() as i64 + index
+    } else {
+        index
+    };
+
+    match usize::try_from(index)
+        .ok()
+        .and_then(|index| list.get_mut(index))
+    {
+        None => return Err(CommandError::Other("index out of range".to_owned()).into()),
+        Some(current_element) => *current_element = element.clone(),
+    }
+
+    context.set_response(RespValue::ok().into());
+
+    Ok(())
+}
+
+/// Removes the elements equal to a value, and replies how many were removed, e.g.: <br/>
+/// "redis-cli lrem jobs 2 a" (the first 2 from the head) <br/>
+/// "redis-cli lrem jobs -2 a" (the first 2 from the tail) <br/>
+/// "redis-cli lrem jobs 0 a" (all of them)
+pub(crate) async fn handle_command_lrem_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let count = resp_command.get_parameter_i64(1)?;
+    let element = &resp_command.parameters[2];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let removed_count = match get_live_list_mut(&mut db_lock, context.selected_db, key)? {
+        None => 0,
+        Some(list) => {
+            let limit = if count == 0 {
+                usize::MAX
+            } else {
+                usize::try_from(count.unsigned_abs()).unwrap_or(usize::MAX)
+            };
+
+            // Removing the last matches is removing all matches but the first ones.
+            let kept_matches = if count < 0 {
+                let matches = list.iter().filter(|current| *current == element).count();
+                matches.saturating_sub(limit)
+            } else {
+                0
+            };
+
+            let mut seen_matches = 0;
+            let mut removed_count = 0;
+            list.retain(|current| {
+                if current != element {
+                    return true;
+                }
+
+                seen_matches += 1;
+                if seen_matches <= kept_matches || removed_count == limit {
+                    return true;
+                }
+
+                removed_count += 1;
+                false
+            });
+
+            removed_count
+        }
+    };
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    if removed_count == 0 {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(removed_count as i64).into());
+
+    Ok(())
+}
+
+/// Keeps only the elements between two inclusive indexes, where negative indexes count from the
+/// end. <br/>
+/// E.g.: "redis-cli ltrim logs 0 99" (keep the first 100)
+pub(crate) async fn handle_command_ltrim_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let start = resp_command.get_parameter_i64(1)?;
+    let stop = resp_command.get_parameter_i64(2)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    if let Some(list) = get_live_list_mut(&mut db_lock, context.selected_db, key)? {
+        match get_list_range(start, stop, list.len()) {
+            None => list.clear(),
+            Some((start, stop)) => {
+                list.truncate(stop + 1);
+                list.drain(..start);
+            }
+        }
+    }
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    context.set_response(RespValue::ok().into());
+
+    Ok(())
+}
+
+/// Inserts an element before or after the first element equal to a pivot, e.g.: <br/>
+/// "redis-cli linsert jobs before b a"
+///
+/// Replies the new length, -1 if the pivot was not found, or 0 if the key does not exist.
+pub(crate) async fn handle_command_linsert_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let (pivot, element) = (&resp_command.parameters[2], &resp_command.parameters[3]);
+
+    let offset = match resp_command
+        .get_parameter_str(1)?
+        .to_ascii_uppercase()
+        .as_str()
+    {
+        RespCommandLInsertOptions::BEFORE => 0,
+        RespCommandLInsertOptions::AFTER => 1,
+        _ => return Err(CommandError::Syntax.into()),
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let response = match get_live_list_mut(&mut db_lock, context.selected_db, key)? {
+        None => 0,
+        Some(list) => match list.iter().position(|current| current == pivot) {
+            None => -1,
+            Some(position) => {
+                list.insert(position + offset, element.clone());
+
+                list.len() as i64
+            }
+        },
+    };
+
+    if response <= 0 {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    } else {
+        db_lock
+            .blocked_clients
+            .signal_ready(context.selected_db, key);
+        context.served_commands = serve_blocked_clients(&mut db_lock);
+    }
+
+    context.set_response(RespValue::Integer(response).into());
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct LPosOptions {
+    /// Which match to start from, counting from the tail if negative.
+    rank: i64,
+    /// How many matches to reply, all of them if 0, or only the first if not set.
+    count: Option<usize>,
+    /// How many elements to compare at most, all of them if 0.
+    max_len: usize,
+}
+
+fn parse_lpos_options(resp_command: &RespCommand) -> Result<LPosOptions, Error> {
+    let mut options = LPosOptions {
+        rank: 1,
+        count: None,
+        max_len: 0,
+    };
+
+    let mut position = 2;
+    while position < resp_command.parameters.len() {
+        if position + 1 == resp_command.parameters.len() {
+            return Err(CommandError::Syntax.into());
+        }
+
+        let option = resp_command
+            .get_parameter_str(position)?
+            .to_ascii_uppercase();
+        let value = resp_command.get_parameter_i64(position + 1)?;
+
+        match option.as_str() {
+            RespCommandLPosOptions::RANK if value == 0 => return Err(CommandError::Other(
+                "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_owned(),
+            )
+            .into()),
+            RespCommandLPosOptions::RANK if value == i64::MIN => return Err(CommandError::Other(
+                "value is out of range, value must between -9223372036854775807 and 9223372036854775807".to_owned(),
+            )
+            .into()),
+            RespCommandLPosOptions::RANK => options.rank = value,
+            RespCommandLPosOptions::COUNT => {
+                options.count = Some(usize::try_from(value).map_err(|_| {
+                    CommandError::Other("COUNT can't be negative".to_owned())
+                })?)
+            }
+            RespCommandLPosOptions::MAXLEN => {
+                options.max_len = usize::try_from(value).map_err(|_| {
+                    CommandError::Other("MAXLEN can't be negative".to_owned())
+                })?
+            }
+            _ => return Err(CommandError::Syntax.into()),
+        }
+
+        position += 2;
+    }
+
+    Ok(options)
+}
+
+/// Replies the index of the elements equal to a value, e.g.: <br/>
+/// "redis-cli lpos jobs a" (the index of the first match) <br/>
+/// "redis-cli lpos jobs a rank -1" (the index of the last match) <br/>
+/// "redis-cli lpos jobs a count 0 maxlen 100" (all matches in the first 100 elements)
+pub(crate) async fn handle_command_lpos_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let element = &resp_command.parameters[1];
+    let options = parse_lpos_options(resp_command)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut indexes = Vec::new();
+    if let Some(list) = get_live_list_mut(&mut db_lock, context.selected_db, key)? {
+        let max_len = if options.max_len == 0 {
+            list.len()
+        } else {
+            options.max_len.min(list.len())
+        };
+        let max_matches = match options.count {
+            None => 1,
+            Some(0) => usize::MAX,
+            Some(count) => count,
+        };
+        let skipped_matches = (options.rank.unsigned_abs() - 1) as usize;
+
+        let compared_indexes: Box<dyn Iterator<Item = usize>> = if options.rank > 0 {
+            Box::new(0..max_len)
+        } else {
+            Box::new((list.len() - max_len..list.len()).rev())
+        };
+
+        indexes = compared_indexes
+            .filter(|index| list[*index] == element)
+            .skip(skipped_matches)
+            .take(max_matches)
+            .map(|index| RespValue::Integer(index as i64))
+            .collect();
+    }
+
+    context.set_response(
+        match options.count {
+            Some(_) => RespValue::Array(indexes),
+            None => indexes.pop().unwrap_or(RespValue::NullBulkString),
+        }
+        .into(),
+    );
+
+    Ok(())
+}
+
+/// Pops an element from one end of a list and pushes it to one end of another, e.g.: <br/>
+/// "redis-cli lmove jobs processing left right" <br/>
+/// "redis-cli lmove jobs jobs left right" (rotates the list)
+///
+/// Replies the element, or a null if the source list does not exist.
+pub(crate) async fn handle_command_lmove_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (source, destination) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+    let from = parse_list_end(resp_command, 2)?;
+    let to = parse_list_end(resp_command, 3)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let response = match move_list_element(
+        &mut db_lock,
+        context.selected_db,
+        (source, from),
+        (destination, to),
+    )? {
+        None => {
+            context.propagation = Propagation::Rewritten(Vec::new());
+
+            RespValue::NullBulkString
+        }
+        Some(element) => {
+            context.served_commands = serve_blocked_clients(&mut db_lock);
+
+            RespValue::BulkString(element)
+        }
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Parses the timeout of blocking commands, in seconds with decimals. <br/>
+/// Returns `None` for a timeout of 0, which blocks forever.
+fn parse_block_timeout(
+    resp_command: &RespCommand,
+    position: usize,
+) -> Result<Option<Duration>, Error> {
+    let timeout = resp_command
+        .get_parameter_f64(position)
+        .map_err(|_| CommandError::Other("timeout is not a float or out of range".to_owned()))?;
+
+    if timeout < 0.0 {
+        return Err(CommandError::Other("timeout is negative".to_owned()).into());
+    }
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+
+    Duration::try_from_secs_f64(timeout)
+        .map(Some)
+        .map_err(|_| CommandError::Other("timeout is out of range".to_owned()).into())
+}
+
+/// Runs the operation of a blocking command on the list of `key`, which is expected to exist.
+fn serve_blocked_client(
+    db: &mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+    operation: &BlockedListOperation,
+) -> Result<ServedList, Error> {
+    let elements = match operation {
+        BlockedListOperation::Pop { end, count } => {
+            let list = get_live_list_mut(db, db_index, key)?.unwrap();
+            let elements = (0..(*count).min(list.len()))
+                .map(|_| end.pop(list).unwrap())
+                .collect();
+            db.remove_if_empty(db_index, key);
+
+            elements
+        }
+        BlockedListOperation::Move {
+            from,
+            destination,
+            to,
+        } => move_list_element(db, db_index, (key, *from), (destination, *to))?
+            .into_iter()
+            .collect(),
+    };
+
+    Ok(ServedList {
+        key: key.clone(),
+        elements,
+    })
+}
+
+/// The command replicas are sent for what a blocking command was served, since they must not
+/// block: the pop or the LMOVE it ran.
+fn get_served_command(operation: &BlockedListOperation, served: &ServedList) -> RespCommand {
+    let get_direction = |end: &ListEnd| match end {
+        ListEnd::Left => Bytes::from(RespCommandListDirections::LEFT),
+        ListEnd::Right => Bytes::from(RespCommandListDirections::RIGHT),
+    };
+
+    match operation {
+        BlockedListOperation::Pop { end, count } => {
+            let pop_command_name = match end {
+                ListEnd::Left => RespCommandNames::LPOP,
+                ListEnd::Right => RespCommandNames::RPOP,
+            };
+            let mut parameters = vec![served.key.clone()];
+            if *count > 1 {
+                parameters.push(Bytes::from(served.elements.len().to_string()));
+            }
+
+            RespCommand::new(pop_command_name, parameters)
+        }
+        BlockedListOperation::Move {
+            from,
+            destination,
+            to,
+        } => RespCommand::new(
+            RespCommandNames::LMOVE,
+            vec![
+                served.key.clone(),
+                destination.clone(),
+                get_direction(from),
+                get_direction(to),
+            ],
+        ),
+    }
+}
+
+/// Serves the clients blocked on the keys recorded as ready, in the order they blocked. <br/>
+/// Called by the commands that give keys elements before they release the DB lock, so that no
+/// other command can take the elements first. Keeps going until no key is ready, since BLMOVE
+/// pushes to a list other clients may be blocked on.
+///
+/// Returns the commands replicas are sent for what was served, with their database index, which
+/// the command keeps in `ConnectionContext::served_commands` to propagate them after itself.
+pub(crate) fn serve_blocked_clients(db: &mut InMemoryDb) -> Vec<(usize, RespCommand)> {
+    let mut served_commands = Vec::new();
+
+    loop {
+        let ready_keys = db.blocked_clients.take_ready_keys();
+        if ready_keys.is_empty() {
+            break;
+        }
+
+        for (db_index, key) in ready_keys {
+            while let Some(id) = db.blocked_clients.get_first_blocked_on(db_index, &key) {
+                // Like in Redis, a key of another type does not wake up the clients blocked on it.
+                if !get_live_list_mut(db, db_index, &key).is_ok_and(|list| list.is_some()) {
+                    break;
+                }
+
+                let client = db.blocked_clients.unblock(id).unwrap();
+
+                // The client went away without unblocking, e.g. its connection task was dropped.
+                if client.sender.is_closed() {
+                    continue;
+                }
+
+                let served = serve_blocked_client(db, db_index, &key, &client.operation);
+                if let Result::Ok(served) = &served {
+                    served_commands.push((db_index, get_served_command(&client.operation, served)));
+                }
+                let _ = client.sender.send(served);
+            }
+        }
+    }
+
+    served_commands
+}
+
+/// Runs `operation` on the first of `keys` that holds a list, or parks the connection until a
+/// push from another connection serves it, or `timeout` runs out (returns `None`). <br/>
+/// Sets what the command propagates: what it ran if it did not block, and nothing otherwise,
+/// since what a blocked client is served is propagated by the push that served it.
+async fn pop_or_block_async(
+    context: &mut ConnectionContext<'_>,
+    keys: Vec<Bytes>,
+    operation: BlockedListOperation,
+    timeout: Option<Duration>,
+) -> Result<Option<ServedList>, Error> {
+    let mut db_lock = context.mem_db.lock().await;
+
+    for key in &keys {
+        if get_live_list_mut(&mut db_lock, context.selected_db, key)?.is_some() {
+            let served = serve_blocked_client(&mut db_lock, context.selected_db, key, &operation)?;
+            // BLMOVE may push to a list other clients are blocked on.
+            context.served_commands = serve_blocked_clients(&mut db_lock);
+            drop(db_lock);
+
+            context.propagation =
+                Propagation::Rewritten(vec![get_served_command(&operation, &served)]);
+
+            return Ok(Some(served));
+        }
+    }
+
+    // The client is blocked under the same lock the keys were checked with, so that a push
+    // can't slip in between.
+    let (sender, receiver) = oneshot::channel();
+    let id = db_lock.blocked_clients.block(BlockedClient {
+        db_index: context.selected_db,
+        keys,
+        operation,
+        sender,
+    });
+
+    drop(db_lock);
+
+    context.propagation = Propagation::Rewritten(Vec::new());
+
+    wait_until_served_async(context, id, receiver, timeout).await
+}
+
+/// Waits without holding the DB lock until the blocked client `id` is served, times out, or
+/// disconnects. <br/>
+/// The stream is read meanwhile, so that a client that disconnects is unblocked instead of being
+/// served elements it would never receive. Commands it pipelines after the blocking one are kept
+/// in the request buffer, and handled once it is unblocked. <br/>
+/// Like the read loop of the connection, each read locks the stream for at most
+/// `TCP_READ_TIMEOUT`, so that e.g. propagating to a replica on this stream is not held back
+/// for as long as the client is blocked.
+async fn wait_until_served_async(
+    context: &mut ConnectionContext<'_>,
+    id: u64,
+    mut receiver: oneshot::Receiver<Result<ServedList, Error>>,
+    timeout: Option<Duration>,
+) -> Result<Option<ServedList>, Error> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let timeout_elapsed = async {
+        match deadline {
+            None => std::future::pending().await,
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+        }
+    };
+    tokio::pin!(timeout_elapsed);
+
+    let tcp_stream = context.request.tcp_stream;
+    let mut read_chunk = [0; TCP_RESPONSE_BUFFER_SIZE];
+
+    loop {
+        let mut tcp_stream_lock = tcp_stream.lock().await;
+        let read = tokio::time::timeout(TCP_READ_TIMEOUT, tcp_stream_lock.read(&mut read_chunk));
+
+        tokio::select! {
+            served = &mut receiver => match served {
+                Result::Ok(served) => return served.map(Some),
+                Err(_) => break,
+            },
+            _ = &mut timeout_elapsed => break,
+            read_result = read => match read_result {
+                // The stream is unlocked for a moment before it is read again.
+                Err(_) => continue,
+                Result::Ok(Result::Ok(byte_count)) if byte_count > 0 => context
+                    .request
+                    .buffer
+                    .extend_from_slice(&read_chunk[..byte_count]),
+                _ => break,
+            },
+        }
+    }
+
+    // The client may have been served right before it was unblocked, in which case it still
+    // replies what it popped, so that no element is lost.
+    context.mem_db.lock().await.blocked_clients.unblock(id);
+
+    match receiver.try_recv() {
+        Result::Ok(served) => served.map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Handles BLPOP and BRPOP, which pop an element from the first of the lists that is not empty,
+/// or block until one gets elements, e.g.: <br/>
+/// "redis-cli blpop jobs:high jobs:low 5" (waits up to 5 seconds) <br/>
+/// "redis-cli brpop jobs 0" (waits forever)
+///
+/// Replies the key and the element, or a null if it timed out.
+pub(crate) async fn handle_command_bpop_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let end = if resp_command.name == RespCommandNames::BLPOP {
+        ListEnd::Left
+    } else {
+        ListEnd::Right
+    };
+    let timeout_position = resp_command.parameters.len() - 1;
+    let timeout = parse_block_timeout(resp_command, timeout_position)?;
+    let keys = resp_command.parameters[..timeout_position].to_vec();
+
+    let served = pop_or_block_async(
+        context,
+        keys,
+        BlockedListOperation::Pop { end, count: 1 },
+        timeout,
+    )
+    .await?;
+
+    let response = match served {
+        None => RespValue::NullArray,
+        Some(ServedList { key, mut elements }) => RespValue::Array(vec![
+            RespValue::BulkString(key),
+            RespValue::BulkString(elements.pop().unwrap()),
+        ]),
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// The blocking LMOVE, which waits for the source list to get elements, e.g.: <br/>
+/// "redis-cli blmove jobs processing left right 5"
+///
+/// Replies the element, or a null if it timed out.
+pub(crate) async fn handle_command_blmove_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let source = resp_command.parameters[0].clone();
+    let destination = resp_command.parameters[1].clone();
+    let from = parse_list_end(resp_command, 2)?;
+    let to = parse_list_end(resp_command, 3)?;
+    let timeout = parse_block_timeout(resp_command, 4)?;
+
+    let served = pop_or_block_async(
+        context,
+        vec![source],
+        BlockedListOperation::Move {
+            from,
+            destination,
+            to,
+        },
+        timeout,
+    )
+    .await?;
+
+    let response = match served.and_then(|mut served| served.elements.pop()) {
+        None => RespValue::NullBulkString,
+        Some(element) => RespValue::BulkString(element),
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Pops up to COUNT elements (1 by default) from the first of the lists that is not empty, or
+/// blocks until one gets elements, e.g.: <br/>
+/// "redis-cli blmpop 5 2 jobs:high jobs:low left count 10"
+///
+/// Replies the key and an array of the elements, or a null if it timed out.
+pub(crate) async fn handle_command_blmpop_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let timeout = parse_block_timeout(resp_command, 0)?;
+
+    let key_count = resp_command
+        .get_parameter_i64(1)
+        .ok()
+        .and_then(|key_count| usize::try_from(key_count).ok())
+        .filter(|key_count| *key_count > 0)
+        .ok_or_else(|| CommandError::Other("numkeys should be greater than 0".to_owned()))?;
+    if key_count > resp_command.parameters.len() - 3 {
+        return Err(CommandError::Other(
+            "Number of keys can't be greater than number of args".to_owned(),
+        )
+        .into());
+    }
+
+    let keys = resp_command.parameters[2..2 + key_count].to_vec();
+    let end = parse_list_end(resp_command, 2 + key_count)?;
+
+    let count = match &resp_command.parameters[3 + key_count..] {
+        [] => 1,
+        [option, _] if option.eq_ignore_ascii_case(RespCommandLMPopOptions::COUNT.as_bytes()) => {
+            resp_command
+                .get_parameter_i64(4 + key_count)
+                .ok()
+                .and_then(|count| usize::try_from(count).ok())
+                .filter(|count| *count > 0)
+                .ok_or_else(|| CommandError::Other("count should be greater than 0".to_owned()))?
+        }
+        _ => return Err(CommandError::Syntax.into()),
+    };
+
+    let served = pop_or_block_async(
+        context,
+        keys,
+        BlockedListOperation::Pop { end, count },
+        timeout,
+    )
+    .await?;
+
+    let response = match served {
+        None => RespValue::NullArray,
+        Some(ServedList { key, elements }) => RespValue::Array(vec![
+            RespValue::BulkString(key),
+            RespValue::Array(elements.into_iter().map(RespValue::BulkString).collect()),
+        ]),
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::db::{app_data::AppDataSlave, in_memory_db::InMemoryDb},
+        resp_parser::shared::RespValue,
+        test_helpers::utils::{
+            bulk_string, bulk_strings, create_test_mem_db, error, send_test_request, FakeTStream,
+        },
+    };
+
+    use std::sync::Arc;
+
+    use tokio::{sync::Mutex, task::JoinHandle};
+
+    /// Sends `request` on a connection of its own, and waits until it is blocked, so that
+    /// clients block in the order they are sent.
+    async fn send_blocking_test_request(
+        mem_db: &Arc<Mutex<InMemoryDb>>,
+        request: &'static [u8],
+    ) -> JoinHandle<Vec<RespValue>> {
+        let blocked_count = mem_db.lock().await.blocked_clients.len();
+
+        let mem_db_arc_pointer = Arc::clone(mem_db);
+        let handle = tokio::spawn(async move {
+            send_test_request(&mem_db_arc_pointer, request)
+                .await
+                .unwrap()
+        });
+
+        while mem_db.lock().await.blocked_clients.len() == blocked_count {
+            tokio::task::yield_now().await;
+        }
+
+        handle
+    }
+
+    #[tokio::test]
+    async fn push_pop_and_lrange_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"RPUSH l b c\r\nLPUSH l a z\r\nLRANGE l 0 -1\r\nLRANGE l -2 100\r\nLRANGE l 3 1\r\nLPOP l\r\nRPOP l 2\r\nLLEN l\r\nRPOP l 5\r\nEXISTS l\r\nLPOP l\r\nLPOP l 1\r\nLPOP l -1\r\nLLEN l\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(2),
+                RespValue::Integer(4),
+                bulk_strings(&["z", "a", "b", "c"]),
+                bulk_strings(&["b", "c"]),
+                bulk_strings(&[]),
+                bulk_string("z"),
+                bulk_strings(&["c", "b"]),
+                RespValue::Integer(1),
+                bulk_strings(&["a"]),
+                RespValue::Integer(0),
+                RespValue::NullBulkString,
+                RespValue::NullArray,
+                error("ERR value is out of range, must be positive"),
+                RespValue::Integer(0),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_commands_reply_wrongtype_errors() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let wrong_type =
+            || error("WRONGTYPE Operation against a key holding the wrong kind of value");
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET s v\r\nLPUSH s a\r\nLRANGE s 0 -1\r\nRPUSH l a\r\nGET l\r\nAPPEND l a\r\nINCR l\r\nLMOVE l s LEFT LEFT\r\nLLEN l\r\nMGET s l\r\nTYPE l\r\nSET l v\r\nTYPE l\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                wrong_type(),
+                wrong_type(),
+                RespValue::Integer(1),
+                wrong_type(),
+                wrong_type(),
+                wrong_type(),
+                wrong_type(),
+                RespValue::Integer(1),
+                RespValue::Array(vec![bulk_string("v"), RespValue::NullBulkString]),
+                RespValue::SimpleString("list".to_owned()),
+                RespValue::ok(),
+                RespValue::SimpleString("string".to_owned()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lindex_lset_linsert_and_ltrim_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"RPUSH l a b c d e\r\nLINDEX l 0\r\nLINDEX l -1\r\nLINDEX l 5\r\nLSET l -2 D\r\nLSET l 5 x\r\nLSET nope 0 x\r\nLINSERT l BEFORE a _\r\nLINSERT l after e f\r\nLINSERT l before nope x\r\nLINSERT nope before a x\r\nLINSERT l middle a x\r\nLTRIM l 1 -2\r\nLRANGE l 0 -1\r\nLTRIM l 5 1\r\nEXISTS l\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(5),
+                bulk_string("a"),
+                bulk_string("e"),
+                RespValue::NullBulkString,
+                RespValue::ok(),
+                error("ERR index out of range"),
+                error("ERR no such key"),
+                RespValue::Integer(6),
+                RespValue::Integer(7),
+                RespValue::Integer(-1),
+                RespValue::Integer(0),
+                error("ERR syntax error"),
+                RespValue::ok(),
+                bulk_strings(&["a", "b", "c", "D", "e"]),
+                RespValue::ok(),
+                RespValue::Integer(0),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lrem_and_lpos_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"RPUSH l a x b x c x d x\r\nLPOS l x\r\nLPOS l x RANK 2\r\nLPOS l x RANK -1\r\nLPOS l x COUNT 0\r\nLPOS l x COUNT 2 RANK -2\r\nLPOS l x COUNT 0 MAXLEN 4\r\nLPOS l nope\r\nLPOS l nope COUNT 1\r\nLPOS l x RANK 0\r\nLPOS l x COUNT -1\r\nLPOS l x MAXLEN\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(8),
+                RespValue::Integer(1),
+                RespValue::Integer(3),
+                RespValue::Integer(7),
+                RespValue::Array(vec![
+                    RespValue::Integer(1),
+                    RespValue::Integer(3),
+                    RespValue::Integer(5),
+                    RespValue::Integer(7),
+                ]),
+                RespValue::Array(vec![RespValue::Integer(5), RespValue::Integer(3)]),
+                RespValue::Array(vec![RespValue::Integer(1), RespValue::Integer(3)]),
+                RespValue::NullBulkString,
+                RespValue::Array(vec![]),
+                error("ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list"),
+                error("ERR COUNT can't be negative"),
+                error("ERR syntax error"),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"LREM l 1 x\r\nLREM l -2 x\r\nLRANGE l 0 -1\r\nLREM l 0 x\r\nLREM l 0 nope\r\nLRANGE l 0 -1\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(1),
+                RespValue::Integer(2),
+                bulk_strings(&["a", "b", "x", "c", "d"]),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                bulk_strings(&["a", "b", "c", "d"]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_lmove_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"RPUSH src a b c\r\nLMOVE src dst LEFT RIGHT\r\nLMOVE src dst RIGHT LEFT\r\nLRANGE dst 0 -1\r\nLMOVE dst dst LEFT RIGHT\r\nLRANGE dst 0 -1\r\nLMOVE src src RIGHT LEFT\r\nLRANGE src 0 -1\r\nLMOVE nope dst LEFT LEFT\r\nLMOVE src dst UP LEFT\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(3),
+                bulk_string("a"),
+                bulk_string("c"),
+                bulk_strings(&["c", "a"]),
+                bulk_string("c"),
+                bulk_strings(&["a", "c"]),
+                bulk_string("b"),
+                bulk_strings(&["b"]),
+                RespValue::NullBulkString,
+                error("ERR syntax error"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocked_clients_are_served_in_order() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let first_client = send_blocking_test_request(&fake_mem_db, b"BLPOP q1 q2 0\r\n").await;
+        let second_client = send_blocking_test_request(&fake_mem_db, b"BRPOP q2 0\r\n").await;
+        let third_client =
+            send_blocking_test_request(&fake_mem_db, b"BLMPOP 0 1 q2 LEFT COUNT 5\r\n").await;
+
+        // The push replies the length before serving the blocked clients, like in Redis.
+        assert_eq!(
+            send_test_request(&fake_mem_db, b"RPUSH q2 a b c d\r\n").await?,
+            vec![RespValue::Integer(4)]
+        );
+
+        assert_eq!(first_client.await?, vec![bulk_strings(&["q2", "a"])]);
+        assert_eq!(second_client.await?, vec![bulk_strings(&["q2", "d"])]);
+        assert_eq!(
+            third_client.await?,
+            vec![RespValue::Array(vec![
+                bulk_string("q2"),
+                bulk_strings(&["b", "c"])
+            ])]
+        );
+
+        assert_eq!(
+            send_test_request(&fake_mem_db, b"EXISTS q2\r\n").await?,
+            vec![RespValue::Integer(0)]
+        );
+        assert!(fake_mem_db.lock().await.blocked_clients.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blmove_is_served_and_serves_clients_blocked_on_its_destination(
+    ) -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let pop_client = send_blocking_test_request(&fake_mem_db, b"BLPOP dst 0\r\n").await;
+        let move_client =
+            send_blocking_test_request(&fake_mem_db, b"BLMOVE src dst RIGHT LEFT 0\r\n").await;
+
+        assert_eq!(
+            send_test_request(&fake_mem_db, b"LPUSH src a\r\nEXISTS src dst\r\n").await?,
+            vec![RespValue::Integer(1), RespValue::Integer(0)]
+        );
+
+        assert_eq!(move_client.await?, vec![bulk_string("a")]);
+        assert_eq!(pop_client.await?, vec![bulk_strings(&["dst", "a"])]);
+
+        // Lists that already have elements are popped without blocking.
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"RPUSH l a b c\r\nBLMOVE l l LEFT RIGHT 0\r\nBRPOP nope l 0\r\nBLMPOP 0 2 nope l RIGHT COUNT 5\r\nEXISTS l\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(3),
+                bulk_string("a"),
+                bulk_strings(&["l", "a"]),
+                RespValue::Array(vec![bulk_string("l"), bulk_strings(&["c", "b"])]),
+                RespValue::Integer(0),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocked_clients_are_served_before_the_push_releases_the_lock(
+    ) -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let blocked_client = send_blocking_test_request(&fake_mem_db, b"BLPOP q 0\r\n").await;
+
+        // The push and then the pop wait for the DB lock, so that the pop gets it as soon as the
+        // push releases it.
+        let db_lock = fake_mem_db.lock().await;
+        let mem_db_arc_pointer = Arc::clone(&fake_mem_db);
+        let push_client = tokio::spawn(async move {
+            send_test_request(&mem_db_arc_pointer, b"RPUSH q a\r\n")
+                .await
+                .unwrap()
+        });
+        tokio::task::yield_now().await;
+        let mem_db_arc_pointer = Arc::clone(&fake_mem_db);
+        let pop_client = tokio::spawn(async move {
+            send_test_request(&mem_db_arc_pointer, b"LPOP q\r\n")
+                .await
+                .unwrap()
+        });
+        tokio::task::yield_now().await;
+        drop(db_lock);
+
+        assert_eq!(push_client.await?, vec![RespValue::Integer(1)]);
+        assert_eq!(pop_client.await?, vec![RespValue::NullBulkString]);
+        assert_eq!(blocked_client.await?, vec![bulk_strings(&["q", "a"])]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocked_clients_are_served_by_key_commands() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let renamed_client = send_blocking_test_request(&fake_mem_db, b"BLPOP a 0\r\n").await;
+        let copied_client = send_blocking_test_request(&fake_mem_db, b"BLPOP b 0\r\n").await;
+        let moved_client =
+            send_blocking_test_request(&fake_mem_db, b"SELECT 1\r\nBLPOP a 0\r\n").await;
+        let swapped_client =
+            send_blocking_test_request(&fake_mem_db, b"SELECT 2\r\nBLPOP d 0\r\n").await;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"RPUSH l 1 2\r\nRENAME l a\r\nCOPY a b\r\nMOVE a 1\r\nSELECT 3\r\nRPUSH d x\r\nSWAPDB 2 3\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(2),
+                RespValue::ok(),
+                RespValue::Integer(1),
+                RespValue::Integer(1),
+                RespValue::ok(),
+                RespValue::Integer(1),
+                RespValue::ok(),
+            ]
+        );
+
+        assert_eq!(renamed_client.await?, vec![bulk_strings(&["a", "1"])]);
+        assert_eq!(copied_client.await?, vec![bulk_strings(&["b", "2"])]);
+        assert_eq!(
+            moved_client.await?,
+            vec![RespValue::ok(), bulk_strings(&["a", "2"])]
+        );
+        assert_eq!(
+            swapped_client.await?,
+            vec![RespValue::ok(), bulk_strings(&["d", "x"])]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn served_clients_are_propagated_after_the_push() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_replica_stream = Arc::new(Mutex::new(FakeTStream::default()));
+        fake_mem_db
+            .lock()
+            .await
+            .get_app_data_mut()
+            .get_master_data_mut()
+            .unwrap()
+            .slaves
+            .insert(
+                6380,
+                AppDataSlave {
+                    port: 6380,
+                    tcp_stream: fake_replica_stream.clone(),
+                    full_handshake: true,
+                },
+            );
+
+        let pop_client =
+            send_blocking_test_request(&fake_mem_db, b"BLMPOP 0 1 q LEFT COUNT 2\r\n").await;
+        let move_client =
+            send_blocking_test_request(&fake_mem_db, b"BLMOVE q dst RIGHT LEFT 0\r\n").await;
+
+        send_test_request(
+            &fake_mem_db,
+            b"*5\r\n$5\r\nRPUSH\r\n$1\r\nq\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n",
+        )
+        .await?;
+        pop_client.await?;
+        move_client.await?;
+
+        // The served clients propagate nothing themselves.
+        assert_eq!(
+            String::from_utf8(fake_replica_stream.lock().await.written.clone())?,
+            [
+                "*2\r\n$6\r\nSELECT\r\n$1\r\n0\r\n",
+                "*5\r\n$5\r\nRPUSH\r\n$1\r\nq\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n",
+                "*3\r\n$4\r\nLPOP\r\n$1\r\nq\r\n$1\r\n2\r\n",
+                "*5\r\n$5\r\nLMOVE\r\n$1\r\nq\r\n$3\r\ndst\r\n$5\r\nRIGHT\r\n$4\r\nLEFT\r\n",
+            ]
+            .concat()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blocking_commands_time_out_and_validate_arguments() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"BLPOP nope 0.01\r\nBLMOVE nope dst LEFT LEFT 0.01\r\nBLMPOP 0.01 1 nope LEFT\r\nSET s v\r\nBLPOP nope s 0\r\nBLPOP l -1\r\nBLPOP l one\r\nBLMPOP 0 0 l LEFT\r\nBLMPOP 0 2 l LEFT\r\nBLMPOP 0 1 l LEFT COUNT 0\r\nBLMPOP 0 1 l UP\r\nBLMPOP 0 1 l LEFT LIMIT 1\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::NullArray,
+                RespValue::NullBulkString,
+                RespValue::NullArray,
+                RespValue::ok(),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                error("ERR timeout is negative"),
+                error("ERR timeout is not a float or out of range"),
+                error("ERR numkeys should be greater than 0"),
+                error("ERR Number of keys can't be greater than number of args"),
+                error("ERR count should be greater than 0"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+            ]
+        );
+        assert!(fake_mem_db.lock().await.blocked_clients.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{
+    models::{
+        connection_context::{ConnectionContext, Handshake, Response},
+        db::{app_data::AppDataSlave, in_memory_db::EMPTY_RDB_HEX_FILE},
+    },
+    resp_parser::shared::{RespCommandNames, RespCommandReplConfOption, RespValue},
+    utils::{hex_to_utf8_bytes, return_err},
+};
+
+use anyhow::{Error, Ok};
+
+pub(crate) async fn handle_command_replconf<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let mut db_lock = context.mem_db.lock().await;
+    let app_data_master = db_lock.get_app_data_mut().get_master_data_mut().unwrap();
+
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+
+    if resp_command.get_parameter_str(0)? == RespCommandReplConfOption::LISTENING_PORT {
+        let port = match resp_command.get_parameter_str(1)?.parse::<u16>() {
+            Err(_) => {
+                return return_err(format!(
+                    "{} parameter value malformed - Not a number.",
+                    RespCommandReplConfOption::LISTENING_PORT
+                ))
+            }
+
+            Result::Ok(port) => port,
+        };
+
+        context.request.handshake = Handshake::Replica { port };
+
+        app_data_master.slaves.insert(
+            port,
+            AppDataSlave {
+                port,
+                tcp_stream: context.request.tcp_stream.clone(),
+                full_handshake: false,
+            },
+        );
+    }
+
+    context.set_response(RespValue::ok().into());
+
+    Ok(())
+}
+
+/// E.g. input: *3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n
+pub(crate) async fn handle_command_psync<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    fn error(port: &u16) -> Result<(), Error> {
+        return_err::<()>(format!(
+            "Cannot {}, not a replica, or command {} no yet performed for port {}.",
+            RespCommandNames::PSYNC,
+            RespCommandNames::REPLCONF,
+            port,
+        ))
+    }
+
+    let mut db_lock = context.mem_db.lock().await;
+    let app_data_master = db_lock.get_app_data_mut().get_master_data_mut().unwrap();
+
+    let port = match context.request.handshake {
+        Handshake::None => return error(&0),
+        Handshake::Replica { port } => port,
+    };
+
+    let slave = app_data_master.slaves.get_mut(&port);
+
+    if slave.is_none() {
+        return error(&port);
+    }
+
+    let slave = slave.unwrap();
+    slave.full_handshake = true;
+
+    // The new replica starts from database 0, which the others may not be on.
+    app_data_master.propagated_db = None;
+
+    let response = RespValue::SimpleString(format!(
+        "FULLRESYNC {} {}",
+        app_data_master.replid, app_data_master.repl_offset
+    ));
+
+    let mut decoded_rdb_file = hex_to_utf8_bytes(EMPTY_RDB_HEX_FILE)?;
+
+    let mut rdb_file_response = format!("${}\r\n", decoded_rdb_file.len())
+        .as_bytes()
+        .to_vec();
+    rdb_file_response.append(&mut decoded_rdb_file);
+
+    context.add_response(response.into());
+    // The RDB file is not a bulk string, since it has no trailing CRLF.
+    context.add_response(Response::Raw(rdb_file_response));
+
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use crate::{
+    models::{command_error::CommandError, connection_context::ConnectionContext},
+    node::command_handlers::{keys, lists},
+    resp_parser::shared::{RespCommand, RespCommandFlushOptions, RespCommandNames, RespValue},
+};
+
+use anyhow::{Error, Ok};
+use bytes::Bytes;
+
+pub(crate) async fn handle_command_info<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let db_lock = context.mem_db.lock().await;
+    let app_data = db_lock.get_app_data_ref();
+    let expiry_stats = &db_lock.expiry_stats;
+
+    let clients_section = format!(
+        "# Clients\r\nblocked_clients:{}",
+        db_lock.blocked_clients.len()
+    );
+
+    let stats_section = format!(
+        "# Stats\r\nexpired_keys:{}\r\nexpired_subkeys:{}\r\nexpired_stale_perc:{:.2}\r\nexpired_time_cap_reached_count:{}\r\nexpire_cycle_cpu_milliseconds:{}",
+        expiry_stats.expired_keys,
+        expiry_stats.expired_subkeys,
+        expiry_stats.expired_stale_perc,
+        expiry_stats.expired_time_cap_reached_count,
+        expiry_stats.expire_cycle_cpu_milliseconds,
+    );
+
+    let replication_section = format!(
+        "# Replication\r\nrole:{}\r\nconnected_slaves:0{}",
+        if app_data.get_replication_data_ref().is_none() {
+            "master"
+        } else {
+            "slave"
+        },
+        if app_data.get_replication_data_ref().is_some() {
+            "".to_owned()
+        } else {
+            let master_data = app_data.get_master_data_ref().unwrap();
+            format!(
+                "\r\nmaster_replid:{}\r\nmaster_repl_offset:{}",
+                master_data.replid, master_data.repl_offset
+            )
+        }
+    );
+
+    // Like Redis, empty dbs are not listed. Keys that expired but were not removed yet are.
+    let mut keyspace_section = "# Keyspace".to_owned();
+    for db_index in 0..db_lock.get_db_count() {
+        let records = db_lock.get_records_ref(db_index);
+
+        if records.len() > 0 {
+            keyspace_section.push_str(&format!(
+                "\r\ndb{}:keys={},expires={},avg_ttl=0",
+                db_index,
+                records.len(),
+                records
+                    .iter()
+                    .filter(|(_, record)| record.expire_at_milli.is_some())
+                    .count()
+            ));
+        }
+    }
+
+    context.set_response(
+        RespValue::BulkString(Bytes::from(
+            [
+                clients_section,
+                stats_section,
+                replication_section,
+                keyspace_section,
+            ]
+            .join("\r\n\r\n"),
+        ))
+        .into(),
+    );
+
+    Ok(())
+}
+
+/// Replies the number of keys in the selected database, e.g.: "redis-cli dbsize" <br/>
+/// Keys that expired but were not removed yet are counted, like in Redis.
+pub(crate) async fn handle_command_dbsize_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let db_size = context
+        .mem_db
+        .lock()
+        .await
+        .get_records_ref(context.selected_db)
+        .len();
+
+    context.set_response(RespValue::Integer(db_size as i64).into());
+
+    Ok(())
+}
+
+/// Swaps two databases, so that the clients that selected one see the keys of the other. <br/>
+/// E.g.: "redis-cli swapdb 0 1"
+pub(crate) async fn handle_command_swapdb_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+
+    let parse_db_index = |position: usize, description: &str| {
+        resp_command
+            .get_parameter_i64(position)
+            .map_err(|_| CommandError::Other(format!("invalid {} DB index", description)))
+    };
+    let first_db_index = parse_db_index(0, "first")?;
+    let second_db_index = parse_db_index(1, "second")?;
+
+    let mut db_lock = context.mem_db.lock().await;
+    let first_db_index = keys::check_db_index(&db_lock, first_db_index)?;
+    let second_db_index = keys::check_db_index(&db_lock, second_db_index)?;
+
+    db_lock.swap_dbs(first_db_index, second_db_index);
+    // The clients blocked on a key of either database may find a list under it now.
+    db_lock.blocked_clients.signal_db_ready(first_db_index);
+    db_lock.blocked_clients.signal_db_ready(second_db_index);
+    context.served_commands = lists::serve_blocked_clients(&mut db_lock);
+
+    context.set_response(RespValue::ok().into());
+
+    Ok(())
+}
+
+/// Whether FLUSHDB and FLUSHALL drop the keys on a background task, with the ASYNC option.
+fn parse_flush_async(resp_command: &RespCommand) -> Result<bool, Error> {
+    match resp_command.parameters.len() {
+        0 => Ok(false),
+        1 => match resp_command
+            .get_parameter_str(0)?
+            .to_ascii_uppercase()
+            .as_str()
+        {
+            RespCommandFlushOptions::ASYNC => Ok(true),
+            RespCommandFlushOptions::SYNC => Ok(false),
+            _ => Err(CommandError::Syntax.into()),
+        },
+        _ => Err(CommandError::Syntax.into()),
+    }
+}
+
+/// Handles FLUSHDB and FLUSHALL, which delete all keys of the selected database or of all of
+/// them, e.g.: <br/>
+/// "redis-cli flushdb" <br/>
+/// "redis-cli flushall async" (the keys are dropped on a background task)
+pub(crate) async fn handle_command_flush_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let is_async = parse_flush_async(resp_command)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let db_indexes = if resp_command.name == RespCommandNames::FLUSHALL {
+        0..db_lock.get_db_count()
+    } else {
+        context.selected_db..context.selected_db + 1
+    };
+    let flushed_records: Vec<_> = db_indexes
+        .map(|db_index| db_lock.take_records(db_index))
+        .collect();
+
+    drop(db_lock);
+
+    if is_async {
+        let free_effort = flushed_records
+            .iter()
+            .flat_map(|records| records.iter())
+            .map(|(_, record)| record.get_free_effort())
+            .sum();
+        keys::free_lazily(flushed_records, free_effort);
+    }
+
+    context.set_response(RespValue::ok().into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        resp_parser::shared::RespValue,
+        test_helpers::utils::{create_test_mem_db, error, send_test_request},
+    };
+
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn databases_are_selected_swapped_and_flushed() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a 0\r\nSELECT 1\r\nGET a\r\nMSET a 1 b 1\r\nDBSIZE\r\nSELECT 2\r\nSET c 2\r\nSWAPDB 0 1\r\nSELECT 0\r\nDBSIZE\r\nGET a\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::ok(),
+                RespValue::NullBulkString,
+                RespValue::ok(),
+                RespValue::Integer(2),
+                RespValue::ok(),
+                RespValue::ok(),
+                RespValue::ok(),
+                RespValue::ok(),
+                RespValue::Integer(2),
+                RespValue::BulkString(Bytes::from("1")),
+            ]
+        );
+
+        // A new connection starts on database 0.
+        assert_eq!(
+            send_test_request(&fake_mem_db, b"DBSIZE\r\nFLUSHDB\r\nDBSIZE\r\nSELECT 1\r\nDBSIZE\r\nFLUSHALL ASYNC\r\nDBSIZE\r\nSELECT 2\r\nDBSIZE\r\n").await?,
+            vec![
+                RespValue::Integer(2),
+                RespValue::ok(),
+                RespValue::Integer(0),
+                RespValue::ok(),
+                RespValue::Integer(1),
+                RespValue::ok(),
+                RespValue::Integer(0),
+                RespValue::ok(),
+                RespValue::Integer(0),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SELECT 16\r\nSELECT -1\r\nSELECT one\r\nSWAPDB 0 x\r\nSWAPDB 0 16\r\nFLUSHDB LATER\r\nFLUSHALL SYNC ASYNC\r\n",
+            )
+            .await?,
+            vec![
+                error("ERR DB index is out of range"),
+                error("ERR DB index is out of range"),
+                error("ERR value is not an integer or out of range"),
+                error("ERR invalid second DB index"),
+                error("ERR DB index is out of range"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,867 @@
+use crate::{
+    models::{
+        command_error::CommandError,
+        connection_context::{ConnectionContext, Propagation},
+        db::{
+            in_memory_db::InMemoryDb,
+            in_memory_record::{InMemoryRecord, RecordValue},
+            set_value::SetValue,
+        },
+    },
+    node::command_handlers::keys::{
+        parse_counted_keys, parse_scan_cursor, parse_scan_options, ScanTarget,
+    },
+    resp_parser::shared::{RespCommand, RespCommandNames, RespCommandSInterCardOptions, RespValue},
+    utils::{glob_match, random_below},
+};
+
+use std::collections::HashSet;
+
+use anyhow::{Error, Ok};
+use bytes::Bytes;
+
+/// The set of `key` if it exists, or a WRONGTYPE error if the key holds another type.
+pub(crate) fn get_live_set_mut<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+) -> Result<Option<&'a mut SetValue>, Error> {
+    match db.get_live_record_mut(db_index, key)? {
+        None => Ok(None),
+        Some(record) => record.get_set_mut().map(Some),
+    }
+}
+
+/// The set of `key`, which is created empty if the key does not exist.
+fn get_or_create_set_mut<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+) -> Result<&'a mut SetValue, Error> {
+    if get_live_set_mut(db, db_index, key)?.is_none() {
+        db.get_records_ref_mut(db_index).insert(
+            key.clone(),
+            InMemoryRecord::new(RecordValue::Set(SetValue::default()), None),
+        );
+    }
+
+    Ok(get_live_set_mut(db, db_index, key)?.unwrap())
+}
+
+/// The sets of `keys`, in order, with `None` for the keys that don't exist, or a WRONGTYPE error
+/// if any of the keys holds another type.
+fn get_live_sets<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    keys: &[Bytes],
+) -> Result<Vec<Option<&'a SetValue>>, Error> {
+    // Expired keys are removed first, so that the sets can then be borrowed all at once.
+    for key in keys {
+        db.get_live_record_mut(db_index, key)?;
+    }
+
+    let records = db.get_records_ref(db_index);
+
+    keys.iter()
+        .map(|key| {
+            records
+                .get(key)
+                .map(InMemoryRecord::get_set_ref)
+                .transpose()
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SetOperation {
+    Intersection,
+    Union,
+    /// The members of the first set that are in none of the others.
+    Difference,
+}
+
+impl SetOperation {
+    /// The operation of SINTER, SUNION, SDIFF or their STORE variant.
+    fn from_command_name(name: &str) -> Self {
+        match name {
+            RespCommandNames::SINTER | RespCommandNames::SINTERSTORE => SetOperation::Intersection,
+            RespCommandNames::SUNION | RespCommandNames::SUNIONSTORE => SetOperation::Union,
+            _ => SetOperation::Difference,
+        }
+    }
+
+    /// Applies the operation to `sets`, where a key that does not exist counts as an empty set,
+    /// and returns up to `limit` members of the result.
+    fn apply(&self, sets: &[Option<&SetValue>], limit: usize) -> Vec<Bytes> {
+        match self {
+            SetOperation::Intersection => {
+                if sets.iter().any(Option::is_none) {
+                    return Vec::new();
+                }
+
+                // Walking the smallest set checks as few members as possible.
+                let mut sets: Vec<&SetValue> = sets.iter().flatten().copied().collect();
+                sets.sort_by_key(|set| set.len());
+
+                sets[0]
+                    .iter()
+                    .filter(|member| sets[1..].iter().all(|set| set.contains(member)))
+                    .take(limit)
+                    .collect()
+            }
+            SetOperation::Union => {
+                let mut seen_members = HashSet::new();
+
+                sets.iter()
+                    .flatten()
+                    .flat_map(|set| set.iter())
+                    .filter(|member| seen_members.insert(member.clone()))
+                    .take(limit)
+                    .collect()
+            }
+            SetOperation::Difference => match sets.split_first() {
+                Some((Some(first), others)) => first
+                    .iter()
+                    .filter(|member| others.iter().flatten().all(|set| !set.contains(member)))
+                    .take(limit)
+                    .collect(),
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+/// Adds members to a set, e.g.: "redis-cli sadd tags:1 rust redis" <br/>
+/// Replies how many members are new.
+pub(crate) async fn handle_command_sadd_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+    let set = get_or_create_set_mut(&mut db_lock, context.selected_db, key)?;
+
+    let added_count = resp_command.parameters[1..]
+        .iter()
+        .filter(|member| set.insert((*member).clone()))
+        .count();
+
+    if added_count == 0 {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(added_count as i64).into());
+
+    Ok(())
+}
+
+/// Removes members from a set, e.g.: "redis-cli srem tags:1 redis" <br/>
+/// Replies how many members were removed. The key is deleted with its last member.
+pub(crate) async fn handle_command_srem_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let removed_count = match get_live_set_mut(&mut db_lock, context.selected_db, key)? {
+        None => 0,
+        Some(set) => resp_command.parameters[1..]
+            .iter()
+            .filter(|member| set.remove(member))
+            .count(),
+    };
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    if removed_count == 0 {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(removed_count as i64).into());
+
+    Ok(())
+}
+
+/// Replies the members of a set, e.g.: "redis-cli smembers tags:1"
+pub(crate) async fn handle_command_smembers_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let members = get_live_set_mut(&mut db_lock, context.selected_db, key)?
+        .map_or(Vec::new(), |set| {
+            set.iter().map(RespValue::BulkString).collect()
+        });
+
+    context.set_response(RespValue::Set(members).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli sismember tags:1 rust"
+pub(crate) async fn handle_command_sismember_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (key, member) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let is_member = get_live_set_mut(&mut db_lock, context.selected_db, key)?
+        .is_some_and(|set| set.contains(member));
+
+    context.set_response(RespValue::Integer(is_member as i64).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli smismember tags:1 rust go" <br/>
+/// Replies 1 or 0 for each member, in the order they were given.
+pub(crate) async fn handle_command_smismember_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+    let set = get_live_set_mut(&mut db_lock, context.selected_db, key)?;
+
+    let are_members = resp_command.parameters[1..]
+        .iter()
+        .map(|member| {
+            RespValue::Integer(set.as_ref().is_some_and(|set| set.contains(member)) as i64)
+        })
+        .collect();
+
+    context.set_response(RespValue::Array(are_members).into());
+
+    Ok(())
+}
+
+/// Replies how many members a set has, e.g.: "redis-cli scard tags:1"
+pub(crate) async fn handle_command_scard_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let member_count =
+        get_live_set_mut(&mut db_lock, context.selected_db, key)?.map_or(0, |set| set.len());
+
+    context.set_response(RespValue::Integer(member_count as i64).into());
+
+    Ok(())
+}
+
+/// Removes random members from a set and replies them, e.g.: <br/>
+/// "redis-cli spop tags:1" (a single member, or a null if the key does not exist) <br/>
+/// "redis-cli spop tags:1 3" (up to 3 members)
+///
+/// It is propagated as an SREM of the removed members, so that replicas remove the same ones.
+pub(crate) async fn handle_command_spop_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let count = match &resp_command.parameters[1..] {
+        [] => None,
+        [_] => Some(
+            usize::try_from(resp_command.get_parameter_i64(1)?).map_err(|_| {
+                CommandError::Other("value is out of range, must be positive".to_owned())
+            })?,
+        ),
+        _ => return Err(CommandError::Syntax.into()),
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let members: Vec<Bytes> = match get_live_set_mut(&mut db_lock, context.selected_db, key)? {
+        None => Vec::new(),
+        Some(set) => (0..count.unwrap_or(1))
+            .map_while(|_| set.pop_random())
+            .collect(),
+    };
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    let response = match count {
+        None => members.first().map_or(RespValue::NullBulkString, |member| {
+            RespValue::BulkString(member.clone())
+        }),
+        Some(_) => RespValue::Set(members.iter().cloned().map(RespValue::BulkString).collect()),
+    };
+
+    context.propagation = Propagation::Rewritten(if members.is_empty() {
+        Vec::new()
+    } else {
+        let mut parameters = vec![key.clone()];
+        parameters.extend(members);
+
+        vec![RespCommand::new(RespCommandNames::SREM, parameters)]
+    });
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Replies random members of a set, e.g.: <br/>
+/// "redis-cli srandmember tags:1" (a single member) <br/>
+/// "redis-cli srandmember tags:1 3" (up to 3 distinct members) <br/>
+/// "redis-cli srandmember tags:1 -3" (3 members that may repeat)
+pub(crate) async fn handle_command_srandmember_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let count = match &resp_command.parameters[1..] {
+        [] => None,
+        [_] => Some(resp_command.get_parameter_i64(1)?),
+        _ => return Err(CommandError::Syntax.into()),
+    };
+    if count.is_some_and(|count| count.unsigned_abs() > i64::MAX as u64 / 2) {
+        return Err(CommandError::Other("value is out of range".to_owned()).into());
+    }
+
+    let mut db_lock = context.mem_db.lock().await;
+    let set = get_live_set_mut(&mut db_lock, context.selected_db, key)?;
+
+    let (count, set) = match (count, set) {
+        (None, set) => {
+            let member = set.and_then(|set| set.get_random());
+            context.set_response(
+                member
+                    .map_or(RespValue::NullBulkString, RespValue::BulkString)
+                    .into(),
+            );
+
+            return Ok(());
+        }
+        (Some(_), None) => {
+            context.set_response(RespValue::Array(Vec::new()).into());
+
+            return Ok(());
+        }
+        (Some(count), Some(set)) => (count, set),
+    };
+
+    let members: Vec<Bytes> = if count < 0 {
+        (0..count.unsigned_abs())
+            .filter_map(|_| set.get_random())
+            .collect()
+    } else if (count as usize).saturating_mul(3) <= set.len() {
+        // Like in Redis, when few members are asked for, random ones are picked until `count` of
+        // them are distinct, which does not walk the whole set.
+        let mut seen_members = HashSet::new();
+
+        std::iter::from_fn(|| set.get_random())
+            .filter(|member| seen_members.insert(member.clone()))
+            .take(count as usize)
+            .collect()
+    } else {
+        let mut members: Vec<Bytes> = set.iter().collect();
+
+        // A partial shuffle, which picks `count` distinct members.
+        let count = (count as usize).min(members.len());
+        for index in 0..count {
+            let picked = index + random_below(members.len() - index);
+            members.swap(index, picked);
+        }
+        members.truncate(count);
+
+        members
+    };
+
+    context.set_response(
+        RespValue::Array(members.into_iter().map(RespValue::BulkString).collect()).into(),
+    );
+
+    Ok(())
+}
+
+/// Handles SINTER, SUNION and SDIFF, e.g.: <br/>
+/// "redis-cli sinter tags:1 tags:2" (the members in both sets) <br/>
+/// "redis-cli sdiff tags:1 tags:2" (the members of tags:1 that are not in tags:2)
+///
+/// A key that does not exist counts as an empty set.
+pub(crate) async fn handle_command_sinter_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let operation = SetOperation::from_command_name(&resp_command.name);
+
+    let mut db_lock = context.mem_db.lock().await;
+    let sets = get_live_sets(&mut db_lock, context.selected_db, &resp_command.parameters)?;
+
+    let members = operation.apply(&sets, usize::MAX);
+
+    context.set_response(
+        RespValue::Set(members.into_iter().map(RespValue::BulkString).collect()).into(),
+    );
+
+    Ok(())
+}
+
+/// Handles SINTERSTORE, SUNIONSTORE and SDIFFSTORE, which store the result of their operation in
+/// the first key, e.g.: "redis-cli sunionstore tags:all tags:1 tags:2" <br/>
+/// Replies how many members the result has. The destination is replaced whatever its type, and
+/// deleted if the result is empty.
+pub(crate) async fn handle_command_sinterstore_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let operation = SetOperation::from_command_name(&resp_command.name);
+    let destination = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+    let sets = get_live_sets(
+        &mut db_lock,
+        context.selected_db,
+        &resp_command.parameters[1..],
+    )?;
+
+    let set: SetValue = operation.apply(&sets, usize::MAX).into_iter().collect();
+    let member_count = set.len();
+
+    if set.is_empty() {
+        db_lock.remove_live_record(context.selected_db, destination)?;
+    } else {
+        db_lock.get_records_ref_mut(context.selected_db).insert(
+            destination.clone(),
+            InMemoryRecord::new(RecordValue::Set(set), None),
+        );
+    }
+
+    context.set_response(RespValue::Integer(member_count as i64).into());
+
+    Ok(())
+}
+
+/// Replies how many members the intersection of sets has, without building it, e.g.: <br/>
+/// "redis-cli sintercard 2 tags:1 tags:2" <br/>
+/// "redis-cli sintercard 2 tags:1 tags:2 limit 10" (stops counting at 10, 0 is no limit)
+pub(crate) async fn handle_command_sintercard_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let keys = parse_counted_keys(resp_command, 0)?;
+
+    let limit = match &resp_command.parameters[1 + keys.len()..] {
+        [] => usize::MAX,
+        [option, _]
+            if option.eq_ignore_ascii_case(RespCommandSInterCardOptions::LIMIT.as_bytes()) =>
+        {
+            match usize::try_from(resp_command.get_parameter_i64(2 + keys.len())?) {
+                Result::Ok(0) => usize::MAX,
+                Result::Ok(limit) => limit,
+                Err(_) => {
+                    return Err(CommandError::Other("LIMIT can't be negative".to_owned()).into())
+                }
+            }
+        }
+        _ => return Err(CommandError::Syntax.into()),
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+    let sets = get_live_sets(&mut db_lock, context.selected_db, keys)?;
+
+    let member_count = SetOperation::Intersection.apply(&sets, limit).len();
+
+    context.set_response(RespValue::Integer(member_count as i64).into());
+
+    Ok(())
+}
+
+/// Moves a member from a set to another, e.g.: "redis-cli smove tags:draft tags:1 rust" <br/>
+/// Replies 1 if the member was in the source set, or 0 if it was not and nothing was done.
+pub(crate) async fn handle_command_smove_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (source, destination, member) = (
+        &resp_command.parameters[0],
+        &resp_command.parameters[1],
+        &resp_command.parameters[2],
+    );
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let is_member = get_live_set_mut(&mut db_lock, context.selected_db, source)?
+        .map(|set| set.contains(member));
+
+    // Like in Redis, the destination is only checked if the source exists, but before anything is
+    // moved, so that a WRONGTYPE changes nothing.
+    if is_member.is_some() {
+        get_live_set_mut(&mut db_lock, context.selected_db, destination)?;
+    }
+    let is_moved = is_member == Some(true);
+
+    if is_moved && source != destination {
+        if let Some(set) = get_live_set_mut(&mut db_lock, context.selected_db, source)? {
+            set.remove(member);
+        }
+        db_lock.remove_if_empty(context.selected_db, source);
+        get_or_create_set_mut(&mut db_lock, context.selected_db, destination)?
+            .insert(member.clone());
+    } else {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(is_moved as i64).into());
+
+    Ok(())
+}
+
+/// Walks the members of a set a few at a time, like SCAN does with keys, e.g.: <br/>
+/// "redis-cli sscan tags:1 0 match 'r*' count 100"
+pub(crate) async fn handle_command_sscan_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let cursor = parse_scan_cursor(resp_command, 1)?;
+    let options = parse_scan_options(resp_command, 2, ScanTarget::Set)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut members = Vec::new();
+    let next_cursor = match get_live_set_mut(&mut db_lock, context.selected_db, key)? {
+        None => 0,
+        Some(set) => set.scan(cursor, options.count, |member| {
+            let is_match = options
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| glob_match(pattern, member));
+
+            if is_match {
+                members.push(RespValue::BulkString(member.clone()));
+            }
+        }),
+    };
+
+    context.set_response(
+        RespValue::Array(vec![
+            RespValue::BulkString(Bytes::from(next_cursor.to_string())),
+            RespValue::Array(members),
+        ])
+        .into(),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        resp_parser::shared::RespValue,
+        test_helpers::utils::{
+            bulk_string, create_test_mem_db, error, integers, send_test_request,
+        },
+    };
+
+    use std::collections::HashSet;
+
+    fn members(values: &[&str]) -> RespValue {
+        RespValue::Set(values.iter().map(|value| bulk_string(value)).collect())
+    }
+
+    #[tokio::test]
+    async fn set_commands_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SADD s 3 1 2 3\r\nSADD s 1\r\nSMEMBERS s\r\nOBJECT ENCODING s\r\nSISMEMBER s 2\r\nSISMEMBER s 02\r\nSMISMEMBER s 1 4 3\r\nSMISMEMBER nope 1\r\nSCARD s\r\nSADD s a\r\nOBJECT ENCODING s\r\nSMEMBERS s\r\nSREM s 1 a nope\r\nSREM s 2 3\r\nEXISTS s\r\nSMEMBERS s\r\nSCARD s\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(3),
+                RespValue::Integer(0),
+                members(&["1", "2", "3"]),
+                bulk_string("intset"),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                integers(&[1, 0, 1]),
+                integers(&[0]),
+                RespValue::Integer(3),
+                RespValue::Integer(1),
+                bulk_string("hashtable"),
+                members(&["1", "2", "3", "a"]),
+                RespValue::Integer(2),
+                RespValue::Integer(2),
+                RespValue::Integer(0),
+                members(&[]),
+                RespValue::Integer(0),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET str v\r\nSADD str a\r\nSMEMBERS str\r\nSADD s a\r\nGET s\r\nTYPE s\r\nSINTER s str\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                RespValue::Integer(1),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                RespValue::SimpleString("set".to_owned()),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_spop_async_and_srandmember_async_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let replies = send_test_request(
+            &fake_mem_db,
+            b"SADD s a b c d\r\nSPOP s\r\nSPOP s 2\r\nSCARD s\r\nSRANDMEMBER s\r\nSRANDMEMBER s 5\r\nSRANDMEMBER s -4\r\nSPOP s 0\r\nSPOP s 5\r\nEXISTS s\r\nSPOP s\r\nSPOP s 1\r\nSRANDMEMBER s\r\nSRANDMEMBER s 2\r\nSPOP s -1\r\nSRANDMEMBER s 1 2\r\n",
+        )
+        .await?;
+
+        let all_members = ["a", "b", "c", "d"].map(bulk_string);
+        let as_members = |reply: &RespValue| match reply {
+            RespValue::Set(items) | RespValue::Array(items) => items.clone(),
+            reply => panic!("unexpected reply: {:?}", reply),
+        };
+
+        // Every member is popped once, whatever the order.
+        let mut popped = vec![replies[1].clone()];
+        popped.extend(as_members(&replies[2]));
+        assert_eq!(replies[3], RespValue::Integer(1));
+
+        let last_member = replies[4].clone();
+        assert!(!popped.contains(&last_member));
+        assert_eq!(as_members(&replies[5]), vec![last_member.clone()]);
+        assert_eq!(as_members(&replies[6]), vec![last_member.clone(); 4]);
+        assert_eq!(replies[7], members(&[]));
+        assert_eq!(replies[8], RespValue::Set(vec![last_member.clone()]));
+
+        popped.push(last_member);
+        popped.sort_by_key(|member| format!("{:?}", member));
+        assert_eq!(popped, all_members);
+
+        assert_eq!(
+            replies[9..],
+            [
+                RespValue::Integer(0),
+                RespValue::NullBulkString,
+                members(&[]),
+                RespValue::NullBulkString,
+                RespValue::Array(vec![]),
+                error("ERR value is out of range, must be positive"),
+                error("ERR syntax error"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_srandmember_async_picks_distinct_members_of_a_large_set(
+    ) -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut request = b"SADD s".to_vec();
+        for i in 0..100 {
+            request.extend(format!(" m{}", i).as_bytes());
+        }
+        request.extend(b"\r\nSRANDMEMBER s 10\r\n");
+
+        let replies = send_test_request(&fake_mem_db, &request).await?;
+
+        let picked = match &replies[1] {
+            RespValue::Array(picked) => picked,
+            reply => panic!("unexpected reply: {:?}", reply),
+        };
+        let distinct: HashSet<_> = picked
+            .iter()
+            .map(|member| format!("{:?}", member))
+            .collect();
+        assert_eq!(distinct.len(), 10);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_algebra_commands_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SADD a 1 2 3 4\r\nSADD b 3 4 5\r\nSADD c 4 x\r\nSINTER a b\r\nSINTER a b c\r\nSINTER a nope\r\nSUNION a b\r\nSUNION nope c\r\nSDIFF a b\r\nSDIFF a b c\r\nSDIFF nope a\r\nSINTERCARD 2 a b\r\nSINTERCARD 2 a b LIMIT 1\r\nSINTERCARD 2 a b LIMIT 0\r\nSINTERCARD 1 nope\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(4),
+                RespValue::Integer(3),
+                RespValue::Integer(2),
+                members(&["3", "4"]),
+                members(&["4"]),
+                members(&[]),
+                members(&["1", "2", "3", "4", "5"]),
+                members(&["4", "x"]),
+                members(&["1", "2"]),
+                members(&["1", "2"]),
+                members(&[]),
+                RespValue::Integer(2),
+                RespValue::Integer(1),
+                RespValue::Integer(2),
+                RespValue::Integer(0),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET d v\r\nEXPIRE d 100\r\nSINTERSTORE d a b\r\nTYPE d\r\nTTL d\r\nSMEMBERS d\r\nSUNIONSTORE d a c\r\nOBJECT ENCODING d\r\nSDIFFSTORE d a a\r\nEXISTS d\r\nSINTERSTORE a a\r\nSMEMBERS a\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::Integer(1),
+                RespValue::Integer(2),
+                RespValue::SimpleString("set".to_owned()),
+                RespValue::Integer(-1),
+                members(&["3", "4"]),
+                RespValue::Integer(5),
+                bulk_string("hashtable"),
+                RespValue::Integer(0),
+                RespValue::Integer(0),
+                RespValue::Integer(4),
+                members(&["1", "2", "3", "4"]),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SINTERCARD 0 a\r\nSINTERCARD 3 a b\r\nSINTERCARD 2 a b LIMIT -1\r\nSINTERCARD 2 a b LIMIT x\r\nSINTERCARD 2 a b LIMITS 1\r\nSINTERCARD 1 a b\r\n",
+            )
+            .await?,
+            vec![
+                error("ERR numkeys should be greater than 0"),
+                error("ERR Number of keys can't be greater than number of args"),
+                error("ERR LIMIT can't be negative"),
+                error("ERR value is not an integer or out of range"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_smove_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SADD a 1 2\r\nSMOVE a b 1\r\nSMOVE a b 1\r\nSMOVE nope b 1\r\nSMOVE a a 2\r\nSMOVE a a 3\r\nSMEMBERS b\r\nSMOVE a b 2\r\nEXISTS a\r\nSMEMBERS b\r\nSET s v\r\nSMOVE b s 1\r\nSMOVE s b 1\r\nSMOVE nope s 1\r\nSMEMBERS b\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(2),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                RespValue::Integer(0),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                members(&["1"]),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                members(&["1", "2"]),
+                RespValue::ok(),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                // A source that does not exist is checked first.
+                RespValue::Integer(0),
+                members(&["1", "2"]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_sscan_async_replies_every_member_once() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        // An intset is walked in a single step.
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SADD i 10 2 30\r\nSSCAN i 0 COUNT 1\r\nSSCAN i 0 MATCH 1*\r\nSSCAN nope 0\r\nSSCAN i 0 NOVALUES\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(3),
+                RespValue::Array(vec![
+                    bulk_string("0"),
+                    RespValue::Array(vec![bulk_string("2"), bulk_string("10"), bulk_string("30")]),
+                ]),
+                RespValue::Array(vec![bulk_string("0"), RespValue::Array(vec![bulk_string("10")])]),
+                RespValue::Array(vec![bulk_string("0"), RespValue::Array(vec![])]),
+                error("ERR syntax error"),
+            ]
+        );
+
+        let mut request = b"SADD h".to_vec();
+        for i in 0..100 {
+            request.extend_from_slice(format!(" m:{}", i).as_bytes());
+        }
+        request.extend_from_slice(b"\r\n");
+        send_test_request(&fake_mem_db, &request).await?;
+
+        let mut scanned_members = Vec::new();
+        let mut cursor = "0".to_owned();
+
+        loop {
+            let replies = send_test_request(
+                &fake_mem_db,
+                format!("SSCAN h {} MATCH m:1* COUNT 7\r\n", cursor).as_bytes(),
+            )
+            .await?;
+
+            match &replies[0] {
+                RespValue::Array(reply) => match &reply[..] {
+                    [RespValue::BulkString(next_cursor), RespValue::Array(items)] => {
+                        cursor = String::from_utf8(next_cursor.to_vec())?;
+                        scanned_members.extend(items.iter().cloned());
+                    }
+                    _ => panic!("unexpected reply: {:?}", reply),
+                },
+                reply => panic!("unexpected reply: {:?}", reply),
+            }
+
+            if cursor == "0" {
+                break;
+            }
+        }
+
+        // m:1 and m:10 to m:19.
+        let mut expected_members: Vec<RespValue> =
+            (10..20).map(|i| bulk_string(&format!("m:{}", i))).collect();
+        expected_members.insert(0, bulk_string("m:1"));
+        assert_eq!(scanned_members, expected_members);
+
+        Ok(())
+    }
+}
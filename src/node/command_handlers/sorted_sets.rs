@@ -0,0 +1,1128 @@
+use crate::{
+    models::{
+        command_error::CommandError,
+        connection_context::{ConnectionContext, Propagation},
+        db::{
+            in_memory_db::InMemoryDb,
+            in_memory_record::{InMemoryRecord, RecordValue},
+            set_value::SetValue,
+            sorted_set_value::{LexBound, ScoreBound, SortedSetValue},
+        },
+    },
+    resp_parser::shared::{
+        RespCommand, RespCommandNames, RespCommandZAddOptions, RespCommandZRangeOptions,
+        RespCommandZRankOptions, RespCommandZStoreOptions, RespProtocolVersion, RespValue,
+    },
+    utils::parse_strict_f64,
+};
+
+use std::collections::HashMap;
+
+use anyhow::{Error, Ok};
+use bytes::Bytes;
+
+/// The sorted set of `key` if it exists, or a WRONGTYPE error if the key holds another type.
+pub(crate) fn get_live_sorted_set_mut<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+) -> Result<Option<&'a mut SortedSetValue>, Error> {
+    match db.get_live_record_mut(db_index, key)? {
+        None => Ok(None),
+        Some(record) => record.get_sorted_set_mut().map(Some),
+    }
+}
+
+/// The sorted set of `key`, which is created empty if the key does not exist.
+fn get_or_create_sorted_set_mut<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    key: &Bytes,
+) -> Result<&'a mut SortedSetValue, Error> {
+    if get_live_sorted_set_mut(db, db_index, key)?.is_none() {
+        db.get_records_ref_mut(db_index).insert(
+            key.clone(),
+            InMemoryRecord::new(RecordValue::SortedSet(SortedSetValue::default()), None),
+        );
+    }
+
+    Ok(get_live_sorted_set_mut(db, db_index, key)?.unwrap())
+}
+
+/// Parses the end of a range of scores, e.g. "1.5", "(1.5" (exclusive) or "-inf".
+fn parse_score_bound(resp_command: &RespCommand, position: usize) -> Result<ScoreBound, Error> {
+    let raw_bound = &resp_command.parameters[position];
+
+    match raw_bound.strip_prefix(b"(") {
+        None => parse_strict_f64(raw_bound).map(ScoreBound::Inclusive),
+        Some(raw_score) => parse_strict_f64(raw_score).map(ScoreBound::Exclusive),
+    }
+    .ok_or_else(|| CommandError::Other("min or max is not a float".to_owned()).into())
+}
+
+/// Parses the end of a range of members, e.g. "[a", "(a" (exclusive), "-" or "+".
+fn parse_lex_bound(resp_command: &RespCommand, position: usize) -> Result<LexBound, Error> {
+    let raw_bound = &resp_command.parameters[position];
+
+    match raw_bound.split_first() {
+        Some((b'-', [])) => Ok(LexBound::Lowest),
+        Some((b'+', [])) => Ok(LexBound::Highest),
+        Some((b'[', member)) => Ok(LexBound::Inclusive(raw_bound.slice_ref(member))),
+        Some((b'(', member)) => Ok(LexBound::Exclusive(raw_bound.slice_ref(member))),
+        _ => Err(CommandError::Other("min or max not valid string range item".to_owned()).into()),
+    }
+}
+
+/// Replies members, with their score if `with_scores`: as flat member and score pairs in RESP2,
+/// or as an array of pairs in RESP3, like Redis does.
+fn scored_members_reply(
+    members: Vec<(Bytes, f64)>,
+    with_scores: bool,
+    protocol_version: RespProtocolVersion,
+) -> RespValue {
+    let response = if !with_scores {
+        members
+            .into_iter()
+            .map(|(member, _)| RespValue::BulkString(member))
+            .collect()
+    } else if protocol_version == RespProtocolVersion::Resp3 {
+        members
+            .into_iter()
+            .map(|(member, score)| {
+                RespValue::Array(vec![
+                    RespValue::BulkString(member),
+                    RespValue::Double(score),
+                ])
+            })
+            .collect()
+    } else {
+        members
+            .into_iter()
+            .flat_map(|(member, score)| [RespValue::BulkString(member), RespValue::Double(score)])
+            .collect()
+    };
+
+    RespValue::Array(response)
+}
+
+fn resulting_score_is_nan_error() -> Error {
+    CommandError::Other("resulting score is not a number (NaN)".to_owned()).into()
+}
+
+/// Adds members to a sorted set or updates their score, e.g.: <br/>
+/// "redis-cli zadd scores 10 alice 8 bob" <br/>
+/// "redis-cli zadd scores gt ch 12 alice 7 bob" (only raises scores, and replies how many changed)
+/// <br/>
+/// "redis-cli zadd scores xx incr 2 alice" (increments the score of an existing member)
+///
+/// NX only adds new members and XX only updates existing ones, while GT and LT only update a
+/// score if the new one is greater or less. <br/>
+/// Replies how many members were added, or also updated with CH. With INCR, replies the new
+/// score, or a null if a condition was not met.
+pub(crate) async fn handle_command_zadd_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let (mut nx, mut xx, mut gt, mut lt, mut ch, mut incr) =
+        (false, false, false, false, false, false);
+
+    let mut position = 1;
+    while position < resp_command.parameters.len() {
+        let flag = match resp_command.get_parameter_str(position) {
+            Result::Ok(option) => option.to_ascii_uppercase(),
+            Err(_) => break,
+        };
+
+        match flag.as_str() {
+            RespCommandZAddOptions::NX => nx = true,
+            RespCommandZAddOptions::XX => xx = true,
+            RespCommandZAddOptions::GT => gt = true,
+            RespCommandZAddOptions::LT => lt = true,
+            RespCommandZAddOptions::CH => ch = true,
+            RespCommandZAddOptions::INCR => incr = true,
+            _ => break,
+        }
+
+        position += 1;
+    }
+
+    let score_members = &resp_command.parameters[position..];
+    if score_members.is_empty() || !score_members.len().is_multiple_of(2) {
+        return Err(CommandError::Syntax.into());
+    }
+    if nx && xx {
+        return Err(CommandError::Other(
+            "XX and NX options at the same time are not compatible".to_owned(),
+        )
+        .into());
+    }
+    if [nx, gt, lt].into_iter().filter(|flag| *flag).count() > 1 {
+        return Err(CommandError::Other(
+            "GT, LT, and/or NX options at the same time are not compatible".to_owned(),
+        )
+        .into());
+    }
+    if incr && score_members.len() > 2 {
+        return Err(CommandError::Other(
+            "INCR option supports a single increment-element pair".to_owned(),
+        )
+        .into());
+    }
+
+    // All the scores are parsed first, so that an invalid one changes nothing.
+    let score_members = score_members
+        .chunks(2)
+        .map(|score_member| {
+            parse_strict_f64(&score_member[0])
+                .map(|score| (score, &score_member[1]))
+                .ok_or(CommandError::NotAFloat)
+        })
+        .collect::<Result<Vec<(f64, &Bytes)>, CommandError>>()?;
+
+    let mut db_lock = context.mem_db.lock().await;
+    let sorted_set = get_or_create_sorted_set_mut(&mut db_lock, context.selected_db, key)?;
+
+    let (mut added_count, mut updated_count) = (0, 0);
+    let mut incremented_score = None;
+
+    for (score, member) in score_members {
+        let new_score = match sorted_set.get_score(member) {
+            None if xx => continue,
+            None => score,
+            Some(_) if nx => continue,
+            Some(current_score) => {
+                let new_score = if incr { current_score + score } else { score };
+
+                if new_score.is_nan() {
+                    return Err(resulting_score_is_nan_error());
+                }
+                if (gt && new_score <= current_score) || (lt && new_score >= current_score) {
+                    continue;
+                }
+
+                new_score
+            }
+        };
+
+        match sorted_set.insert(member.clone(), new_score) {
+            None => added_count += 1,
+            Some(previous_score) if previous_score != new_score => updated_count += 1,
+            Some(_) => {}
+        }
+        incremented_score = Some(new_score);
+    }
+
+    // XX may leave a new sorted set empty.
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    let response = if incr {
+        incremented_score.map_or(RespValue::NullBulkString, RespValue::Double)
+    } else if ch {
+        RespValue::Integer(added_count + updated_count)
+    } else {
+        RespValue::Integer(added_count)
+    };
+
+    if added_count + updated_count == 0 {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Replies how many members a sorted set has, e.g.: "redis-cli zcard scores"
+pub(crate) async fn handle_command_zcard_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let member_count = get_live_sorted_set_mut(&mut db_lock, context.selected_db, key)?
+        .map_or(0, |sorted_set| sorted_set.len());
+
+    context.set_response(RespValue::Integer(member_count as i64).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli zscore scores alice" <br/>
+/// Replies a null if the member or the key does not exist.
+pub(crate) async fn handle_command_zscore_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (key, member) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let score = get_live_sorted_set_mut(&mut db_lock, context.selected_db, key)?
+        .and_then(|sorted_set| sorted_set.get_score(member));
+
+    context.set_response(
+        score
+            .map_or(RespValue::NullBulkString, RespValue::Double)
+            .into(),
+    );
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli zincrby scores 5 alice" <br/>
+/// A missing member counts as scoring 0. Replies the new score.
+pub(crate) async fn handle_command_zincrby_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (key, member) = (&resp_command.parameters[0], &resp_command.parameters[2]);
+    let increment = resp_command.get_parameter_f64(1)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let current_score = get_live_sorted_set_mut(&mut db_lock, context.selected_db, key)?
+        .and_then(|sorted_set| sorted_set.get_score(member))
+        .unwrap_or(0.0);
+
+    let new_score = current_score + increment;
+    if new_score.is_nan() {
+        return Err(resulting_score_is_nan_error());
+    }
+
+    get_or_create_sorted_set_mut(&mut db_lock, context.selected_db, key)?
+        .insert(member.clone(), new_score);
+
+    context.set_response(RespValue::Double(new_score).into());
+
+    Ok(())
+}
+
+/// Removes members from a sorted set, e.g.: "redis-cli zrem scores bob" <br/>
+/// Replies how many members were removed. The key is deleted with its last member.
+pub(crate) async fn handle_command_zrem_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let removed_count = match get_live_sorted_set_mut(&mut db_lock, context.selected_db, key)? {
+        None => 0,
+        Some(sorted_set) => resp_command.parameters[1..]
+            .iter()
+            .filter(|member| sorted_set.remove(member).is_some())
+            .count(),
+    };
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    if removed_count == 0 {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(RespValue::Integer(removed_count as i64).into());
+
+    Ok(())
+}
+
+/// Handles ZRANK and ZREVRANK, which reply the position of a member from the lowest or the
+/// highest score, e.g.: <br/>
+/// "redis-cli zrevrank scores alice" <br/>
+/// "redis-cli zrank scores alice withscore" (replies the rank and the score)
+pub(crate) async fn handle_command_zrank_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let (key, member) = (&resp_command.parameters[0], &resp_command.parameters[1]);
+    let is_reversed = resp_command.name == RespCommandNames::ZREVRANK;
+
+    let with_score = match &resp_command.parameters[2..] {
+        [] => false,
+        [option] if option.eq_ignore_ascii_case(RespCommandZRankOptions::WITHSCORE.as_bytes()) => {
+            true
+        }
+        _ => return Err(CommandError::Syntax.into()),
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let rank_score =
+        get_live_sorted_set_mut(&mut db_lock, context.selected_db, key)?.and_then(|sorted_set| {
+            let rank = sorted_set.get_rank(member)?;
+            let rank = if is_reversed {
+                sorted_set.len() - 1 - rank
+            } else {
+                rank
+            };
+
+            Some((rank, sorted_set.get_score(member)?))
+        });
+
+    let response = match rank_score {
+        None if with_score => RespValue::NullArray,
+        None => RespValue::NullBulkString,
+        Some((rank, score)) if with_score => RespValue::Array(vec![
+            RespValue::Integer(rank as i64),
+            RespValue::Double(score),
+        ]),
+        Some((rank, _)) => RespValue::Integer(rank as i64),
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Replies how many members of a sorted set score between a minimum and a maximum, e.g.: <br/>
+/// "redis-cli zcount scores (5 +inf" (scores above 5)
+pub(crate) async fn handle_command_zcount_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let (min, max) = (
+        parse_score_bound(resp_command, 1)?,
+        parse_score_bound(resp_command, 2)?,
+    );
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let member_count = get_live_sorted_set_mut(&mut db_lock, context.selected_db, key)?
+        .map_or(0, |sorted_set| sorted_set.range_by_score(min, max).len());
+
+    context.set_response(RespValue::Integer(member_count as i64).into());
+
+    Ok(())
+}
+
+/// What ZRANGE walks, which tells how its `start` and `stop` are parsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ZRangeBy {
+    /// Positions from the lowest score, where negative ones count from the highest score.
+    Rank,
+    Score,
+    /// Members, which are only ordered lexicographically if they all have the same score.
+    Lex,
+}
+
+/// The range replied by ZRANGE, from its minimum to its maximum.
+#[derive(Debug, Clone, PartialEq)]
+enum ZRangeBounds {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+/// Replies a range of a sorted set, with the unified syntax of Redis 6.2, e.g.: <br/>
+/// "redis-cli zrange scores 0 9 rev withscores" (the 10 highest scores) <br/>
+/// "redis-cli zrange scores (5 +inf byscore limit 0 3" (the first 3 members scoring above 5) <br/>
+/// "redis-cli zrange names [a (c bylex" (the members from "a" to before "c")
+///
+/// With REV, the range is walked from the highest score, so with BYSCORE or BYLEX the maximum
+/// comes first. LIMIT skips `offset` members and replies up to `count` of them, or all of them if
+/// `count` is negative.
+pub(crate) async fn handle_command_zrange_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let mut range_by = ZRangeBy::Rank;
+    let (mut is_reversed, mut with_scores) = (false, false);
+    let mut limit = None;
+
+    let mut position = 3;
+    while position < resp_command.parameters.len() {
+        match resp_command
+            .get_parameter_str(position)?
+            .to_ascii_uppercase()
+            .as_str()
+        {
+            RespCommandZRangeOptions::BYSCORE if range_by == ZRangeBy::Rank => {
+                range_by = ZRangeBy::Score
+            }
+            RespCommandZRangeOptions::BYLEX if range_by == ZRangeBy::Rank => {
+                range_by = ZRangeBy::Lex
+            }
+            RespCommandZRangeOptions::REV => is_reversed = true,
+            RespCommandZRangeOptions::WITHSCORES => with_scores = true,
+            RespCommandZRangeOptions::LIMIT if position + 2 < resp_command.parameters.len() => {
+                limit = Some((
+                    resp_command.get_parameter_i64(position + 1)?,
+                    resp_command.get_parameter_i64(position + 2)?,
+                ));
+                position += 2;
+            }
+            _ => return Err(CommandError::Syntax.into()),
+        }
+
+        position += 1;
+    }
+
+    if limit.is_some() && range_by == ZRangeBy::Rank {
+        return Err(CommandError::Other(
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                .to_owned(),
+        )
+        .into());
+    }
+    if with_scores && range_by == ZRangeBy::Lex {
+        return Err(CommandError::Other(
+            "syntax error, WITHSCORES not supported in combination with BYLEX".to_owned(),
+        )
+        .into());
+    }
+
+    // With REV, the range goes from its maximum to its minimum.
+    let (min_position, max_position) = if is_reversed { (2, 1) } else { (1, 2) };
+    let bounds = match range_by {
+        ZRangeBy::Rank => ZRangeBounds::Rank(
+            resp_command.get_parameter_i64(1)?,
+            resp_command.get_parameter_i64(2)?,
+        ),
+        ZRangeBy::Score => ZRangeBounds::Score(
+            parse_score_bound(resp_command, min_position)?,
+            parse_score_bound(resp_command, max_position)?,
+        ),
+        ZRangeBy::Lex => ZRangeBounds::Lex(
+            parse_lex_bound(resp_command, min_position)?,
+            parse_lex_bound(resp_command, max_position)?,
+        ),
+    };
+
+    let (offset, count) = match limit {
+        None => (0, usize::MAX),
+        Some((offset, _)) if offset < 0 => (0, 0),
+        Some((offset, count)) => (
+            offset as usize,
+            usize::try_from(count).unwrap_or(usize::MAX),
+        ),
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let members: Vec<(Bytes, f64)> =
+        match get_live_sorted_set_mut(&mut db_lock, context.selected_db, key)? {
+            None => Vec::new(),
+            Some(sorted_set) => {
+                let range: Box<dyn DoubleEndedIterator<Item = (&Bytes, f64)>> = match &bounds {
+                    ZRangeBounds::Rank(start, stop) => {
+                        let len = sorted_set.len();
+                        let (start, count) = get_rank_range(*start, *stop, len);
+
+                        // With REV, ranks count from the highest score.
+                        let start = if is_reversed {
+                            len - start - count
+                        } else {
+                            start
+                        };
+
+                        Box::new(sorted_set.range_by_rank(start..start + count))
+                    }
+                    ZRangeBounds::Score(min, max) => {
+                        Box::new(sorted_set.range_by_score(*min, *max))
+                    }
+                    ZRangeBounds::Lex(min, max) => Box::new(sorted_set.range_by_lex(min, max)),
+                };
+
+                let range: Box<dyn Iterator<Item = (&Bytes, f64)>> = if is_reversed {
+                    Box::new(range.rev())
+                } else {
+                    Box::new(range)
+                };
+
+                range
+                    .skip(offset)
+                    .take(count)
+                    .map(|(member, score)| (member.clone(), score))
+                    .collect()
+            }
+        };
+
+    context
+        .set_response(scored_members_reply(members, with_scores, context.protocol_version).into());
+
+    Ok(())
+}
+
+/// Turns the `start` and `stop` ranks of ZRANGE, which may count from the end, into the first
+/// position and the number of members of the range.
+fn get_rank_range(start: i64, stop: i64, len: usize) -> (usize, usize) {
+    let len = len as i64;
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        stop + len
+    } else {
+        stop.min(len - 1)
+    };
+
+    if start > stop || start >= len {
+        return (0, 0);
+    }
+
+    (start as usize, (stop - start + 1) as usize)
+}
+
+/// Handles ZPOPMIN and ZPOPMAX, which remove the members with the lowest or highest scores and
+/// reply them with their score, e.g.: <br/>
+/// "redis-cli zpopmax scores" <br/>
+/// "redis-cli zpopmin scores 3"
+pub(crate) async fn handle_command_zpopmin_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let highest = resp_command.name == RespCommandNames::ZPOPMAX;
+
+    let count = match &resp_command.parameters[1..] {
+        [] => None,
+        [_] => Some(
+            usize::try_from(resp_command.get_parameter_i64(1)?).map_err(|_| {
+                CommandError::Other("value is out of range, must be positive".to_owned())
+            })?,
+        ),
+        _ => return Err(CommandError::Syntax.into()),
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let members: Vec<(Bytes, f64)> =
+        match get_live_sorted_set_mut(&mut db_lock, context.selected_db, key)? {
+            None => Vec::new(),
+            Some(sorted_set) => (0..count.unwrap_or(1))
+                .map_while(|_| sorted_set.pop(highest))
+                .collect(),
+        };
+
+    db_lock.remove_if_empty(context.selected_db, key);
+
+    // Like in Redis, a single member is replied as a flat pair even in RESP3.
+    let protocol_version = match count {
+        None => RespProtocolVersion::Resp2,
+        Some(_) => context.protocol_version,
+    };
+
+    if members.is_empty() {
+        context.propagation = Propagation::Rewritten(Vec::new());
+    }
+
+    context.set_response(scored_members_reply(members, true, protocol_version).into());
+
+    Ok(())
+}
+
+/// How ZUNIONSTORE and ZINTERSTORE combine the scores of a member that is in several keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(&self, score: f64, other_score: f64) -> f64 {
+        match self {
+            // Like in Redis, inf plus -inf sums to 0 rather than NaN.
+            Aggregate::Sum => zero_if_nan(score + other_score),
+            Aggregate::Min => score.min(other_score),
+            Aggregate::Max => score.max(other_score),
+        }
+    }
+}
+
+fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+/// A key read by ZUNIONSTORE and the like, which accept sets too, whose members all score 1.
+enum ScoredMembers<'a> {
+    SortedSet(&'a SortedSetValue),
+    Set(&'a SetValue),
+}
+
+impl ScoredMembers<'_> {
+    fn len(&self) -> usize {
+        match self {
+            ScoredMembers::SortedSet(sorted_set) => sorted_set.len(),
+            ScoredMembers::Set(set) => set.len(),
+        }
+    }
+
+    fn get_score(&self, member: &Bytes) -> Option<f64> {
+        match self {
+            ScoredMembers::SortedSet(sorted_set) => sorted_set.get_score(member),
+            ScoredMembers::Set(set) => set.contains(member).then_some(1.0),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Bytes, f64)> + '_> {
+        match self {
+            ScoredMembers::SortedSet(sorted_set) => Box::new(
+                sorted_set
+                    .iter()
+                    .map(|(member, score)| (member.clone(), score)),
+            ),
+            ScoredMembers::Set(set) => Box::new(set.iter().map(|member| (member, 1.0))),
+        }
+    }
+}
+
+/// The sorted sets or sets of `keys`, in order, with `None` for the keys that don't exist, or a
+/// WRONGTYPE error if any of the keys holds another type.
+fn get_live_scored_members<'a>(
+    db: &'a mut InMemoryDb,
+    db_index: usize,
+    keys: &[Bytes],
+) -> Result<Vec<Option<ScoredMembers<'a>>>, Error> {
+    // Expired keys are removed first, so that the values can then be borrowed all at once.
+    for key in keys {
+        db.get_live_record_mut(db_index, key)?;
+    }
+
+    let records = db.get_records_ref(db_index);
+
+    keys.iter()
+        .map(|key| {
+            records
+                .get(key)
+                .map(|record| match &record.value {
+                    RecordValue::SortedSet(sorted_set) => Ok(ScoredMembers::SortedSet(sorted_set)),
+                    RecordValue::Set(set) => Ok(ScoredMembers::Set(set)),
+                    _ => Err(CommandError::WrongType.into()),
+                })
+                .transpose()
+        })
+        .collect()
+}
+
+/// Handles ZUNIONSTORE, ZINTERSTORE and ZDIFFSTORE, which store the result of their operation in
+/// the first key, e.g.: <br/>
+/// "redis-cli zunionstore scores:all 2 scores:1 scores:2 weights 1 0.5 aggregate max" <br/>
+/// "redis-cli zdiffstore scores:new 2 scores:all scores:seen"
+///
+/// The score of a member is multiplied by the weight of its key, 1 by default, and the scores of
+/// a member in several keys are summed, or their minimum or maximum is kept. Sets count as
+/// sorted sets whose members all score 1. ZDIFFSTORE keeps the scores of the first key, and
+/// accepts neither WEIGHTS nor AGGREGATE.
+///
+/// Replies how many members the result has. The destination is replaced whatever its type, and
+/// deleted if the result is empty.
+pub(crate) async fn handle_command_zunionstore_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let destination = &resp_command.parameters[0];
+    let is_diff = resp_command.name == RespCommandNames::ZDIFFSTORE;
+
+    let key_count = resp_command.get_parameter_i64(1)?;
+    if key_count < 1 {
+        return Err(CommandError::Other(format!(
+            "at least 1 input key is needed for '{}' command",
+            resp_command.name.to_ascii_lowercase()
+        ))
+        .into());
+    }
+    let key_count = key_count as usize;
+    if key_count > resp_command.parameters.len() - 2 {
+        return Err(CommandError::Syntax.into());
+    }
+    let keys = &resp_command.parameters[2..2 + key_count];
+
+    let mut weights = vec![1.0; key_count];
+    let mut aggregate = Aggregate::Sum;
+
+    let mut position = 2 + key_count;
+    while position < resp_command.parameters.len() {
+        let option = resp_command
+            .get_parameter_str(position)?
+            .to_ascii_uppercase();
+
+        match option.as_str() {
+            RespCommandZStoreOptions::WEIGHTS
+                if !is_diff && position + key_count < resp_command.parameters.len() =>
+            {
+                for (index, weight) in weights.iter_mut().enumerate() {
+                    *weight = parse_strict_f64(&resp_command.parameters[position + 1 + index])
+                        .ok_or_else(|| {
+                            CommandError::Other("weight value is not a float".to_owned())
+                        })?;
+                }
+                position += 1 + key_count;
+            }
+            RespCommandZStoreOptions::AGGREGATE
+                if !is_diff && position + 1 < resp_command.parameters.len() =>
+            {
+                aggregate = match resp_command
+                    .get_parameter_str(position + 1)?
+                    .to_ascii_uppercase()
+                    .as_str()
+                {
+                    RespCommandZStoreOptions::SUM => Aggregate::Sum,
+                    RespCommandZStoreOptions::MIN => Aggregate::Min,
+                    RespCommandZStoreOptions::MAX => Aggregate::Max,
+                    _ => return Err(CommandError::Syntax.into()),
+                };
+                position += 2;
+            }
+            _ => return Err(CommandError::Syntax.into()),
+        }
+    }
+
+    let mut db_lock = context.mem_db.lock().await;
+    let inputs = get_live_scored_members(&mut db_lock, context.selected_db, keys)?;
+
+    let weighted = |score: f64, index: usize| zero_if_nan(score * weights[index]);
+    let mut result = SortedSetValue::default();
+
+    match resp_command.name.as_str() {
+        RespCommandNames::ZUNIONSTORE => {
+            let mut scores: HashMap<Bytes, f64> = HashMap::new();
+
+            for (index, input) in inputs.iter().enumerate() {
+                for (member, score) in input.iter().flat_map(ScoredMembers::iter) {
+                    let score = weighted(score, index);
+
+                    scores
+                        .entry(member)
+                        .and_modify(|current_score| {
+                            *current_score = aggregate.apply(*current_score, score)
+                        })
+                        .or_insert(score);
+                }
+            }
+
+            for (member, score) in scores {
+                result.insert(member, score);
+            }
+        }
+        RespCommandNames::ZINTERSTORE => {
+            let inputs: Option<Vec<&ScoredMembers>> = inputs.iter().map(Option::as_ref).collect();
+
+            // A key that does not exist empties the intersection.
+            if let Some(inputs) = inputs {
+                // Walking the smallest input checks as few members as possible.
+                let smallest_index = (0..inputs.len())
+                    .min_by_key(|index| inputs[*index].len())
+                    .unwrap();
+
+                for (member, _) in inputs[smallest_index].iter() {
+                    let scores: Option<Vec<f64>> = inputs
+                        .iter()
+                        .enumerate()
+                        .map(|(index, input)| {
+                            input.get_score(&member).map(|score| weighted(score, index))
+                        })
+                        .collect();
+
+                    if let Some(score) = scores.and_then(|scores| {
+                        scores
+                            .into_iter()
+                            .reduce(|score, other_score| aggregate.apply(score, other_score))
+                    }) {
+                        result.insert(member, score);
+                    }
+                }
+            }
+        }
+        _ => {
+            if let Some((Some(first), others)) = inputs.split_first() {
+                for (member, score) in first.iter() {
+                    if others
+                        .iter()
+                        .flatten()
+                        .all(|other| other.get_score(&member).is_none())
+                    {
+                        result.insert(member, score);
+                    }
+                }
+            }
+        }
+    }
+
+    let member_count = result.len();
+
+    if result.is_empty() {
+        db_lock.remove_live_record(context.selected_db, destination)?;
+    } else {
+        db_lock.get_records_ref_mut(context.selected_db).insert(
+            destination.clone(),
+            InMemoryRecord::new(RecordValue::SortedSet(result), None),
+        );
+    }
+
+    context.set_response(RespValue::Integer(member_count as i64).into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        resp_parser::shared::RespValue,
+        test_helpers::utils::{
+            bulk_string, bulk_strings, create_test_mem_db, error, send_test_request,
+        },
+    };
+
+    fn with_scores(members: &[(&str, f64)]) -> RespValue {
+        RespValue::Array(
+            members
+                .iter()
+                .flat_map(|(member, score)| [bulk_string(member), RespValue::Double(*score)])
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn handle_command_zadd_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"ZADD z 1 a 2 b 3 c\r\nZADD z 5 a 2 b 4 d\r\nZADD z CH 6 a 1 e\r\nZADD z NX 0 a 0 f\r\nZADD z XX 9 g 7 f\r\nZADD z GT CH 5 a 1 f 8 h\r\nZADD z LT 4 a\r\nZADD z INCR 2.5 a\r\nZADD z XX INCR 1 nope\r\nZADD z GT INCR -1 a\r\nZCARD z\r\nZRANGE z 0 -1 WITHSCORES\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(3),
+                RespValue::Integer(1),
+                RespValue::Integer(2),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                RespValue::Double(6.5),
+                RespValue::NullBulkString,
+                RespValue::NullBulkString,
+                RespValue::Integer(7),
+                with_scores(&[
+                    ("e", 1.0),
+                    ("b", 2.0),
+                    ("c", 3.0),
+                    ("d", 4.0),
+                    ("a", 6.5),
+                    ("f", 7.0),
+                    ("h", 8.0),
+                ]),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"ZADD z NX XX 1 a\r\nZADD z GT LT 1 a\r\nZADD z NX GT 1 a\r\nZADD z INCR 1 a 2 b\r\nZADD z CH 1\r\nZADD z 1 a 2\r\nZADD z nan a\r\nZADD z 1 a x b\r\nZADD z INCR -inf e\r\nZADD z INCR inf e\r\nZADD x XX 1 a\r\nEXISTS x\r\n",
+            )
+            .await?,
+            vec![
+                error("ERR XX and NX options at the same time are not compatible"),
+                error("ERR GT, LT, and/or NX options at the same time are not compatible"),
+                error("ERR GT, LT, and/or NX options at the same time are not compatible"),
+                error("ERR INCR option supports a single increment-element pair"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR value is not a valid float"),
+                error("ERR value is not a valid float"),
+                RespValue::Double(f64::NEG_INFINITY),
+                error("ERR resulting score is not a number (NaN)"),
+                RespValue::Integer(0),
+                RespValue::Integer(0),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sorted_set_commands_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"ZADD z 1 a 2 b 3 c\r\nZSCORE z b\r\nZSCORE z nope\r\nZINCRBY z 1.5 b\r\nZINCRBY z 4 d\r\nZINCRBY z x d\r\nZRANK z b\r\nZREVRANK z b\r\nZRANK z d WITHSCORE\r\nZREVRANK z nope\r\nZRANK z nope WITHSCORE\r\nZRANK z b WITHSCORES\r\nZCOUNT z 2 4\r\nZCOUNT z (3 +inf\r\nZCOUNT z -inf (1\r\nZCOUNT z x 1\r\nZREM z a nope\r\nZREM z b c d\r\nEXISTS z\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(3),
+                RespValue::Double(2.0),
+                RespValue::NullBulkString,
+                RespValue::Double(3.5),
+                RespValue::Double(4.0),
+                error("ERR value is not a valid float"),
+                RespValue::Integer(2),
+                RespValue::Integer(1),
+                RespValue::Array(vec![RespValue::Integer(3), RespValue::Double(4.0)]),
+                RespValue::NullBulkString,
+                RespValue::NullArray,
+                error("ERR syntax error"),
+                RespValue::Integer(3),
+                RespValue::Integer(2),
+                RespValue::Integer(0),
+                error("ERR min or max is not a float"),
+                RespValue::Integer(1),
+                RespValue::Integer(3),
+                RespValue::Integer(0),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET s v\r\nZADD s 1 a\r\nZSCORE s a\r\nZADD z 1 a\r\nGET z\r\nTYPE z\r\nOBJECT ENCODING z\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                RespValue::Integer(1),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                RespValue::SimpleString("zset".to_owned()),
+                bulk_string("skiplist"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_zrange_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"ZADD z 1 a 2 b 3 c 4 d 5 e\r\nZRANGE z 0 -1\r\nZRANGE z -2 10\r\nZRANGE z 3 1\r\nZRANGE z 0 1 REV WITHSCORES\r\nZRANGE z 2 4 BYSCORE\r\nZRANGE z (2 +inf BYSCORE LIMIT 1 2\r\nZRANGE z +inf (2 BYSCORE REV LIMIT 0 2 WITHSCORES\r\nZRANGE z -inf +inf BYSCORE LIMIT 3 -1\r\nZRANGE z -inf +inf BYSCORE LIMIT -1 2\r\nZRANGE nope 0 -1\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(5),
+                bulk_strings(&["a", "b", "c", "d", "e"]),
+                bulk_strings(&["d", "e"]),
+                bulk_strings(&[]),
+                with_scores(&[("e", 5.0), ("d", 4.0)]),
+                bulk_strings(&["b", "c", "d"]),
+                bulk_strings(&["d", "e"]),
+                with_scores(&[("e", 5.0), ("d", 4.0)]),
+                bulk_strings(&["d", "e"]),
+                bulk_strings(&[]),
+                bulk_strings(&[]),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"ZADD l 0 a 0 b 0 c 0 d\r\nZRANGE l [b + BYLEX\r\nZRANGE l - (c BYLEX\r\nZRANGE l [c - BYLEX REV\r\nZRANGE l - + BYLEX LIMIT 1 2\r\nZRANGE l + - BYLEX\r\nZRANGE l b c BYLEX\r\nZRANGE l - + BYLEX WITHSCORES\r\nZRANGE l 0 1 LIMIT 0 1\r\nZRANGE l 0 1 BYSCORE BYLEX\r\nZRANGE l 0 1 LIMIT 0\r\nZRANGE l a 1\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(4),
+                bulk_strings(&["b", "c", "d"]),
+                bulk_strings(&["a", "b"]),
+                bulk_strings(&["c", "b", "a"]),
+                bulk_strings(&["b", "c"]),
+                bulk_strings(&[]),
+                error("ERR min or max not valid string range item"),
+                error("ERR syntax error, WITHSCORES not supported in combination with BYLEX"),
+                error(
+                    "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                ),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR value is not an integer or out of range"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_zpopmin_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"ZADD z 1 a 2 b 3 c 4 d\r\nZPOPMIN z\r\nZPOPMAX z 2\r\nZPOPMIN z 0\r\nZPOPMIN z 5\r\nEXISTS z\r\nZPOPMAX z\r\nZPOPMIN z -1\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(4),
+                with_scores(&[("a", 1.0)]),
+                with_scores(&[("d", 4.0), ("c", 3.0)]),
+                with_scores(&[]),
+                with_scores(&[("b", 2.0)]),
+                RespValue::Integer(0),
+                with_scores(&[]),
+                error("ERR value is out of range, must be positive"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_zunionstore_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"ZADD z1 1 a 2 b 3 c\r\nZADD z2 10 b 20 c 30 d\r\nSADD s c d e\r\nZUNIONSTORE u 2 z1 z2\r\nZRANGE u 0 -1 WITHSCORES\r\nZUNIONSTORE u 3 z1 z2 s WEIGHTS 2 0.5 -1 AGGREGATE MAX\r\nZRANGE u 0 -1 WITHSCORES\r\nZINTERSTORE i 2 z1 z2 AGGREGATE MIN\r\nZRANGE i 0 -1 WITHSCORES\r\nZINTERSTORE i 3 z1 z2 s\r\nZRANGE i 0 -1 WITHSCORES\r\nZDIFFSTORE d 2 z1 s\r\nZRANGE d 0 -1 WITHSCORES\r\nZINTERSTORE i 2 z1 nope\r\nEXISTS i\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(3),
+                RespValue::Integer(3),
+                RespValue::Integer(3),
+                RespValue::Integer(4),
+                with_scores(&[("a", 1.0), ("b", 12.0), ("c", 23.0), ("d", 30.0)]),
+                RespValue::Integer(5),
+                with_scores(&[("e", -1.0), ("a", 2.0), ("b", 5.0), ("c", 10.0), ("d", 15.0)]),
+                RespValue::Integer(2),
+                with_scores(&[("b", 2.0), ("c", 3.0)]),
+                RespValue::Integer(1),
+                with_scores(&[("c", 24.0)]),
+                RespValue::Integer(2),
+                with_scores(&[("a", 1.0), ("b", 2.0)]),
+                RespValue::Integer(0),
+                RespValue::Integer(0),
+            ]
+        );
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"ZUNIONSTORE u 0 z1\r\nZUNIONSTORE u 3 z1 z2\r\nZUNIONSTORE u 2 z1 z2 WEIGHTS 1\r\nZUNIONSTORE u 2 z1 z2 WEIGHTS 1 x\r\nZUNIONSTORE u 1 z1 AGGREGATE AVG\r\nZDIFFSTORE d 1 z1 WEIGHTS 1\r\nSET str v\r\nZUNIONSTORE u 2 z1 str\r\nZUNIONSTORE str 1 z1\r\nTYPE str\r\n",
+            )
+            .await?,
+            vec![
+                error("ERR at least 1 input key is needed for 'zunionstore' command"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR weight value is not a float"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                RespValue::ok(),
+                error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+                RespValue::Integer(3),
+                RespValue::SimpleString("zset".to_owned()),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1329 @@
+use crate::{
+    models::{
+        command_error::CommandError,
+        connection_context::{ConnectionContext, Propagation},
+        db::{
+            in_memory_db::InMemoryDb,
+            in_memory_record::{InMemoryRecord, RecordValue},
+        },
+    },
+    node::command_handlers::keys,
+    resp_parser::shared::{
+        RespCommand, RespCommandGetExOptions, RespCommandLcsOptions, RespCommandNames,
+        RespCommandSetOptions, RespValue,
+    },
+    utils::{format_float_value, parse_strict_f64, parse_strict_i64, unix_time_millis},
+};
+
+use anyhow::{Error, Ok};
+use bytes::{Bytes, BytesMut};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SetCondition {
+    IfNotExists,
+    IfExists,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expiry {
+    /// Milliseconds from now.
+    In(u128),
+    /// Unix time in milliseconds.
+    At(u128),
+    /// SET only.
+    KeepTtl,
+    /// GETEX only.
+    Persist,
+}
+
+#[derive(Debug, Default)]
+struct SetOptions {
+    condition: Option<SetCondition>,
+    expiry: Option<Expiry>,
+    get: bool,
+}
+
+/// Parses the options after the key and value of a SET, which can be given in any order.
+fn parse_set_options(resp_command: &RespCommand) -> Result<SetOptions, Error> {
+    let mut options = SetOptions::default();
+    let mut position = 2;
+
+    while position < resp_command.parameters.len() {
+        let option = resp_command
+            .get_parameter_str(position)?
+            .to_ascii_uppercase();
+        let has_argument = position + 1 < resp_command.parameters.len();
+
+        match option.as_str() {
+            RespCommandSetOptions::IF_NOT_EXISTS | RespCommandSetOptions::IF_EXISTS => {
+                let condition = if option == RespCommandSetOptions::IF_NOT_EXISTS {
+                    SetCondition::IfNotExists
+                } else {
+                    SetCondition::IfExists
+                };
+
+                if options
+                    .condition
+                    .is_some_and(|current| current != condition)
+                {
+                    return Err(CommandError::Syntax.into());
+                }
+
+                options.condition = Some(condition);
+            }
+            RespCommandSetOptions::GET => options.get = true,
+            RespCommandSetOptions::KEEP_TTL if options.expiry.is_none() => {
+                options.expiry = Some(Expiry::KeepTtl);
+            }
+            RespCommandSetOptions::EXPIRY_SECONDS
+            | RespCommandSetOptions::EXPIRY_MILLISECONDS
+            | RespCommandSetOptions::EXPIRY_AT_SECONDS
+            | RespCommandSetOptions::EXPIRY_AT_MILLISECONDS
+                if options.expiry.is_none() && has_argument =>
+            {
+                position += 1;
+                options.expiry = Some(parse_expiry(resp_command, &option, position)?);
+            }
+            _ => return Err(CommandError::Syntax.into()),
+        };
+
+        position += 1;
+    }
+
+    Ok(options)
+}
+
+/// Parses the amount of an EX, PX, EXAT or PXAT `option`, at `position`.
+fn parse_expiry(
+    resp_command: &RespCommand,
+    option: &str,
+    position: usize,
+) -> Result<Expiry, Error> {
+    let invalid_expire_time_error = || {
+        CommandError::Other(format!(
+            "invalid expire time in '{}' command",
+            resp_command.name.to_ascii_lowercase()
+        ))
+    };
+
+    let amount = resp_command.get_parameter_i64(position)?;
+
+    if amount <= 0 {
+        return Err(invalid_expire_time_error().into());
+    }
+
+    let milliseconds = match option {
+        RespCommandSetOptions::EXPIRY_SECONDS | RespCommandSetOptions::EXPIRY_AT_SECONDS => amount
+            .checked_mul(1000)
+            .ok_or_else(invalid_expire_time_error)?,
+        _ => amount,
+    } as u128;
+
+    Ok(match option {
+        RespCommandSetOptions::EXPIRY_SECONDS | RespCommandSetOptions::EXPIRY_MILLISECONDS => {
+            Expiry::In(milliseconds)
+        }
+        _ => Expiry::At(milliseconds),
+    })
+}
+
+/// Example commands: <br/>
+/// "redis-cli set foo bar" <br/>
+/// "redis-cli set lock owner ex 10 nx" (only set if missing, expiring in 10 seconds) <br/>
+/// "redis-cli set foo baz get" (reply the previous value)
+///
+/// The reply is OK, or a null if an NX or XX condition is not met, unless GET is given, in
+/// which case it is always the previous value.
+pub(crate) async fn handle_command_set_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let value = &resp_command.parameters[1];
+    let options = parse_set_options(resp_command)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let existing_record = db_lock.get_live_record_mut(context.selected_db, key)?;
+    // SET replaces a value of any type, but only replies a previous string value.
+    let previous_value = match existing_record.as_deref() {
+        Some(record) if options.get => Some(record.get_string_ref()?.clone()),
+        _ => None,
+    };
+
+    let is_condition_met = match options.condition {
+        None => true,
+        Some(SetCondition::IfNotExists) => existing_record.is_none(),
+        Some(SetCondition::IfExists) => existing_record.is_some(),
+    };
+
+    let response = |previous_value: Option<Bytes>| match (options.get, previous_value) {
+        (true, Some(previous_value)) => RespValue::BulkString(previous_value),
+        (true, None) => RespValue::NullBulkString,
+        (false, _) if is_condition_met => RespValue::ok(),
+        (false, _) => RespValue::NullBulkString,
+    };
+
+    if !is_condition_met {
+        context.propagation = Propagation::Rewritten(Vec::new());
+        context.set_response(response(previous_value).into());
+
+        return Ok(());
+    }
+
+    let now = unix_time_millis()?;
+    let expire_at_milli = match options.expiry {
+        None | Some(Expiry::Persist) => None,
+        Some(Expiry::In(milliseconds)) => Some(now + milliseconds),
+        Some(Expiry::At(unix_time_milli)) => Some(unix_time_milli),
+        Some(Expiry::KeepTtl) => existing_record.and_then(|record| record.expire_at_milli),
+    };
+
+    if expire_at_milli.is_some_and(|expire_at_milli| expire_at_milli <= now) {
+        // The key expires right away, so it is deleted rather than set.
+        db_lock.get_records_ref_mut(context.selected_db).remove(key);
+
+        context.propagation = Propagation::Rewritten(vec![keys::del_command(key)]);
+    } else {
+        db_lock.get_records_ref_mut(context.selected_db).insert(
+            key.clone(),
+            InMemoryRecord::new(value.clone(), expire_at_milli),
+        );
+
+        if let (Some(Expiry::In(_) | Expiry::At(_)), Some(expire_at_milli)) =
+            (options.expiry, expire_at_milli)
+        {
+            // Replicas get the absolute deadline, so that their copy expires at the same time.
+            context.propagation = Propagation::Rewritten(vec![RespCommand::new(
+                RespCommandNames::SET,
+                vec![
+                    key.clone(),
+                    value.clone(),
+                    Bytes::from_static(RespCommandSetOptions::EXPIRY_AT_MILLISECONDS.as_bytes()),
+                    Bytes::from(expire_at_milli.to_string()),
+                ],
+            )]);
+        }
+    }
+
+    context.set_response(response(previous_value).into());
+
+    Ok(())
+}
+
+pub(crate) async fn handle_command_get_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let response = match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => RespValue::NullBulkString,
+        Some(record) => RespValue::BulkString(record.get_string_ref()?.clone()),
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli mget foo bar" <br/>
+/// Replies a null for each key that does not exist or does not hold a string.
+pub(crate) async fn handle_command_mget_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let keys = &context.get_request_resp_command_ref().unwrap().parameters;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut values = Vec::with_capacity(keys.len());
+    for key in keys {
+        values.push(
+            match db_lock.get_live_record_mut(context.selected_db, key)? {
+                None => RespValue::NullBulkString,
+                Some(record) => match record.get_string_ref() {
+                    Err(_) => RespValue::NullBulkString,
+                    Result::Ok(value) => RespValue::BulkString(value.clone()),
+                },
+            },
+        );
+    }
+
+    context.set_response(RespValue::Array(values).into());
+
+    Ok(())
+}
+
+/// Handles MSET and MSETNX, e.g.: <br/>
+/// "redis-cli mset foo 1 bar 2"
+///
+/// All keys are set under the same DB lock, so no client sees some of them set and not others.
+/// MSETNX sets none of the keys if any of them exists, and replies whether they were set.
+pub(crate) async fn handle_command_mset_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let parameters = &resp_command.parameters;
+    let only_if_none_exists = resp_command.name == RespCommandNames::MSETNX;
+
+    if !parameters.len().is_multiple_of(2) {
+        return Err(CommandError::wrong_arity(&resp_command.name).into());
+    }
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    if only_if_none_exists {
+        for key in parameters.iter().step_by(2) {
+            if db_lock
+                .get_live_record_mut(context.selected_db, key)?
+                .is_some()
+            {
+                context.propagation = Propagation::Rewritten(Vec::new());
+                context.set_response(RespValue::Integer(0).into());
+
+                return Ok(());
+            }
+        }
+    }
+
+    for key_value in parameters.chunks_exact(2) {
+        db_lock.get_records_ref_mut(context.selected_db).insert(
+            key_value[0].clone(),
+            InMemoryRecord::new(key_value[1].clone(), None),
+        );
+    }
+
+    context.set_response(if only_if_none_exists {
+        RespValue::Integer(1).into()
+    } else {
+        RespValue::ok().into()
+    });
+
+    Ok(())
+}
+
+/// Sets the value of a key and replies its previous value, like "SET key value GET". <br/>
+/// E.g.: "redis-cli getset foo bar"
+pub(crate) async fn handle_command_getset_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let parameters = &context.get_request_resp_command_ref().unwrap().parameters;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let previous_value = match db_lock.get_live_record_mut(context.selected_db, &parameters[0])? {
+        None => None,
+        Some(record) => Some(record.get_string_ref()?.clone()),
+    };
+
+    db_lock.get_records_ref_mut(context.selected_db).insert(
+        parameters[0].clone(),
+        InMemoryRecord::new(parameters[1].clone(), None),
+    );
+
+    context.set_response(
+        match previous_value {
+            None => RespValue::NullBulkString,
+            Some(previous_value) => RespValue::BulkString(previous_value),
+        }
+        .into(),
+    );
+
+    Ok(())
+}
+
+/// Deletes a key and replies its value. <br/>
+/// E.g.: "redis-cli getdel foo"
+pub(crate) async fn handle_command_getdel_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let response = match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => {
+            context.propagation = Propagation::Rewritten(Vec::new());
+
+            RespValue::NullBulkString
+        }
+        Some(record) => {
+            let value = record.get_string_ref()?.clone();
+            db_lock.get_records_ref_mut(context.selected_db).remove(key);
+
+            RespValue::BulkString(value)
+        }
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Replies the value of a key, and optionally changes its expiry, e.g.: <br/>
+/// "redis-cli getex foo ex 10" <br/>
+/// "redis-cli getex foo persist" (remove the expiry)
+pub(crate) async fn handle_command_getex_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let expiry = match resp_command.parameters.len() {
+        1 => None,
+        2 if resp_command
+            .get_parameter_str(1)?
+            .eq_ignore_ascii_case(RespCommandGetExOptions::PERSIST) =>
+        {
+            Some(Expiry::Persist)
+        }
+        3 => {
+            let option = resp_command.get_parameter_str(1)?.to_ascii_uppercase();
+
+            match option.as_str() {
+                RespCommandSetOptions::EXPIRY_SECONDS
+                | RespCommandSetOptions::EXPIRY_MILLISECONDS
+                | RespCommandSetOptions::EXPIRY_AT_SECONDS
+                | RespCommandSetOptions::EXPIRY_AT_MILLISECONDS => {
+                    Some(parse_expiry(resp_command, &option, 2)?)
+                }
+                _ => return Err(CommandError::Syntax.into()),
+            }
+        }
+        _ => return Err(CommandError::Syntax.into()),
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let record = match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => {
+            context.propagation = Propagation::Rewritten(Vec::new());
+            context.set_response(RespValue::NullBulkString.into());
+
+            return Ok(());
+        }
+        Some(record) => record,
+    };
+
+    let response = RespValue::BulkString(record.get_string_ref()?.clone());
+
+    let now = unix_time_millis()?;
+    let expire_at_milli = match expiry {
+        None | Some(Expiry::KeepTtl) => {
+            context.propagation = Propagation::Rewritten(Vec::new());
+            context.set_response(response.into());
+
+            return Ok(());
+        }
+        Some(Expiry::Persist) => None,
+        Some(Expiry::In(milliseconds)) => Some(now + milliseconds),
+        Some(Expiry::At(unix_time_milli)) => Some(unix_time_milli),
+    };
+
+    context.propagation = Propagation::Rewritten(match expire_at_milli {
+        None if record.expire_at_milli.is_some() => {
+            record.expire_at_milli = None;
+
+            vec![keys::persist_command(key)]
+        }
+        None => Vec::new(),
+        Some(expire_at_milli) if expire_at_milli <= now => {
+            db_lock.get_records_ref_mut(context.selected_db).remove(key);
+
+            vec![keys::del_command(key)]
+        }
+        Some(expire_at_milli) => {
+            record.expire_at_milli = Some(expire_at_milli);
+
+            vec![keys::pexpireat_command(key, expire_at_milli)]
+        }
+    });
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Fails if a string would grow over the maximum size of a bulk string, so it can still be
+/// replied.
+fn check_string_length(db: &InMemoryDb, length: usize) -> Result<(), Error> {
+    if length > db.get_app_data_ref().protocol_limits.max_bulk_len {
+        return Err(CommandError::Other(
+            "string exceeds maximum allowed size (proto-max-bulk-len)".to_owned(),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Appends a value to a string, creating it if it does not exist, and replies its new length.
+/// <br/>
+/// E.g.: "redis-cli append log entry"
+pub(crate) async fn handle_command_append_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let parameters = &context.get_request_resp_command_ref().unwrap().parameters;
+    let (key, value) = (&parameters[0], &parameters[1]);
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let current_length = db_lock
+        .get_live_record_mut(context.selected_db, key)?
+        .map_or(Ok(0), |record| record.get_string_ref().map(Bytes::len))?;
+    check_string_length(&db_lock, current_length + value.len())?;
+
+    let new_length = match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => {
+            db_lock
+                .get_records_ref_mut(context.selected_db)
+                .insert(key.clone(), InMemoryRecord::new(value.clone(), None));
+
+            value.len()
+        }
+        Some(record) => {
+            let current_value = record.get_string_ref()?;
+
+            let mut new_value = BytesMut::with_capacity(current_value.len() + value.len());
+            new_value.extend_from_slice(current_value);
+            new_value.extend_from_slice(value);
+
+            let new_length = new_value.len();
+            record.value = RecordValue::String(new_value.freeze());
+
+            new_length
+        }
+    };
+
+    context.set_response(RespValue::Integer(new_length as i64).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli strlen foo" <br/>
+/// Replies 0 if the key does not exist.
+pub(crate) async fn handle_command_strlen_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let key = &context.get_request_resp_command_ref().unwrap().parameters[0];
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let length = db_lock
+        .get_live_record_mut(context.selected_db, key)?
+        .map_or(Ok(0), |record| record.get_string_ref().map(Bytes::len))?;
+
+    context.set_response(RespValue::Integer(length as i64).into());
+
+    Ok(())
+}
+
+/// Replies the bytes of a string between two inclusive offsets, where negative offsets count
+/// from the end (-1 is the last byte). Offsets past either end are clamped to the string. <br/>
+/// E.g.: "redis-cli getrange foo 0 -1" (the whole string)
+pub(crate) async fn handle_command_getrange_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let start = resp_command.get_parameter_i64(1)?;
+    let end = resp_command.get_parameter_i64(2)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let value = match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => Bytes::new(),
+        Some(record) => {
+            let value = record.get_string_ref()?;
+
+            match get_range_bounds(start, end, value.len() as i64) {
+                None => Bytes::new(),
+                Some((start, end)) => value.slice(start..=end),
+            }
+        }
+    };
+
+    context.set_response(RespValue::BulkString(value).into());
+
+    Ok(())
+}
+
+/// Turns the inclusive `start` and `end` offsets of GETRANGE into indexes of a string of
+/// `length` bytes, or `None` if the range is empty.
+fn get_range_bounds(start: i64, end: i64, length: i64) -> Option<(usize, usize)> {
+    if (start < 0 && end < 0 && start > end) || length == 0 {
+        return None;
+    }
+
+    let start = if start < 0 { length + start } else { start }.max(0);
+    let end = if end < 0 { length + end } else { end }
+        .max(0)
+        .min(length - 1);
+
+    if start > end {
+        return None;
+    }
+
+    Some((start as usize, end as usize))
+}
+
+/// Overwrites part of a string starting at an offset, padding it with zero bytes if it is
+/// shorter than the offset, and replies its new length. <br/>
+/// E.g.: "redis-cli setrange foo 6 redis"
+pub(crate) async fn handle_command_setrange_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let value = &resp_command.parameters[2];
+
+    let offset = match resp_command.get_parameter_i64(1)? {
+        offset if offset < 0 => {
+            return Err(CommandError::Other("offset is out of range".to_owned()).into())
+        }
+        offset => offset as usize,
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let current_length = db_lock
+        .get_live_record_mut(context.selected_db, key)?
+        .map_or(Ok(0), |record| record.get_string_ref().map(Bytes::len))?;
+
+    // Setting nothing does not create the key, nor pads it.
+    if value.is_empty() {
+        context.propagation = Propagation::Rewritten(Vec::new());
+        context.set_response(RespValue::Integer(current_length as i64).into());
+
+        return Ok(());
+    }
+
+    check_string_length(&db_lock, offset + value.len())?;
+
+    let mut new_value = BytesMut::new();
+    if let Some(record) = db_lock.get_live_record_mut(context.selected_db, key)? {
+        new_value.extend_from_slice(record.get_string_ref()?);
+    }
+    if new_value.len() < offset + value.len() {
+        new_value.resize(offset + value.len(), 0);
+    }
+    new_value[offset..offset + value.len()].copy_from_slice(value);
+
+    let new_length = new_value.len();
+    match db_lock.get_live_record_mut(context.selected_db, key)? {
+        None => {
+            db_lock
+                .get_records_ref_mut(context.selected_db)
+                .insert(key.clone(), InMemoryRecord::new(new_value.freeze(), None));
+        }
+        Some(record) => record.value = RecordValue::String(new_value.freeze()),
+    };
+
+    context.set_response(RespValue::Integer(new_length as i64).into());
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct LcsOptions {
+    len: bool,
+    idx: bool,
+    min_match_len: usize,
+    with_match_len: bool,
+}
+
+fn parse_lcs_options(resp_command: &RespCommand) -> Result<LcsOptions, Error> {
+    let mut options = LcsOptions::default();
+    let mut position = 2;
+
+    while position < resp_command.parameters.len() {
+        let option = resp_command
+            .get_parameter_str(position)?
+            .to_ascii_uppercase();
+
+        match option.as_str() {
+            RespCommandLcsOptions::LEN => options.len = true,
+            RespCommandLcsOptions::IDX => options.idx = true,
+            RespCommandLcsOptions::WITH_MATCH_LEN => options.with_match_len = true,
+            RespCommandLcsOptions::MIN_MATCH_LEN
+                if position + 1 < resp_command.parameters.len() =>
+            {
+                position += 1;
+                options.min_match_len = resp_command.get_parameter_i64(position)?.max(0) as usize;
+            }
+            _ => return Err(CommandError::Syntax.into()),
+        };
+
+        position += 1;
+    }
+
+    if options.len && options.idx {
+        return Err(CommandError::Other(
+            "If you want both the length and indexes, please just use IDX.".to_owned(),
+        )
+        .into());
+    }
+
+    Ok(options)
+}
+
+/// A run of bytes that is part of the longest common subsequence of two strings, with the
+/// inclusive ranges it spans in each of them.
+#[derive(Debug, PartialEq)]
+struct LcsMatch {
+    first_range: (usize, usize),
+    second_range: (usize, usize),
+}
+
+impl LcsMatch {
+    fn len(&self) -> usize {
+        self.first_range.1 - self.first_range.0 + 1
+    }
+}
+
+/// Computes the longest common subsequence of `first` and `second`, and the runs of contiguous
+/// bytes it is made of, from the last one to the first one, like Redis reports them.
+fn longest_common_subsequence(first: &[u8], second: &[u8]) -> (Vec<u8>, Vec<LcsMatch>) {
+    let columns = second.len() + 1;
+    // lengths[i * columns + j] is the length of the LCS of first[..i] and second[..j].
+    let mut lengths = vec![0u32; (first.len() + 1) * columns];
+
+    for i in 1..=first.len() {
+        for j in 1..=second.len() {
+            lengths[i * columns + j] = if first[i - 1] == second[j - 1] {
+                lengths[(i - 1) * columns + j - 1] + 1
+            } else {
+                lengths[(i - 1) * columns + j].max(lengths[i * columns + j - 1])
+            };
+        }
+    }
+
+    let mut subsequence = vec![0u8; lengths[first.len() * columns + second.len()] as usize];
+    let mut matches = Vec::new();
+    let mut current: Option<LcsMatch> = None;
+    let (mut i, mut j, mut remaining) = (first.len(), second.len(), subsequence.len());
+
+    while i > 0 && j > 0 {
+        if first[i - 1] == second[j - 1] {
+            subsequence[remaining - 1] = first[i - 1];
+            remaining -= 1;
+
+            // Matches are found backwards, so a run grows towards the start of the strings.
+            match current.as_mut() {
+                Some(run) if run.first_range.0 == i && run.second_range.0 == j => {
+                    run.first_range.0 -= 1;
+                    run.second_range.0 -= 1;
+                }
+                _ => {
+                    matches.extend(current.replace(LcsMatch {
+                        first_range: (i - 1, i - 1),
+                        second_range: (j - 1, j - 1),
+                    }));
+                }
+            };
+
+            i -= 1;
+            j -= 1;
+        } else {
+            if lengths[(i - 1) * columns + j] > lengths[i * columns + j - 1] {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+
+            matches.extend(current.take());
+        }
+    }
+
+    matches.extend(current.take());
+
+    (subsequence, matches)
+}
+
+/// Replies the longest common subsequence of two strings, e.g.: <br/>
+/// "redis-cli lcs key1 key2" (the subsequence) <br/>
+/// "redis-cli lcs key1 key2 len" (its length) <br/>
+/// "redis-cli lcs key1 key2 idx minmatchlen 4 withmatchlen" (where its runs of bytes are)
+///
+/// Keys that don't exist are treated as empty strings.
+pub(crate) async fn handle_command_lcs_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let options = parse_lcs_options(resp_command)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let mut values = Vec::with_capacity(2);
+    for key in &resp_command.parameters[0..2] {
+        values.push(
+            db_lock
+                .get_live_record_mut(context.selected_db, key)?
+                .map_or(Ok(Bytes::new()), |record| record.get_string_ref().cloned())?,
+        );
+    }
+    drop(db_lock);
+
+    // The table of the LCS lengths holds a u32 per pair of prefixes of the two strings.
+    if (values[0].len() as u64 + 1) * (values[1].len() as u64 + 1) > (u32::MAX as u64 - 1) / 4 {
+        return Err(CommandError::Other("String too long for LCS".to_owned()).into());
+    }
+
+    let (subsequence, matches) = longest_common_subsequence(&values[0], &values[1]);
+
+    let response = if options.len {
+        RespValue::Integer(subsequence.len() as i64)
+    } else if options.idx {
+        let field = |name: &'static str| RespValue::BulkString(Bytes::from_static(name.as_bytes()));
+        let range = |(start, end): (usize, usize)| {
+            RespValue::Array(vec![
+                RespValue::Integer(start as i64),
+                RespValue::Integer(end as i64),
+            ])
+        };
+
+        RespValue::Map(vec![
+            (
+                field("matches"),
+                RespValue::Array(
+                    matches
+                        .iter()
+                        .filter(|lcs_match| lcs_match.len() >= options.min_match_len)
+                        .map(|lcs_match| {
+                            let mut entry =
+                                vec![range(lcs_match.first_range), range(lcs_match.second_range)];
+                            if options.with_match_len {
+                                entry.push(RespValue::Integer(lcs_match.len() as i64));
+                            }
+
+                            RespValue::Array(entry)
+                        })
+                        .collect(),
+                ),
+            ),
+            (field("len"), RespValue::Integer(subsequence.len() as i64)),
+        ])
+    } else {
+        RespValue::BulkString(Bytes::from(subsequence))
+    };
+
+    context.set_response(response.into());
+
+    Ok(())
+}
+
+/// Handles INCR, DECR, INCRBY and DECRBY, e.g.: <br/>
+/// "redis-cli incrby counter 5"
+///
+/// A missing key counts as 0, and the TTL of an existing key is kept.
+pub(crate) async fn handle_command_incr_by_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+
+    let increment = match resp_command.name.as_str() {
+        RespCommandNames::INCR => 1,
+        RespCommandNames::DECR => -1,
+        RespCommandNames::INCRBY => resp_command.get_parameter_i64(1)?,
+        _ => match resp_command.get_parameter_i64(1)?.checked_neg() {
+            None => return Err(CommandError::Other("decrement would overflow".to_owned()).into()),
+            Some(increment) => increment,
+        },
+    };
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let record = db_lock.get_live_record_mut(context.selected_db, key)?;
+    let current = match &record {
+        None => 0,
+        Some(record) => {
+            parse_strict_i64(record.get_string_ref()?).ok_or(CommandError::NotAnInteger)?
+        }
+    };
+
+    let new_value = match current.checked_add(increment) {
+        None => {
+            return Err(
+                CommandError::Other("increment or decrement would overflow".to_owned()).into(),
+            )
+        }
+        Some(new_value) => new_value,
+    };
+
+    let new_value_bytes = Bytes::from(new_value.to_string());
+    match record {
+        None => {
+            db_lock
+                .get_records_ref_mut(context.selected_db)
+                .insert(key.clone(), InMemoryRecord::new(new_value_bytes, None));
+        }
+        Some(record) => record.value = RecordValue::String(new_value_bytes),
+    };
+
+    context.set_response(RespValue::Integer(new_value).into());
+
+    Ok(())
+}
+
+/// E.g.: "redis-cli incrbyfloat price 0.5"
+///
+/// It is propagated as a SET of the resulting value, keeping the TTL, so replicas don't depend
+/// on float rounding.
+pub(crate) async fn handle_command_incr_by_float_async<'a>(
+    context: &mut ConnectionContext<'a>,
+) -> Result<(), Error> {
+    let resp_command = context.get_request_resp_command_ref().unwrap();
+    let key = &resp_command.parameters[0];
+    let increment = resp_command.get_parameter_f64(1)?;
+
+    let mut db_lock = context.mem_db.lock().await;
+
+    let record = db_lock.get_live_record_mut(context.selected_db, key)?;
+    let current = match &record {
+        None => 0.0,
+        Some(record) => {
+            parse_strict_f64(record.get_string_ref()?).ok_or(CommandError::NotAFloat)?
+        }
+    };
+
+    let new_value = current + increment;
+
+    if !new_value.is_finite() {
+        return Err(
+            CommandError::Other("increment would produce NaN or Infinity".to_owned()).into(),
+        );
+    }
+
+    let new_value_bytes = Bytes::from(format_float_value(new_value));
+    match record {
+        None => {
+            db_lock.get_records_ref_mut(context.selected_db).insert(
+                key.clone(),
+                InMemoryRecord::new(new_value_bytes.clone(), None),
+            );
+        }
+        Some(record) => record.value = RecordValue::String(new_value_bytes.clone()),
+    };
+
+    context.propagation = Propagation::Rewritten(vec![RespCommand::new(
+        RespCommandNames::SET,
+        vec![
+            key.clone(),
+            new_value_bytes.clone(),
+            Bytes::from_static(RespCommandSetOptions::KEEP_TTL.as_bytes()),
+        ],
+    )]);
+    context.set_response(RespValue::BulkString(new_value_bytes).into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::db::in_memory_record::RecordValue,
+        resp_parser::shared::RespValue,
+        test_helpers::utils::{bulk_string, create_test_mem_db, error, send_test_request},
+        utils::unix_time_millis,
+    };
+
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn handle_command_set_async_handles_conditions_and_get() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET lock a NX\r\nSET lock b NX\r\nSET missing a XX\r\nSET lock c XX GET\r\nSET lock d get nx\r\nSET other e GET\r\nGET lock\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::NullBulkString,
+                RespValue::NullBulkString,
+                bulk_string("a"),
+                bulk_string("c"),
+                RespValue::NullBulkString,
+                bulk_string("c"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_set_async_handles_expiry_options() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let in_an_hour = unix_time_millis()? + 3_600_000;
+
+        send_test_request(
+            &fake_mem_db,
+            format!(
+                "SET ex v EX 10\r\nSET px v px 500 XX\r\nSET px v PX 500\r\nSET pxat v PXAT {}\r\nSET exat v EXAT {}\r\nSET kept v2 KEEPTTL\r\nSET ex v2 KEEPTTL\r\nSET past v PXAT 1000\r\n",
+                in_an_hour,
+                in_an_hour / 1000
+            )
+            .as_bytes(),
+        )
+        .await?;
+
+        let now = unix_time_millis()?;
+        let mut db_lock = fake_mem_db.lock().await;
+        let mut expire_at_milli =
+            |key: &str| match db_lock.get_live_record_mut(0, &Bytes::from(key.to_owned())) {
+                Result::Ok(Some(record)) => Some(record.expire_at_milli),
+                _ => None,
+            };
+
+        assert!(
+            expire_at_milli("ex")
+                .unwrap()
+                .unwrap()
+                .abs_diff(now + 10_000)
+                < 1000
+        );
+        assert!(expire_at_milli("px").unwrap().unwrap().abs_diff(now + 500) < 1000);
+        assert_eq!(expire_at_milli("pxat"), Some(Some(in_an_hour)));
+        assert_eq!(
+            expire_at_milli("exat"),
+            Some(Some(in_an_hour / 1000 * 1000))
+        );
+        assert_eq!(expire_at_milli("kept"), Some(None));
+        assert_eq!(expire_at_milli("past"), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_set_async_rejects_invalid_options() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET k v NX XX\r\nSET k v EX 1 PX 1\r\nSET k v EX 1 KEEPTTL\r\nSET k v KEEPTTL EX 1\r\nSET k v EX\r\nSET k v FOO\r\nSET k v EX 0\r\nSET k v PX -1\r\nSET k v EX 1.5\r\nSET k v EX 9223372036854775807\r\nGET k\r\n",
+            )
+            .await?,
+            vec![
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                error("ERR invalid expire time in 'set' command"),
+                error("ERR invalid expire time in 'set' command"),
+                error("ERR value is not an integer or out of range"),
+                error("ERR invalid expire time in 'set' command"),
+                RespValue::NullBulkString,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_mget_async_and_mset_async_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"MSET a 1 b 2\r\nMGET a nope b\r\nMSETNX c 3 a 4\r\nMSETNX c 3 d 4\r\nMGET a c d\r\nMSET a 1 b\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::Array(vec![
+                    bulk_string("1"),
+                    RespValue::NullBulkString,
+                    bulk_string("2")
+                ]),
+                RespValue::Integer(0),
+                RespValue::Integer(1),
+                RespValue::Array(vec![bulk_string("1"), bulk_string("3"), bulk_string("4")]),
+                error("ERR wrong number of arguments for 'mset' command"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_getset_async_and_getdel_async_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"GETSET a 1\r\nGETSET a 2\r\nGETDEL a\r\nGETDEL a\r\nGET a\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::NullBulkString,
+                bulk_string("1"),
+                bulk_string("2"),
+                RespValue::NullBulkString,
+                RespValue::NullBulkString,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_getex_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a 1 PX 100000\r\nSET b 2\r\nGETEX a PERSIST\r\nGETEX b EX 10\r\nGETEX nope EX 10\r\nGETEX b EX 0\r\nGETEX b FOO 1\r\nGETEX b PERSIST EX 1\r\nGETEX b PXAT 1\r\nGET b\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::ok(),
+                bulk_string("1"),
+                bulk_string("2"),
+                RespValue::NullBulkString,
+                error("ERR invalid expire time in 'getex' command"),
+                error("ERR syntax error"),
+                error("ERR syntax error"),
+                bulk_string("2"),
+                RespValue::NullBulkString,
+            ]
+        );
+
+        let mut db_lock = fake_mem_db.lock().await;
+        let record = db_lock.get_live_record_mut(0, &Bytes::from("a"))?.unwrap();
+        assert_eq!(record.expire_at_milli, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_append_async_and_strlen_async_pass() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"STRLEN log\r\nAPPEND log Hello\r\nAPPEND log \" World\"\r\nSTRLEN log\r\nGET log\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(0),
+                RespValue::Integer(5),
+                RespValue::Integer(11),
+                RespValue::Integer(11),
+                bulk_string("Hello World"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_getrange_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET s \"This is a string\"\r\nGETRANGE s 0 3\r\nGETRANGE s -3 -1\r\nGETRANGE s 0 -1\r\nGETRANGE s 10 100\r\nGETRANGE s 5 3\r\nGETRANGE s -1 -5\r\nGETRANGE s -100 2\r\nGETRANGE nope 0 -1\r\nGETRANGE s a 1\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                bulk_string("This"),
+                bulk_string("ing"),
+                bulk_string("This is a string"),
+                bulk_string("string"),
+                bulk_string(""),
+                bulk_string(""),
+                bulk_string("Thi"),
+                bulk_string(""),
+                error("ERR value is not an integer or out of range"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_setrange_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET k1 \"Hello World\"\r\nSETRANGE k1 6 Redis\r\nGET k1\r\nSETRANGE k2 3 ab\r\nGET k2\r\nSETRANGE k3 5 \"\"\r\nGET k3\r\nSETRANGE k1 -1 x\r\nSETRANGE k1 536870912 x\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                RespValue::Integer(11),
+                bulk_string("Hello Redis"),
+                RespValue::Integer(5),
+                RespValue::BulkString(Bytes::from_static(b"\0\0\0ab")),
+                RespValue::Integer(0),
+                RespValue::NullBulkString,
+                error("ERR offset is out of range"),
+                error("ERR string exceeds maximum allowed size (proto-max-bulk-len)"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_lcs_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        let range = |start: i64, end: i64| {
+            RespValue::Array(vec![RespValue::Integer(start), RespValue::Integer(end)])
+        };
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"MSET key1 ohmytext key2 mynewtext\r\nLCS key1 key2\r\nLCS key1 key2 LEN\r\nLCS key1 key2 IDX\r\nLCS key1 key2 IDX MINMATCHLEN 4 WITHMATCHLEN\r\nLCS key1 nope\r\nLCS key1 key2 LEN IDX\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                bulk_string("mytext"),
+                RespValue::Integer(6),
+                RespValue::Map(vec![
+                    (
+                        bulk_string("matches"),
+                        RespValue::Array(vec![
+                            RespValue::Array(vec![range(4, 7), range(5, 8)]),
+                            RespValue::Array(vec![range(2, 3), range(0, 1)]),
+                        ])
+                    ),
+                    (bulk_string("len"), RespValue::Integer(6)),
+                ]),
+                RespValue::Map(vec![
+                    (
+                        bulk_string("matches"),
+                        RespValue::Array(vec![RespValue::Array(vec![
+                            range(4, 7),
+                            range(5, 8),
+                            RespValue::Integer(4)
+                        ])])
+                    ),
+                    (bulk_string("len"), RespValue::Integer(6)),
+                ]),
+                bulk_string(""),
+                error("ERR If you want both the length and indexes, please just use IDX."),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_incr_by_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"INCR counter\r\nINCRBY counter 10\r\nDECR counter\r\nDECRBY counter -5\r\nGET counter\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::Integer(1),
+                RespValue::Integer(11),
+                RespValue::Integer(10),
+                RespValue::Integer(15),
+                bulk_string("15"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_incr_by_async_rejects_non_integers_and_overflows(
+    ) -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"SET a abc\r\nINCR a\r\nSET b \" 1\"\r\nINCR b\r\nSET c 01\r\nINCR c\r\nINCRBY d 1.5\r\nSET e 9223372036854775807\r\nINCR e\r\nDECRBY f -9223372036854775808\r\nGET e\r\n",
+            )
+            .await?,
+            vec![
+                RespValue::ok(),
+                error("ERR value is not an integer or out of range"),
+                RespValue::ok(),
+                error("ERR value is not an integer or out of range"),
+                RespValue::ok(),
+                error("ERR value is not an integer or out of range"),
+                error("ERR value is not an integer or out of range"),
+                RespValue::ok(),
+                error("ERR increment or decrement would overflow"),
+                error("ERR decrement would overflow"),
+                bulk_string("9223372036854775807"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_incr_by_async_keeps_the_ttl() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        send_test_request(&fake_mem_db, b"SET counter 1 PX 100000\r\nINCR counter\r\n").await?;
+
+        let mut db_lock = fake_mem_db.lock().await;
+        let record = db_lock
+            .get_live_record_mut(0, &Bytes::from("counter"))?
+            .unwrap();
+        assert_eq!(record.value, RecordValue::String(Bytes::from("2")));
+        assert!(record
+            .expire_at_milli
+            .is_some_and(|expire_at_milli| expire_at_milli > unix_time_millis().unwrap() + 90_000));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_incr_by_float_async_passes() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        assert_eq!(
+            send_test_request(
+                &fake_mem_db,
+                b"INCRBYFLOAT f 10.5\r\nINCRBYFLOAT f 0.1\r\nINCRBYFLOAT f -5.6\r\nINCRBYFLOAT f 5.0e3\r\nSET i 3\r\nINCRBYFLOAT i 1.5\r\nSET s abc\r\nINCRBYFLOAT s 1\r\nINCRBYFLOAT f abc\r\nINCRBYFLOAT f inf\r\nSET g 0.2\r\nINCRBYFLOAT g 0.1\r\nGET g\r\n",
+            )
+            .await?,
+            vec![
+                bulk_string("10.5"),
+                bulk_string("10.6"),
+                bulk_string("5"),
+                bulk_string("5005"),
+                RespValue::ok(),
+                bulk_string("4.5"),
+                RespValue::ok(),
+                error("ERR value is not a valid float"),
+                error("ERR value is not a valid float"),
+                error("ERR increment would produce NaN or Infinity"),
+                RespValue::ok(),
+                bulk_string("0.3"),
+                bulk_string("0.3"),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,12 +1,15 @@
 use crate::{
     models::{
-        connection_context::{ConnectionContext, Handshake},
+        command_error::{into_error_reply, is_fatal_error, CommandError},
+        connection_context::{ConnectionContext, Handshake, Response},
         db::in_memory_db::InMemoryDb,
         t_stream::TStream,
     },
-    node::{command_handlers, propagation::propagate},
-    resp_parser::{self, shared::RespCommandNames},
-    TCP_READ_TIMEOUT,
+    node::{
+        command_registry::{command_registry, CommandFlag},
+        propagation::propagate,
+    },
+    resp_parser, TCP_READ_TIMEOUT, TCP_RESPONSE_BUFFER_SIZE,
 };
 
 use std::sync::Arc;
@@ -14,16 +17,19 @@ use std::sync::Arc;
 use anyhow::Error;
 use tokio::{io::AsyncReadExt, net::TcpListener, sync::Mutex};
 
-pub(crate) async fn run<'a>(mem_db: &Arc<Mutex<InMemoryDb>>) -> Result<(), Error> {
-    let listening_port = {
+pub(crate) async fn run(mem_db: &Arc<Mutex<InMemoryDb>>) -> Result<(), Error> {
+    let (listening_port, protocol_limits) = {
         let db_lock = mem_db.lock().await;
-        db_lock.get_app_data_ref().listening_port
+        (
+            db_lock.get_app_data_ref().listening_port,
+            db_lock.get_app_data_ref().protocol_limits,
+        )
     };
 
     let listener = TcpListener::bind(format!("127.0.0.1:{}", listening_port)).await?;
 
     loop {
-        let _ = match listener.accept().await {
+        match listener.accept().await {
             Ok((mut _tcp_stream, addy)) => {
                 let mem_db_arc_pointer = Arc::clone(mem_db);
                 let tcp_stream_arc: Arc<Mutex<dyn TStream>> = Arc::new(Mutex::new(_tcp_stream));
@@ -31,6 +37,7 @@ pub(crate) async fn run<'a>(mem_db: &Arc<Mutex<InMemoryDb>>) -> Result<(), Error
                 tokio::spawn(async move {
                     let mut connection_context =
                         ConnectionContext::new(&mem_db_arc_pointer, &tcp_stream_arc).unwrap();
+                    connection_context.request.protocol_limits = protocol_limits;
 
                     connection_context
                         .println_by(&format!(
@@ -40,13 +47,10 @@ pub(crate) async fn run<'a>(mem_db: &Arc<Mutex<InMemoryDb>>) -> Result<(), Error
                         ))
                         .await;
 
-                    match handle_client_connection(&mut connection_context).await {
-                        Err(e) => {
-                            connection_context
-                                .println_by(&format!("connection handling error: {}", e))
-                                .await;
-                        }
-                        Ok(()) => (),
+                    if let Err(e) = handle_client_connection(&mut connection_context).await {
+                        connection_context
+                            .println_by(&format!("connection handling error: {}", e))
+                            .await;
                     }
 
                     connection_context
@@ -73,21 +77,27 @@ pub(crate) async fn run<'a>(mem_db: &Arc<Mutex<InMemoryDb>>) -> Result<(), Error
     }
 }
 
-async fn handle_client_connection<'a>(
-    connection_context: &mut ConnectionContext<'a>,
+async fn handle_client_connection(
+    connection_context: &mut ConnectionContext<'_>,
 ) -> Result<(), anyhow::Error> {
     connection_context
         .println_by("listening for requests on this stream...")
         .await;
 
     loop {
-        connection_context.reset();
-
         match tokio::time::timeout(TCP_READ_TIMEOUT, async {
+            let mut read_chunk = [0; TCP_RESPONSE_BUFFER_SIZE];
             let mut tcp_stream_lock = connection_context.request.tcp_stream.lock().await;
-            tcp_stream_lock
-                .read(&mut connection_context.request.buffer)
-                .await
+            let read_result = tcp_stream_lock.read(&mut read_chunk).await;
+
+            if let Ok(byte_count) = read_result {
+                connection_context
+                    .request
+                    .buffer
+                    .extend_from_slice(&read_chunk[..byte_count]);
+            }
+
+            read_result
         })
         .await
         {
@@ -108,32 +118,37 @@ async fn handle_client_connection<'a>(
                 if request_byte_count == 0 {
                     // The socket is closed.
                     connection_context
-                        .println_by(&format!("0 byte request, close tcp connection."))
+                        .println_by("0 byte request, close tcp connection.")
                         .await;
 
                     break;
                 }
 
-                connection_context.request.byte_count = request_byte_count;
+                let mut handled_commands = handle_buffered_commands(connection_context).await?;
 
-                handle_command(connection_context).await?;
+                while handled_commands.has_pending_command {
+                    write_responses_async(connection_context, &handled_commands.responses).await?;
 
-                connection_context
-                    .println_by(&format!(
-                        "responding to request with: {:?}",
-                        &connection_context.response
-                    ))
-                    .await;
+                    handled_commands = handle_buffered_commands(connection_context).await?;
+                }
 
-                connection_context
-                    .request
-                    .tcp_stream
-                    .lock()
-                    .await
-                    .write_all_responses(&connection_context.response)
-                    .await?;
+                if handled_commands.responses.is_empty() {
+                    connection_context
+                        .println_by("incomplete command, waiting for the rest of it...")
+                        .await;
+
+                    continue;
+                }
+
+                write_responses_async(connection_context, &handled_commands.responses).await?;
 
-                propagate(connection_context).await?;
+                if handled_commands.close_connection {
+                    connection_context
+                        .println_by("fatal error replied, close tcp connection.")
+                        .await;
+
+                    break;
+                }
             }
         };
 
@@ -145,40 +160,155 @@ async fn handle_client_connection<'a>(
     Ok(())
 }
 
-async fn handle_command<'a>(app_context: &mut ConnectionContext<'a>) -> Result<(), anyhow::Error> {
-    app_context.println_by("parsing request").await;
+async fn write_responses_async(
+    connection_context: &ConnectionContext<'_>,
+    responses: &[Response],
+) -> Result<(), anyhow::Error> {
+    connection_context
+        .println_by(&format!("responding to request with: {:?}", responses))
+        .await;
+
+    connection_context
+        .request
+        .tcp_stream
+        .lock()
+        .await
+        .write_all_responses(responses, connection_context.protocol_version)
+        .await
+}
+
+#[derive(Debug)]
+pub(crate) struct HandledCommands {
+    /// The responses of all handled commands, in the order the commands were received.
+    pub responses: Vec<Response>,
+    /// Set when a fatal error was replied, after which the connection has to be closed.
+    pub close_connection: bool,
+    /// Set when a blocking command was parsed but not handled yet, because the responses of the
+    /// commands before it have to be written first. The next call handles it.
+    pub has_pending_command: bool,
+}
+
+/// Parses and handles every complete command in the request buffer, in the order they were
+/// received, so that pipelined commands sent in a single write are all answered. <br/>
+/// A trailing incomplete command is left in the buffer until the rest of it is read.
+///
+/// Errors while handling a command are replied as RESP errors instead of being returned, and
+/// the following commands are still handled. A request that can't be parsed is replied as a
+/// protocol error, and nothing after it is handled.
+///
+/// Stops before a blocking command (e.g. BLPOP) that follows other commands, so that their
+/// replies are not held back while it waits, e.g. the "+OK" of "SET a 1\r\nBLPOP q 0\r\n".
+pub(crate) async fn handle_buffered_commands(
+    connection_context: &mut ConnectionContext<'_>,
+) -> Result<HandledCommands, anyhow::Error> {
+    let mut handled_commands = HandledCommands {
+        responses: Vec::<Response>::new(),
+        close_connection: false,
+        has_pending_command: false,
+    };
+
+    connection_context.println_by("parsing request").await;
 
-    resp_parser::parse_resp_proc_command(app_context)?;
+    loop {
+        // A blocking command left pending by the previous call is already parsed.
+        if connection_context.request.resp_command.is_none() {
+            match resp_parser::parse_resp_proc_command(connection_context) {
+                Ok(false) => break,
+                Ok(true) => {}
+                Err(e) => {
+                    connection_context
+                        .println_by(&format!("request parsing error: {}", e))
+                        .await;
+
+                    handled_commands.responses.push(into_error_reply(&e).into());
+                    handled_commands.close_connection = true;
+
+                    break;
+                }
+            };
+        }
+
+        if !handled_commands.responses.is_empty() && is_blocking_command(connection_context) {
+            handled_commands.has_pending_command = true;
+
+            break;
+        }
+
+        let protocol_version = connection_context.protocol_version;
+
+        match handle_command(connection_context).await {
+            Ok(()) => propagate(connection_context).await?,
+            Err(e) => {
+                connection_context
+                    .println_by(&format!("command handling error: {}", e))
+                    .await;
+
+                connection_context.set_response(into_error_reply(&e).into());
+                handled_commands.close_connection = is_fatal_error(&e);
+            }
+        };
+
+        if connection_context.protocol_version != protocol_version {
+            // The responses of the commands before HELLO are still sent in the previous protocol.
+            for response in handled_commands.responses.iter_mut() {
+                *response = Response::Raw(response.to_bytes(protocol_version));
+            }
+        }
+
+        handled_commands
+            .responses
+            .append(&mut connection_context.response);
+        connection_context.reset();
+
+        if handled_commands.close_connection {
+            break;
+        }
+    }
+
+    Ok(handled_commands)
+}
+
+fn is_blocking_command(connection_context: &ConnectionContext<'_>) -> bool {
+    command_registry()
+        .lookup(
+            &connection_context
+                .get_request_resp_command_ref()
+                .unwrap()
+                .name,
+        )
+        .is_some_and(|registered_command| registered_command.spec.has_flag(CommandFlag::Blocking))
+}
+
+/// Handles the command already parsed into `app_context.request.resp_command`.
+async fn handle_command(app_context: &mut ConnectionContext<'_>) -> Result<(), anyhow::Error> {
+    let resp_command = app_context.get_request_resp_command_ref().unwrap();
+
+    let registered_command = match command_registry().lookup(&resp_command.name) {
+        None => {
+            return Err(
+                CommandError::unknown_command(&resp_command.name, &resp_command.parameters).into(),
+            )
+        }
+        Some(registered_command) => registered_command,
+    };
+
+    // The parameters don't include the command name, which counts towards the arity.
+    if !registered_command
+        .spec
+        .accepts_argument_count(resp_command.parameters.len() + 1)
+    {
+        return Err(CommandError::wrong_arity(registered_command.spec.name).into());
+    }
 
     app_context
         .println_by(&format!(
-            "handling request - {}",
+            "handling request (keys: {:?}) - {}",
+            registered_command.spec.get_keys(&resp_command.parameters),
             app_context.format_request_info(true)?
         ))
         .await;
 
-    match app_context
-        .get_request_resp_command_ref()
-        .unwrap()
-        .name
-        .as_str()
-    {
-        RespCommandNames::PING => command_handlers::handle_command_ping(app_context)?,
-        RespCommandNames::REPLCONF => {
-            command_handlers::handle_command_replconf(app_context).await?
-        }
-        RespCommandNames::PSYNC => command_handlers::handle_command_psync(app_context).await?,
-        RespCommandNames::ECHO => command_handlers::handle_command_echo(app_context)?,
-        RespCommandNames::GET => command_handlers::handle_command_get_async(app_context).await?,
-        RespCommandNames::SET => command_handlers::handle_command_set_async(app_context).await?,
-        RespCommandNames::INFO => command_handlers::handle_command_info(app_context).await?,
-
-        _ => {
-            return Err(Error::msg(
-                "Could not handle command - Unknown or not implemented command.",
-            ))
-        }
-    };
+    registered_command.handler.handle(app_context).await?;
 
     app_context.println_by("finished handling request.").await;
 
@@ -191,14 +321,18 @@ mod tests {
         models::connection_context::ConnectionContext,
         node::{
             command_handlers::{
-                handle_command_echo, handle_command_get_async, handle_command_ping,
-                handle_command_set_async,
+                connection::{handle_command_echo, handle_command_ping},
+                strings::{handle_command_get_async, handle_command_set_async},
             },
-            command_listener::handle_command,
+            command_listener::{handle_buffered_commands, handle_command},
+        },
+        resp_parser::{
+            parse_resp_proc_command,
+            shared::{RespCommandNames, RespProtocolVersion, RespValue},
+        },
+        test_helpers::utils::{
+            bulk_string, bulk_strings, create_test_mem_db, create_test_tstream, send_test_request,
         },
-        resp_parser::{parse_resp_proc_command, shared::RespCommandNames},
-        test_helpers::utils::{create_test_mem_db, create_test_tstream},
-        utils::copy_to_array_until,
     };
 
     use anyhow::Ok;
@@ -206,19 +340,16 @@ mod tests {
     #[tokio::test]
     async fn handle_command_handles_ping() -> Result<(), anyhow::Error> {
         let fake_mem_db = create_test_mem_db()?;
-        let mut fake_tcp_stream = create_test_tstream();
+        let fake_tcp_stream = create_test_tstream();
 
-        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &mut fake_tcp_stream)?;
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
         let request_buffer = b"*1\r\n$4\r\npiNg\r\n";
-        copy_to_array_until(
-            &mut fake_app_context.request.buffer,
-            request_buffer,
-            0,
-            |_, _, source_idx| source_idx == request_buffer.len() - 1,
-        );
-        fake_app_context.request.byte_count = request_buffer.len();
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(request_buffer);
 
-        parse_resp_proc_command(&mut fake_app_context)?;
+        assert!(parse_resp_proc_command(&mut fake_app_context)?);
         assert_eq!(
             fake_app_context
                 .get_request_resp_command_ref()
@@ -242,13 +373,16 @@ mod tests {
                 .response
                 .first()
                 .unwrap()
-                .command_response
-                .to_owned(),
-            "+PONG\r\n".to_owned()
+                .to_bytes(RespProtocolVersion::Resp2),
+            b"+PONG\r\n"
         );
         assert_eq!(
-            fake_app_context.response.first().unwrap().command_response,
-            "+PONG\r\n".to_owned()
+            fake_app_context
+                .response
+                .first()
+                .unwrap()
+                .to_bytes(RespProtocolVersion::Resp2),
+            b"+PONG\r\n"
         );
 
         Ok(())
@@ -257,19 +391,16 @@ mod tests {
     #[tokio::test]
     async fn handle_command_handles_echo() -> Result<(), anyhow::Error> {
         let fake_mem_db = create_test_mem_db()?;
-        let mut fake_tcp_stream = create_test_tstream();
+        let fake_tcp_stream = create_test_tstream();
 
-        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &mut fake_tcp_stream)?;
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
         let request_buffer = b"*2\r\n$4\r\nEcHo\r\n$19\r\nHey world, I'm Joe!\r\n";
-        copy_to_array_until(
-            &mut fake_app_context.request.buffer,
-            request_buffer,
-            0,
-            |_, _, source_idx| source_idx == request_buffer.len() - 1,
-        );
-        fake_app_context.request.byte_count = request_buffer.len();
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(request_buffer);
 
-        parse_resp_proc_command(&mut fake_app_context)?;
+        assert!(parse_resp_proc_command(&mut fake_app_context)?);
         assert_eq!(
             fake_app_context
                 .get_request_resp_command_ref()
@@ -293,13 +424,16 @@ mod tests {
                 .response
                 .first()
                 .unwrap()
-                .command_response
-                .to_owned(),
-            "$19\r\nHey world, I'm Joe!\r\n".to_owned()
+                .to_bytes(RespProtocolVersion::Resp2),
+            b"$19\r\nHey world, I'm Joe!\r\n"
         );
         assert_eq!(
-            fake_app_context.response.first().unwrap().command_response,
-            "$19\r\nHey world, I'm Joe!\r\n".to_owned()
+            fake_app_context
+                .response
+                .first()
+                .unwrap()
+                .to_bytes(RespProtocolVersion::Resp2),
+            b"$19\r\nHey world, I'm Joe!\r\n"
         );
 
         Ok(())
@@ -308,21 +442,18 @@ mod tests {
     #[tokio::test]
     async fn handle_command_handles_set_get() -> Result<(), anyhow::Error> {
         let fake_mem_db = create_test_mem_db()?;
-        let mut fake_tcp_stream = create_test_tstream();
+        let fake_tcp_stream = create_test_tstream();
 
         // Set:
-        let mut fake_app_context_set = ConnectionContext::new(&fake_mem_db, &mut fake_tcp_stream)?;
+        let mut fake_app_context_set = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
         let request_buffer_set = b"*3\r\n$3\r\nsET\r\n$3\r\nfoo\r\n$19\r\nHey world, I'm Joe!\r\n";
         // let request_buffer_set = b"*3\r\n$3\r\nsET\r\n$3\r\nfoo\r\n$19\r\nHey world, I'm Joe!\r\n$2\r\nPx\r\n$3\r\n100\r\n";
-        copy_to_array_until(
-            &mut fake_app_context_set.request.buffer,
-            request_buffer_set,
-            0,
-            |_, _, source_idx| source_idx == request_buffer_set.len() - 1,
-        );
-        fake_app_context_set.request.byte_count = request_buffer_set.len();
+        fake_app_context_set
+            .request
+            .buffer
+            .extend_from_slice(request_buffer_set);
 
-        parse_resp_proc_command(&mut fake_app_context_set)?;
+        assert!(parse_resp_proc_command(&mut fake_app_context_set)?);
         assert_eq!(
             fake_app_context_set
                 .get_request_resp_command_ref()
@@ -346,31 +477,27 @@ mod tests {
                 .response
                 .first()
                 .unwrap()
-                .command_response
-                .to_owned(),
-            "+OK\r\n".to_owned()
+                .to_bytes(RespProtocolVersion::Resp2),
+            b"+OK\r\n"
         );
         assert_eq!(
             fake_app_context_set
                 .response
                 .first()
                 .unwrap()
-                .command_response,
-            "+OK\r\n".to_owned()
+                .to_bytes(RespProtocolVersion::Resp2),
+            b"+OK\r\n"
         );
 
         // Get:
-        let mut fake_app_context_get = ConnectionContext::new(&fake_mem_db, &mut fake_tcp_stream)?;
+        let mut fake_app_context_get = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
         let request_buffer_get = b"*2\r\n$3\r\ngET\r\n$3\r\nfoo\r\n";
-        copy_to_array_until(
-            &mut fake_app_context_get.request.buffer,
-            request_buffer_get,
-            0,
-            |_, _, source_idx| source_idx == request_buffer_get.len() - 1,
-        );
-        fake_app_context_get.request.byte_count = request_buffer_get.len();
+        fake_app_context_get
+            .request
+            .buffer
+            .extend_from_slice(request_buffer_get);
 
-        parse_resp_proc_command(&mut fake_app_context_get)?;
+        assert!(parse_resp_proc_command(&mut fake_app_context_get)?);
         assert_eq!(
             fake_app_context_get
                 .get_request_resp_command_ref()
@@ -394,17 +521,274 @@ mod tests {
                 .response
                 .first()
                 .unwrap()
-                .command_response
-                .to_owned(),
-            "$19\r\nHey world, I'm Joe!\r\n".to_owned()
+                .to_bytes(RespProtocolVersion::Resp2),
+            b"$19\r\nHey world, I'm Joe!\r\n"
         );
         assert_eq!(
             fake_app_context_get
                 .response
                 .first()
                 .unwrap()
-                .command_response,
-            "$19\r\nHey world, I'm Joe!\r\n".to_owned()
+                .to_bytes(RespProtocolVersion::Resp2),
+            b"$19\r\nHey world, I'm Joe!\r\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_buffered_commands_handles_pipelined_commands() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_tcp_stream = create_test_tstream();
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        let request_buffer = b"*1\r\n$4\r\nPING\r\n*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n*2\r\n$4\r\nECHO";
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(request_buffer);
+
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert_eq!(
+            handled_commands
+                .responses
+                .iter()
+                .map(|response| response.to_bytes(RespProtocolVersion::Resp2))
+                .collect::<Vec<Vec<u8>>>(),
+            vec![
+                b"+PONG\r\n".to_vec(),
+                b"+OK\r\n".to_vec(),
+                b"$3\r\nbar\r\n".to_vec()
+            ]
+        );
+
+        // The incomplete ECHO is answered once the rest of it arrives.
+        assert_eq!(&fake_app_context.request.buffer[..], b"*2\r\n$4\r\nECHO");
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(b"\r\n$2\r\nhi\r\n");
+
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert_eq!(handled_commands.responses.len(), 1);
+        assert_eq!(
+            handled_commands.responses[0].to_bytes(RespProtocolVersion::Resp2),
+            b"$2\r\nhi\r\n"
+        );
+        assert!(fake_app_context.request.buffer.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_buffered_commands_replies_before_blocking() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_tcp_stream = create_test_tstream();
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(b"SET a 1\r\nBLPOP q 0\r\nGET a\r\n");
+
+        // The BLPOP is left pending, so that the reply of SET is written before it blocks.
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert!(handled_commands.has_pending_command);
+        assert_eq!(handled_commands.responses, vec![RespValue::ok().into()]);
+        assert_eq!(&fake_app_context.request.buffer[..], b"GET a\r\n");
+
+        send_test_request(&fake_mem_db, b"RPUSH q x\r\n").await?;
+
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert!(!handled_commands.has_pending_command);
+        assert_eq!(
+            handled_commands.responses,
+            vec![bulk_strings(&["q", "x"]).into(), bulk_string("1").into(),]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_command_handles_binary_keys_and_values() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_tcp_stream = create_test_tstream();
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        let mut request_buffer = b"*3\r\n$3\r\nSET\r\n$2\r\n\xff\x00\r\n$6\r\n".to_vec();
+        request_buffer.extend_from_slice(&[0xc3, 0x28, 0x00, 0x0d, 0x0a, 0xff]);
+        request_buffer.extend_from_slice(b"\r\n*2\r\n$3\r\nGET\r\n$2\r\n\xff\x00\r\n*2\r\n$4\r\nECHO\r\n$4\r\n\xc3\xa9\xc3\xa9\r\n");
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(&request_buffer);
+
+        let responses = handle_buffered_commands(&mut fake_app_context)
+            .await?
+            .responses;
+        assert_eq!(
+            responses[1].to_bytes(RespProtocolVersion::Resp2),
+            b"$6\r\n\xc3\x28\x00\r\n\xff\r\n"
+        );
+        assert_eq!(
+            responses[2].to_bytes(RespProtocolVersion::Resp2),
+            "$4\r\néé\r\n".as_bytes()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_buffered_commands_replies_errors_and_keeps_going() -> Result<(), anyhow::Error>
+    {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_tcp_stream = create_test_tstream();
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        let request_buffer = b"*2\r\n$3\r\nFOO\r\n$1\r\na\r\n*5\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n$2\r\nPX\r\n$3\r\nabc\r\n*1\r\n$4\r\nPING\r\n";
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(request_buffer);
+
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert!(!handled_commands.close_connection);
+        assert_eq!(
+            handled_commands.responses,
+            vec![
+                RespValue::Error(
+                    "ERR unknown command 'FOO', with args beginning with: 'a' ".to_owned()
+                )
+                .into(),
+                RespValue::Error("ERR value is not an integer or out of range".to_owned()).into(),
+                RespValue::SimpleString("PONG".to_owned()).into(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_buffered_commands_validates_arguments() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_tcp_stream = create_test_tstream();
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        let request_buffer = b"*1\r\n$3\r\nGET\r\n*3\r\n$4\r\nECHO\r\n$1\r\na\r\n$1\r\nb\r\n*4\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n$2\r\nPX\r\n";
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(request_buffer);
+
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert!(!handled_commands.close_connection);
+        assert_eq!(
+            handled_commands.responses,
+            vec![
+                RespValue::Error("ERR wrong number of arguments for 'get' command".to_owned())
+                    .into(),
+                RespValue::Error("ERR wrong number of arguments for 'echo' command".to_owned())
+                    .into(),
+                RespValue::Error("ERR syntax error".to_owned()).into(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_buffered_commands_closes_on_protocol_errors() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_tcp_stream = create_test_tstream();
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        let request_buffer = b"*1\r\n$4\r\nPING\r\n*1\r\n$x\r\nPING\r\n*1\r\n$4\r\nPING\r\n";
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(request_buffer);
+
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert!(handled_commands.close_connection);
+        assert_eq!(
+            handled_commands.responses,
+            vec![
+                RespValue::SimpleString("PONG".to_owned()).into(),
+                RespValue::Error("ERR Protocol error: invalid bulk length".to_owned()).into(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_buffered_commands_switches_protocol_with_hello() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_tcp_stream = create_test_tstream();
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(b"GET nope\r\nHELLO 3 AUTH default x SETNAME app\r\nGET nope\r\n");
+
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert_eq!(
+            fake_app_context.protocol_version,
+            RespProtocolVersion::Resp3
+        );
+        assert_eq!(fake_app_context.client_name, Some("app".into()));
+
+        let encoded = handled_commands
+            .responses
+            .iter()
+            .map(|response| response.to_bytes(fake_app_context.protocol_version))
+            .collect::<Vec<Vec<u8>>>();
+        // The reply before HELLO is still encoded in RESP2.
+        assert_eq!(encoded[0], b"$-1\r\n");
+        assert!(encoded[1].starts_with(b"%7\r\n$6\r\nserver\r\n$5\r\nredis\r\n$7\r\nversion\r\n"));
+        assert!(encoded[1]
+            .windows(15)
+            .any(|window| window == b"$5\r\nproto\r\n:3\r\n"));
+        assert_eq!(encoded[2], b"_\r\n");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handle_buffered_commands_rejects_invalid_hello() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let fake_tcp_stream = create_test_tstream();
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        fake_app_context.request.buffer.extend_from_slice(
+            b"HELLO 4\r\nHELLO x\r\nHELLO 3 AUTH bob pass\r\nHELLO 3 SETNAME \"a b\"\r\nHELLO 3 AUTH default\r\n",
+        );
+
+        let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+        assert_eq!(
+            handled_commands.responses,
+            vec![
+                RespValue::Error("NOPROTO unsupported protocol version".to_owned()).into(),
+                RespValue::Error(
+                    "ERR Protocol version is not an integer or out of range".to_owned()
+                )
+                .into(),
+                RespValue::Error(
+                    "WRONGPASS invalid username-password pair or user is disabled.".to_owned()
+                )
+                .into(),
+                RespValue::Error(
+                    "ERR Client names cannot contain spaces, newlines or special characters."
+                        .to_owned()
+                )
+                .into(),
+                RespValue::Error("ERR Syntax error in HELLO option 'AUTH'".to_owned()).into(),
+            ]
+        );
+        assert_eq!(
+            fake_app_context.protocol_version,
+            RespProtocolVersion::Resp2
         );
 
         Ok(())
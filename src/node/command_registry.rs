@@ -0,0 +1,1394 @@
+use crate::{
+    models::connection_context::ConnectionContext,
+    node::command_handlers::{
+        connection, hashes, keys, lists, replication, server, sets, sorted_sets, strings,
+    },
+    resp_parser::shared::RespCommandNames,
+    utils::parse_strict_i64,
+};
+
+use std::{collections::HashMap, future::Future, pin::Pin, sync::LazyLock};
+
+use anyhow::Error;
+use bytes::Bytes;
+
+pub(crate) type CommandFuture<'a> = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'a>>;
+
+/// Handles a command already parsed into `context.request.resp_command`, and sets its response
+/// in the context.
+pub(crate) trait CommandHandler: Send + Sync {
+    fn handle<'a>(&self, context: &'a mut ConnectionContext<'_>) -> CommandFuture<'a>;
+}
+
+/// A handler function that boxes the future of a handler in `command_handlers`, e.g.: <br/>
+/// `|context| Box::pin(strings::handle_command_get_async(context))`
+pub(crate) type CommandHandlerFn =
+    for<'a, 'b> fn(&'a mut ConnectionContext<'b>) -> CommandFuture<'a>;
+
+impl CommandHandler for CommandHandlerFn {
+    fn handle<'a>(&self, context: &'a mut ConnectionContext<'_>) -> CommandFuture<'a> {
+        self(context)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CommandFlag {
+    /// The command may modify the keyspace, so it is propagated to replicas.
+    Write,
+    /// The command only reads from the keyspace.
+    Readonly,
+    /// Administrative command, e.g. used for replication.
+    Admin,
+    /// The command runs in constant or logarithmic time.
+    Fast,
+    /// The command may park the connection until a key it waits for is ready, e.g. BLPOP.
+    Blocking,
+}
+
+/// Describes where the keys are in the arguments of a command, following the Redis convention:
+/// positions count the command name as argument 0, and a negative `last` counts from the end
+/// (-1 is the last argument).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KeyPositions {
+    pub first: i32,
+    pub last: i32,
+    pub step: i32,
+    /// The position of an argument counting the keys right after it, like the `numkeys` of Redis,
+    /// e.g. 1 in "SINTERCARD 2 a b", or 0 if there is none. These keys come on top of the ones
+    /// `first`, `last` and `step` describe.
+    pub numkeys: i32,
+}
+
+impl KeyPositions {
+    pub const NONE: KeyPositions = KeyPositions {
+        first: 0,
+        last: 0,
+        step: 0,
+        numkeys: 0,
+    };
+
+    pub const FIRST: KeyPositions = KeyPositions {
+        first: 1,
+        last: 1,
+        step: 1,
+        numkeys: 0,
+    };
+
+    /// The argument after a subcommand is a key, e.g. OBJECT ENCODING.
+    pub const SECOND: KeyPositions = KeyPositions {
+        first: 2,
+        last: 2,
+        step: 1,
+        numkeys: 0,
+    };
+
+    /// The first two arguments are keys, e.g. RENAME.
+    pub const FIRST_TWO: KeyPositions = KeyPositions {
+        first: 1,
+        last: 2,
+        step: 1,
+        numkeys: 0,
+    };
+
+    /// Every argument but the last is a key, e.g. BLPOP, whose last argument is a timeout.
+    pub const ALL_BUT_LAST: KeyPositions = KeyPositions {
+        first: 1,
+        last: -2,
+        step: 1,
+        numkeys: 0,
+    };
+
+    /// Every argument is a key, e.g. MGET.
+    pub const ALL: KeyPositions = KeyPositions {
+        first: 1,
+        last: -1,
+        step: 1,
+        numkeys: 0,
+    };
+
+    /// Every other argument is a key, followed by its value, e.g. MSET.
+    pub const KEY_VALUE_PAIRS: KeyPositions = KeyPositions {
+        first: 1,
+        last: -1,
+        step: 2,
+        numkeys: 0,
+    };
+
+    /// The same positions, plus the keys counted by the argument at `numkeys`, e.g.
+    /// `KeyPositions::FIRST.with_numkeys(2)` for "ZUNIONSTORE out 2 a b", whose keys are out, a
+    /// and b.
+    pub const fn with_numkeys(self, numkeys: i32) -> KeyPositions {
+        KeyPositions { numkeys, ..self }
+    }
+}
+
+/// Describes a command the server knows how to handle.
+#[derive(Debug)]
+pub(crate) struct CommandSpec {
+    pub name: &'static str,
+
+    /// Number of arguments, counting the command name itself, following the Redis convention:
+    /// a positive arity is the exact number of arguments, while a negative arity is the minimum
+    /// number of arguments (e.g. -3 means at least 3).
+    pub arity: i32,
+
+    pub flags: &'static [CommandFlag],
+
+    pub keys: KeyPositions,
+}
+
+impl CommandSpec {
+    /// `argument_count` includes the command name.
+    pub fn accepts_argument_count(&self, argument_count: usize) -> bool {
+        let argument_count = argument_count as i64;
+        let arity = self.arity as i64;
+
+        if arity >= 0 {
+            argument_count == arity
+        } else {
+            argument_count >= -arity
+        }
+    }
+
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Returns the keys in `parameters`, which don't include the command name.
+    pub fn get_keys<'a>(&self, parameters: &'a [Bytes]) -> Vec<&'a Bytes> {
+        let mut keys = Vec::new();
+
+        if self.keys.first > 0 && !parameters.is_empty() {
+            let argument_count = parameters.len() as i32 + 1;
+            let last = if self.keys.last < 0 {
+                argument_count + self.keys.last
+            } else {
+                self.keys.last.min(argument_count - 1)
+            };
+
+            keys.extend(
+                (self.keys.first..=last)
+                    .step_by(self.keys.step.max(1) as usize)
+                    .filter_map(|position| parameters.get(position as usize - 1)),
+            );
+        }
+
+        if self.keys.numkeys > 0 {
+            let numkeys = self.keys.numkeys as usize;
+            let key_count = parameters
+                .get(numkeys - 1)
+                .and_then(|raw_count| parse_strict_i64(raw_count))
+                .and_then(|key_count| usize::try_from(key_count).ok())
+                .unwrap_or(0);
+
+            keys.extend(parameters.iter().skip(numkeys).take(key_count));
+        }
+
+        keys
+    }
+}
+
+pub(crate) struct RegisteredCommand {
+    pub spec: CommandSpec,
+    pub handler: Box<dyn CommandHandler>,
+}
+
+/// All the commands the server can handle, keyed by their upper case name.
+pub(crate) struct CommandRegistry {
+    commands: HashMap<&'static str, RegisteredCommand>,
+}
+
+impl CommandRegistry {
+    fn new() -> Self {
+        let mut registry = CommandRegistry {
+            commands: HashMap::new(),
+        };
+
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::PING,
+                arity: -1,
+                flags: &[CommandFlag::Fast],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(async move { connection::handle_command_ping(context) }),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ECHO,
+                arity: 2,
+                flags: &[CommandFlag::Fast],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(async move { connection::handle_command_echo(context) }),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HELLO,
+                arity: -1,
+                flags: &[CommandFlag::Fast],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(connection::handle_command_hello(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::INFO,
+                arity: -1,
+                flags: &[],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(server::handle_command_info(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::REPLCONF,
+                arity: -1,
+                flags: &[CommandFlag::Admin],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(replication::handle_command_replconf(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::PSYNC,
+                arity: -3,
+                flags: &[CommandFlag::Admin],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(replication::handle_command_psync(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::GET,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_get_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SET,
+                arity: -3,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_set_async(context)),
+        );
+
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::INCR,
+                arity: 2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_incr_by_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::DECR,
+                arity: 2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_incr_by_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::INCRBY,
+                arity: 3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_incr_by_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::DECRBY,
+                arity: 3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_incr_by_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::INCRBYFLOAT,
+                arity: 3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_incr_by_float_async(context)),
+        );
+
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::MGET,
+                arity: -2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(strings::handle_command_mget_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::MSET,
+                arity: -3,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::KEY_VALUE_PAIRS,
+            },
+            |context| Box::pin(strings::handle_command_mset_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::MSETNX,
+                arity: -3,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::KEY_VALUE_PAIRS,
+            },
+            |context| Box::pin(strings::handle_command_mset_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::GETSET,
+                arity: 3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_getset_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::GETDEL,
+                arity: 2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_getdel_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::GETEX,
+                arity: -2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_getex_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::APPEND,
+                arity: 3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_append_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::STRLEN,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_strlen_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::GETRANGE,
+                arity: 4,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_getrange_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SETRANGE,
+                arity: 4,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(strings::handle_command_setrange_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LCS,
+                arity: -3,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST_TWO,
+            },
+            |context| Box::pin(strings::handle_command_lcs_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::EXPIRE,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_expire_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::PEXPIRE,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_expire_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::EXPIREAT,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_expire_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::PEXPIREAT,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_expire_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::TTL,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_ttl_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::PTTL,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_ttl_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::EXPIRETIME,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_ttl_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::PEXPIRETIME,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_ttl_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::PERSIST,
+                arity: 2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_persist_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::DEL,
+                arity: -2,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(keys::handle_command_del_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::UNLINK,
+                arity: -2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(keys::handle_command_del_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::EXISTS,
+                arity: -2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(keys::handle_command_exists_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::TYPE,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_type_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::OBJECT,
+                arity: -2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::SECOND,
+            },
+            |context| Box::pin(keys::handle_command_object_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::RENAME,
+                arity: 3,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST_TWO,
+            },
+            |context| Box::pin(keys::handle_command_rename_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::RENAMENX,
+                arity: 3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST_TWO,
+            },
+            |context| Box::pin(keys::handle_command_rename_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::COPY,
+                arity: -3,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST_TWO,
+            },
+            |context| Box::pin(keys::handle_command_copy_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::TOUCH,
+                arity: -2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(keys::handle_command_exists_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::KEYS,
+                arity: 2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(keys::handle_command_keys_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SCAN,
+                arity: -2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(keys::handle_command_scan_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SELECT,
+                arity: 2,
+                flags: &[CommandFlag::Fast],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(connection::handle_command_select_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::MOVE,
+                arity: 3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(keys::handle_command_move_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SWAPDB,
+                arity: 3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(server::handle_command_swapdb_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::DBSIZE,
+                arity: 1,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(server::handle_command_dbsize_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::FLUSHDB,
+                arity: -1,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(server::handle_command_flush_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::FLUSHALL,
+                arity: -1,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::NONE,
+            },
+            |context| Box::pin(server::handle_command_flush_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LPUSH,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_push_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::RPUSH,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_push_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LPOP,
+                arity: -2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_pop_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::RPOP,
+                arity: -2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_pop_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LRANGE,
+                arity: 4,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_lrange_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LLEN,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_llen_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LINDEX,
+                arity: 3,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_lindex_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LSET,
+                arity: 4,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_lset_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LREM,
+                arity: 4,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_lrem_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LTRIM,
+                arity: 4,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_ltrim_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LINSERT,
+                arity: 5,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_linsert_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LPOS,
+                arity: -3,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(lists::handle_command_lpos_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::LMOVE,
+                arity: 5,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST_TWO,
+            },
+            |context| Box::pin(lists::handle_command_lmove_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::BLPOP,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Blocking],
+                keys: KeyPositions::ALL_BUT_LAST,
+            },
+            |context| Box::pin(lists::handle_command_bpop_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::BRPOP,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Blocking],
+                keys: KeyPositions::ALL_BUT_LAST,
+            },
+            |context| Box::pin(lists::handle_command_bpop_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::BLMOVE,
+                arity: 6,
+                flags: &[CommandFlag::Write, CommandFlag::Blocking],
+                keys: KeyPositions::FIRST_TWO,
+            },
+            |context| Box::pin(lists::handle_command_blmove_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::BLMPOP,
+                arity: -5,
+                flags: &[CommandFlag::Write, CommandFlag::Blocking],
+                keys: KeyPositions::NONE.with_numkeys(2),
+            },
+            |context| Box::pin(lists::handle_command_blmpop_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HSET,
+                arity: -4,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hset_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HGET,
+                arity: 3,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hget_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HMGET,
+                arity: -3,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hmget_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HGETALL,
+                arity: 2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hgetall_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HDEL,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hdel_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HEXISTS,
+                arity: 3,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hexists_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HINCRBY,
+                arity: 4,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hincrby_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HINCRBYFLOAT,
+                arity: 4,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hincrbyfloat_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HKEYS,
+                arity: 2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hkeys_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HVALS,
+                arity: 2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hkeys_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HLEN,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hkeys_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HSCAN,
+                arity: -3,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hscan_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HRANDFIELD,
+                arity: -2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hrandfield_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HEXPIRE,
+                arity: -6,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hexpire_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HPEXPIRE,
+                arity: -6,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hexpire_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HEXPIREAT,
+                arity: -6,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hexpire_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HPEXPIREAT,
+                arity: -6,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hexpire_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HTTL,
+                arity: -5,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_httl_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HPTTL,
+                arity: -5,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_httl_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HEXPIRETIME,
+                arity: -5,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_httl_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HPEXPIRETIME,
+                arity: -5,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_httl_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::HPERSIST,
+                arity: -5,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(hashes::handle_command_hpersist_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SADD,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_sadd_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SREM,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_srem_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SMEMBERS,
+                arity: 2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_smembers_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SISMEMBER,
+                arity: 3,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_sismember_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SMISMEMBER,
+                arity: -3,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_smismember_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SCARD,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_scard_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SPOP,
+                arity: -2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_spop_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SRANDMEMBER,
+                arity: -2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_srandmember_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SINTER,
+                arity: -2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(sets::handle_command_sinter_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SUNION,
+                arity: -2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(sets::handle_command_sinter_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SDIFF,
+                arity: -2,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(sets::handle_command_sinter_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SINTERSTORE,
+                arity: -3,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(sets::handle_command_sinterstore_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SUNIONSTORE,
+                arity: -3,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(sets::handle_command_sinterstore_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SDIFFSTORE,
+                arity: -3,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::ALL,
+            },
+            |context| Box::pin(sets::handle_command_sinterstore_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SINTERCARD,
+                arity: -3,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::NONE.with_numkeys(1),
+            },
+            |context| Box::pin(sets::handle_command_sintercard_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SMOVE,
+                arity: 4,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST_TWO,
+            },
+            |context| Box::pin(sets::handle_command_smove_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::SSCAN,
+                arity: -3,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sets::handle_command_sscan_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZADD,
+                arity: -4,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zadd_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZCARD,
+                arity: 2,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zcard_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZSCORE,
+                arity: 3,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zscore_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZINCRBY,
+                arity: 4,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zincrby_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZREM,
+                arity: -3,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zrem_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZRANK,
+                arity: -3,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zrank_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZREVRANK,
+                arity: -3,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zrank_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZCOUNT,
+                arity: 4,
+                flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zcount_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZRANGE,
+                arity: -4,
+                flags: &[CommandFlag::Readonly],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zrange_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZPOPMIN,
+                arity: -2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zpopmin_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZPOPMAX,
+                arity: -2,
+                flags: &[CommandFlag::Write, CommandFlag::Fast],
+                keys: KeyPositions::FIRST,
+            },
+            |context| Box::pin(sorted_sets::handle_command_zpopmin_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZUNIONSTORE,
+                arity: -4,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST.with_numkeys(2),
+            },
+            |context| Box::pin(sorted_sets::handle_command_zunionstore_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZINTERSTORE,
+                arity: -4,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST.with_numkeys(2),
+            },
+            |context| Box::pin(sorted_sets::handle_command_zunionstore_async(context)),
+        );
+        registry.register(
+            CommandSpec {
+                name: RespCommandNames::ZDIFFSTORE,
+                arity: -4,
+                flags: &[CommandFlag::Write],
+                keys: KeyPositions::FIRST.with_numkeys(2),
+            },
+            |context| Box::pin(sorted_sets::handle_command_zunionstore_async(context)),
+        );
+
+        registry
+    }
+
+    pub fn register(&mut self, spec: CommandSpec, handler: CommandHandlerFn) {
+        self.commands.insert(
+            spec.name,
+            RegisteredCommand {
+                spec,
+                handler: Box::new(handler),
+            },
+        );
+    }
+
+    /// `command_name` is expected in upper case, as parsed into `RespCommand.name`.
+    pub fn lookup(&self, command_name: &str) -> Option<&RegisteredCommand> {
+        self.commands.get(command_name)
+    }
+}
+
+static COMMAND_REGISTRY: LazyLock<CommandRegistry> = LazyLock::new(CommandRegistry::new);
+
+pub(crate) fn command_registry() -> &'static CommandRegistry {
+    &COMMAND_REGISTRY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_registry, CommandFlag, CommandSpec, KeyPositions};
+    use crate::resp_parser::shared::RespCommandNames;
+
+    use bytes::Bytes;
+
+    #[test]
+    fn accepts_argument_count_passes() {
+        let fixed = CommandSpec {
+            name: RespCommandNames::GET,
+            arity: 2,
+            flags: &[],
+            keys: KeyPositions::FIRST,
+        };
+        assert!(!fixed.accepts_argument_count(1));
+        assert!(fixed.accepts_argument_count(2));
+        assert!(!fixed.accepts_argument_count(3));
+
+        let minimum = CommandSpec {
+            name: RespCommandNames::SET,
+            arity: -3,
+            flags: &[],
+            keys: KeyPositions::FIRST,
+        };
+        assert!(!minimum.accepts_argument_count(2));
+        assert!(minimum.accepts_argument_count(3));
+        assert!(minimum.accepts_argument_count(5));
+    }
+
+    #[test]
+    fn get_keys_passes() {
+        let parameters = ["k1", "v1", "k2", "v2"].map(Bytes::from);
+
+        let key_value_pairs = CommandSpec {
+            name: RespCommandNames::MSET,
+            arity: -3,
+            flags: &[],
+            keys: KeyPositions::KEY_VALUE_PAIRS,
+        };
+        assert_eq!(
+            key_value_pairs.get_keys(&parameters),
+            vec![&parameters[0], &parameters[2]]
+        );
+
+        let first_only = CommandSpec {
+            name: RespCommandNames::SET,
+            arity: -3,
+            flags: &[],
+            keys: KeyPositions::FIRST,
+        };
+        assert_eq!(first_only.get_keys(&parameters), vec![&parameters[0]]);
+
+        let no_keys = CommandSpec {
+            name: RespCommandNames::PING,
+            arity: -1,
+            flags: &[],
+            keys: KeyPositions::NONE,
+        };
+        assert!(no_keys.get_keys(&parameters).is_empty());
+
+        let counted = ["0", "2", "k1", "k2", "LEFT"].map(Bytes::from);
+        let blmpop = CommandSpec {
+            name: RespCommandNames::BLMPOP,
+            arity: -5,
+            flags: &[],
+            keys: KeyPositions::NONE.with_numkeys(2),
+        };
+        assert_eq!(blmpop.get_keys(&counted), vec![&counted[2], &counted[3]]);
+    }
+
+    #[test]
+    fn lookup_passes() {
+        let set_command = command_registry().lookup(RespCommandNames::SET).unwrap();
+        assert_eq!(set_command.spec.name, RespCommandNames::SET);
+        assert!(set_command.spec.has_flag(CommandFlag::Write));
+
+        assert!(command_registry().lookup("NOPE").is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::models::db::in_memory_db::InMemoryDb;
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Error;
+use tokio::{sync::Mutex, time::Instant};
+
+/// How often the active expiry cycle runs, like the default `hz` of Redis.
+const SERVER_HZ: u64 = 10;
+/// Keys with a TTL checked per step of a cycle.
+const ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP: usize = 20;
+/// Slots walked per step at most, per key to check, so that a step stays short when only few
+/// keys have a TTL.
+const ACTIVE_EXPIRE_CYCLE_SLOTS_PER_KEY: usize = 20;
+/// Share of the time between two cycles that a cycle may take.
+const ACTIVE_EXPIRE_CYCLE_SLOW_TIME_PERC: u64 = 25;
+/// Percentage of expired keys in a step under which the cycle stops until the next one.
+const ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE: usize = 10;
+
+/// Removes expired keys and hash fields in the background, so that keys that are never read again
+/// do not stay in memory forever.
+///
+/// Like Redis, every cycle checks keys with a TTL in steps of 20, and keeps going while more than
+/// 10% of them had expired, since that means many more are waiting to be removed. <br/>
+/// A cycle stops after 25% of the time between two cycles, and the lock on the db is released
+/// between steps, so that commands are not held back by it.
+pub(crate) async fn run(mem_db: Arc<Mutex<InMemoryDb>>) -> Result<(), Error> {
+    let mut interval = tokio::time::interval(Duration::from_millis(1000 / SERVER_HZ));
+    let mut current_db = 0;
+
+    loop {
+        interval.tick().await;
+
+        active_expire_cycle(&mem_db, &mut current_db).await?;
+    }
+}
+
+/// Runs a cycle over every database, starting from `current_db`, which is left where the next
+/// cycle should start, so that a cycle that stops at its time limit does not starve the databases
+/// after the one it stopped at.
+async fn active_expire_cycle(
+    mem_db: &Arc<Mutex<InMemoryDb>>,
+    current_db: &mut usize,
+) -> Result<(), Error> {
+    let started_at = Instant::now();
+    let time_limit =
+        Duration::from_millis(1000 / SERVER_HZ * ACTIVE_EXPIRE_CYCLE_SLOW_TIME_PERC / 100);
+    let db_count = mem_db.lock().await.get_db_count();
+
+    let mut total_sampled = 0;
+    let mut total_expired = 0;
+    let mut has_reached_time_limit = false;
+
+    'dbs: for _ in 0..db_count {
+        let db_index = *current_db % db_count;
+        *current_db = (db_index + 1) % db_count;
+
+        loop {
+            let sample = mem_db.lock().await.expire_sample(
+                db_index,
+                ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP,
+                ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP * ACTIVE_EXPIRE_CYCLE_SLOTS_PER_KEY,
+            )?;
+
+            total_sampled += sample.sampled;
+            total_expired += sample.expired;
+
+            if sample.sampled == 0
+                || sample.expired * 100 / sample.sampled <= ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE
+            {
+                break;
+            }
+
+            if started_at.elapsed() > time_limit {
+                has_reached_time_limit = true;
+                break 'dbs;
+            }
+
+            tokio::task::yield_now().await;
+        }
+    }
+
+    let mut db_lock = mem_db.lock().await;
+    let expiry_stats = &mut db_lock.expiry_stats;
+
+    expiry_stats.expire_cycle_cpu_milliseconds += started_at.elapsed().as_millis() as u64;
+    if has_reached_time_limit {
+        expiry_stats.expired_time_cap_reached_count += 1;
+    }
+    if total_sampled > 0 {
+        // A running average, so that a single cycle does not swing it.
+        let current_perc = total_expired as f64 * 100.0 / total_sampled as f64;
+        expiry_stats.expired_stale_perc =
+            current_perc * 0.05 + expiry_stats.expired_stale_perc * 0.95;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::active_expire_cycle;
+    use crate::{
+        models::db::in_memory_record::InMemoryRecord,
+        resp_parser::shared::RespValue,
+        test_helpers::utils::{create_test_mem_db, send_test_request},
+        utils::unix_time_millis,
+    };
+
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn active_expire_cycle_removes_expired_keys() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let now = unix_time_millis()?;
+
+        {
+            let mut db_lock = fake_mem_db.lock().await;
+            let records = db_lock.get_records_ref_mut(0);
+
+            for i in 0..1000 {
+                let expire_at_milli = match i % 4 {
+                    0 => None,
+                    1 => Some(now + 60_000),
+                    _ => Some(now - 1),
+                };
+
+                records.insert(
+                    Bytes::from(format!("key:{}", i)),
+                    InMemoryRecord::new(Bytes::from("v"), expire_at_milli),
+                );
+            }
+        }
+
+        active_expire_cycle(&fake_mem_db, &mut 0).await?;
+
+        {
+            let mut db_lock = fake_mem_db.lock().await;
+
+            // Steps keep going while more than 10% of the sampled keys have expired, which is
+            // the case until all of them are removed.
+            assert_eq!(db_lock.get_records_ref_mut(0).len(), 500);
+            assert_eq!(db_lock.expiry_stats.expired_keys, 500);
+            assert!(db_lock.expiry_stats.expired_stale_perc > 0.0);
+        }
+
+        let replies = send_test_request(&fake_mem_db, b"INFO\r\n").await?;
+        let info = match &replies[0] {
+            RespValue::BulkString(info) => String::from_utf8(info.to_vec())?,
+            reply => panic!("unexpected reply: {:?}", reply),
+        };
+
+        assert!(info.contains("# Stats\r\nexpired_keys:500\r\n"));
+        assert!(info.contains("\r\nexpire_cycle_cpu_milliseconds:"));
+        assert!(info.ends_with("# Keyspace\r\ndb0:keys=500,expires=250,avg_ttl=0"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn active_expire_cycle_removes_expired_hash_fields() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+
+        send_test_request(
+            &fake_mem_db,
+            b"HSET session a 1 b 2\r\nHPEXPIRE session 1 FIELDS 1 a\r\nHSET gone a 1\r\nHPEXPIRE gone 1 FIELDS 1 a\r\n",
+        )
+        .await?;
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        active_expire_cycle(&fake_mem_db, &mut 0).await?;
+
+        // The records are looked at directly, since reading the keys would expire their fields.
+        let mut db_lock = fake_mem_db.lock().await;
+        let records = db_lock.get_records_ref_mut(0);
+
+        let session = records.get_mut(&Bytes::from("session")).unwrap();
+        assert_eq!(session.get_hash_mut()?.len(), 1);
+        assert!(!session.has_expiring_fields());
+        assert!(records.get(&Bytes::from("gone")).is_none());
+        assert_eq!(db_lock.expiry_stats.expired_subkeys, 2);
+        assert_eq!(db_lock.expiry_stats.expired_keys, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn active_expire_cycle_stops_when_few_keys_have_expired() -> Result<(), anyhow::Error> {
+        let fake_mem_db = create_test_mem_db()?;
+        let now = unix_time_millis()?;
+
+        {
+            let mut db_lock = fake_mem_db.lock().await;
+            let records = db_lock.get_records_ref_mut(0);
+
+            for i in 0..1000 {
+                let expire_at_milli = if i == 0 { now - 1 } else { now + 60_000 };
+
+                records.insert(
+                    Bytes::from(format!("key:{}", i)),
+                    InMemoryRecord::new(Bytes::from("v"), Some(expire_at_milli)),
+                );
+            }
+        }
+
+        active_expire_cycle(&fake_mem_db, &mut 0).await?;
+
+        let mut db_lock = fake_mem_db.lock().await;
+        assert_eq!(db_lock.get_records_ref_mut(0).len(), 999);
+        assert_eq!(db_lock.expiry_stats.expired_keys, 1);
+
+        Ok(())
+    }
+}
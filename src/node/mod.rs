@@ -1,4 +1,6 @@
 mod command_handlers;
 pub mod command_listener;
+mod command_registry;
+pub mod expiry;
 mod propagation;
 pub mod replica;
@@ -1,36 +1,74 @@
-use crate::{models::connection_context::ConnectionContext, resp_parser::shared::RespCommandType};
+use crate::{
+    models::connection_context::{ConnectionContext, Propagation},
+    node::command_registry::{command_registry, CommandFlag},
+    resp_parser::shared::{RespCommand, RespCommandNames},
+};
 
 use anyhow::Error;
+use bytes::Bytes;
 use tokio::io::AsyncWriteExt;
 
-pub(crate) async fn propagate<'a>(
-    connection_context: &mut ConnectionContext<'a>,
-) -> Result<(), Error> {
-    if connection_context
-        .request
-        .resp_command
-        .as_ref()
-        .unwrap()
-        .command_type
-        != RespCommandType::Write
-    {
+/// Sends a write command the connection just handled to the replicas, followed by what it served
+/// to blocked clients, so that replicas apply the pops right after the push like the master did.
+pub(crate) async fn propagate(connection_context: &mut ConnectionContext<'_>) -> Result<(), Error> {
+    let is_write_command = command_registry()
+        .lookup(
+            &connection_context
+                .request
+                .resp_command
+                .as_ref()
+                .unwrap()
+                .name,
+        )
+        .is_some_and(|registered_command| registered_command.spec.has_flag(CommandFlag::Write));
+
+    if !is_write_command {
         return Ok(());
     }
 
-    let db_lock = connection_context.mem_db.lock().await;
-    let app_data = db_lock.get_app_data_ref();
+    let mut db_lock = connection_context.mem_db.lock().await;
 
-    if app_data.get_master_data_ref().is_none() {
-        return Ok(());
-    }
+    let master_data = match db_lock.get_app_data_mut().get_master_data_mut() {
+        None => return Ok(()),
+        Some(master_data) => master_data,
+    };
 
     println!("propagating command to all slaves...");
 
-    // To remove the null/0 bytes at the end of the original buffer.
-    let original_request =
-        &connection_context.request.buffer[0..connection_context.request.byte_count];
+    let received_request = match &connection_context.propagation {
+        Propagation::Received => connection_context.request.raw_command.to_vec(),
+        Propagation::Rewritten(propagated_commands) => propagated_commands
+            .iter()
+            .flat_map(|propagated_command| propagated_command.to_resp_bytes())
+            .collect(),
+    };
+
+    let mut propagated_request = Vec::new();
+    let mut propagated_db = master_data.propagated_db;
 
-    for (_, slave) in &app_data.get_master_data_ref().unwrap().slaves {
+    if !received_request.is_empty() {
+        append_command(
+            &mut propagated_request,
+            &mut propagated_db,
+            connection_context.selected_db,
+            &received_request,
+        );
+    }
+    for (db_index, served_command) in &connection_context.served_commands {
+        append_command(
+            &mut propagated_request,
+            &mut propagated_db,
+            *db_index,
+            &served_command.to_resp_bytes(),
+        );
+    }
+
+    if propagated_request.is_empty() {
+        return Ok(());
+    }
+    master_data.propagated_db = propagated_db;
+
+    for slave in master_data.slaves.values() {
         if !slave.full_handshake {
             continue;
         }
@@ -38,10 +76,32 @@ pub(crate) async fn propagate<'a>(
         println!("slave port: {}", slave.port);
 
         let mut slave_tcp_lock = slave.tcp_stream.lock().await;
-        slave_tcp_lock.write_all(&original_request).await?;
+        slave_tcp_lock.write_all(&propagated_request).await?;
         slave_tcp_lock.flush().await?;
     }
 
     println!("finished propagating.");
     Ok(())
 }
+
+/// Appends a command run against `db_index` to the request sent to replicas. <br/>
+/// Replicas are sent a SELECT whenever a command runs against another database than the
+/// previous one.
+fn append_command(
+    propagated_request: &mut Vec<u8>,
+    propagated_db: &mut Option<usize>,
+    db_index: usize,
+    command_bytes: &[u8],
+) {
+    if *propagated_db != Some(db_index) {
+        let select_command = RespCommand::new(
+            RespCommandNames::SELECT,
+            vec![Bytes::from(db_index.to_string())],
+        );
+
+        propagated_request.extend_from_slice(&select_command.to_resp_bytes());
+        *propagated_db = Some(db_index);
+    }
+
+    propagated_request.extend_from_slice(command_bytes);
+}
@@ -1,8 +1,9 @@
 use crate::{
-    models::{connection_context::InternalRequest, db::in_memory_db::InMemoryDb},
+    models::db::in_memory_db::InMemoryDb,
     resp_parser::{
         self,
-        shared::{RespCommandResponseNames, RespDataTypesFirstByte},
+        data_types::RespParseResult,
+        shared::{RespCommandResponseNames, RespProtocolLimits, RespValue},
     },
     TCP_READ_TIMEOUT, TCP_READ_TIMEOUT_MAX_RETRIES, TCP_RESPONSE_BUFFER_SIZE,
 };
@@ -10,13 +11,14 @@ use crate::{
 use std::sync::Arc;
 
 use anyhow::{Error, Ok, Result};
+use bytes::{Buf, BytesMut};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
     sync::Mutex,
 };
 
-pub(crate) async fn handshake<'a>(mem_db: &Arc<Mutex<InMemoryDb>>) -> Result<(), Error> {
+pub(crate) async fn handshake(mem_db: &Arc<Mutex<InMemoryDb>>) -> Result<(), Error> {
     println!("running handshake");
 
     let (master_host, master_port) = {
@@ -34,16 +36,20 @@ pub(crate) async fn handshake<'a>(mem_db: &Arc<Mutex<InMemoryDb>>) -> Result<(),
     let mut tcp_stream_with_master =
         TcpStream::connect(format!("{}:{}", master_host, master_port)).await?;
 
-    send_ping(&mut tcp_stream_with_master).await?;
-    send_replconf(&mut tcp_stream_with_master).await?;
-    send_psync(&mut tcp_stream_with_master).await?;
+    // Replies may be split across reads, or several may come in a single read (e.g. the RDB file
+    // right after FULLRESYNC), so whatever was read after a reply is kept for the next one.
+    let mut buffer = BytesMut::with_capacity(TCP_RESPONSE_BUFFER_SIZE);
+
+    send_ping(&mut tcp_stream_with_master, &mut buffer).await?;
+    send_replconf(&mut tcp_stream_with_master, &mut buffer).await?;
+    send_psync(&mut tcp_stream_with_master, &mut buffer).await?;
 
     println!("finished handshake.");
 
     Ok(())
 }
 
-async fn send_ping(tcp_stream: &mut TcpStream) -> Result<(), Error> {
+async fn send_ping(tcp_stream: &mut TcpStream, buffer: &mut BytesMut) -> Result<(), Error> {
     println!("sending PING");
 
     tcp_stream.write_all(b"*1\r\n$4\r\nping\r\n").await?;
@@ -51,8 +57,8 @@ async fn send_ping(tcp_stream: &mut TcpStream) -> Result<(), Error> {
 
     println!("awaiting PONG as response...");
 
-    await_response(tcp_stream, |response| {
-        response.as_str() == RespCommandResponseNames::PONG
+    await_response(tcp_stream, buffer, |response| {
+        response == RespCommandResponseNames::PONG
     })
     .await?;
 
@@ -61,14 +67,14 @@ async fn send_ping(tcp_stream: &mut TcpStream) -> Result<(), Error> {
     Ok(())
 }
 
-async fn send_replconf(tcp_stream: &mut TcpStream) -> Result<(), Error> {
+async fn send_replconf(tcp_stream: &mut TcpStream, buffer: &mut BytesMut) -> Result<(), Error> {
     println!("sending REPLCONF 1 (listening-port).");
     tcp_stream
         .write_all(b"*3\r\n$8\r\nREPLCONF\r\n$14\r\nlistening-port\r\n$4\r\n6380\r\n")
         .await?;
     tcp_stream.flush().await?;
 
-    await_response_ok(tcp_stream).await?;
+    await_response_ok(tcp_stream, buffer).await?;
 
     println!("sending REPLCONF 2 (capabilities).");
     tcp_stream
@@ -76,12 +82,12 @@ async fn send_replconf(tcp_stream: &mut TcpStream) -> Result<(), Error> {
         .await?;
     tcp_stream.flush().await?;
 
-    await_response_ok(tcp_stream).await?;
+    await_response_ok(tcp_stream, buffer).await?;
 
     Ok(())
 }
 
-async fn send_psync(tcp_stream: &mut TcpStream) -> Result<(), Error> {
+async fn send_psync(tcp_stream: &mut TcpStream, buffer: &mut BytesMut) -> Result<(), Error> {
     println!("sending PSYNC (synchronize state)");
     tcp_stream
         .write_all(b"*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n")
@@ -90,18 +96,28 @@ async fn send_psync(tcp_stream: &mut TcpStream) -> Result<(), Error> {
 
     println!("awaiting FULLRESYNC as response...");
 
-    await_response(tcp_stream, |response| response.starts_with("FULLRESYNC")).await?;
+    await_response(tcp_stream, buffer, |response| {
+        response.starts_with("FULLRESYNC")
+    })
+    .await?;
+
+    println!("FULLRESYNC obtained, awaiting the RDB file...");
+
+    let rdb_file = read_from_master(tcp_stream, buffer, |buffer| {
+        resp_parser::parse_resp_rdb_payload(buffer, RespProtocolLimits::default().max_bulk_len)
+    })
+    .await?;
 
-    println!("FULLRESYNC obtained.");
+    println!("RDB file obtained ({} bytes).", rdb_file.len());
 
     Ok(())
 }
 
-async fn await_response_ok(tcp_stream: &mut TcpStream) -> Result<(), Error> {
+async fn await_response_ok(tcp_stream: &mut TcpStream, buffer: &mut BytesMut) -> Result<(), Error> {
     println!("awaiting OK as response...");
 
-    await_response(tcp_stream, |response| {
-        response.as_str() == RespCommandResponseNames::OK
+    await_response(tcp_stream, buffer, |response| {
+        response == RespCommandResponseNames::OK
     })
     .await?;
 
@@ -110,50 +126,79 @@ async fn await_response_ok(tcp_stream: &mut TcpStream) -> Result<(), Error> {
     Ok(())
 }
 
+/// Reads the next reply of the master, and fails if it is an error or if its text does not
+/// satisfy `expected_response_predicate`.
 async fn await_response(
     tcp_stream: &mut TcpStream,
-    expected_response_predicate: impl Fn(&String) -> bool,
+    buffer: &mut BytesMut,
+    expected_response_predicate: impl Fn(&str) -> bool,
 ) -> Result<(), Error> {
-    let mut response = String::new();
-    let mut num_of_retries = 0;
+    let response = read_from_master(tcp_stream, buffer, |buffer| {
+        resp_parser::parse_redis_resp_proc_response(buffer, &RespProtocolLimits::default())
+    })
+    .await?;
+
+    let response_text = match &response {
+        RespValue::Error(message) => {
+            return Err(Error::msg(format!("Master replied an error: {}", message)))
+        }
+        RespValue::SimpleString(text) => text.to_owned(),
+        RespValue::BulkString(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        _ => String::new(),
+    };
+
+    if !expected_response_predicate(&response_text) {
+        return Err(Error::msg(format!(
+            "Unexpected response from master: {:?}",
+            response
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reads from the master until `parse` gets a value out of `buffer`, which is then removed from
+/// the buffer, keeping whatever was read after it.
+async fn read_from_master<T>(
+    tcp_stream: &mut TcpStream,
+    buffer: &mut BytesMut,
+    parse: impl Fn(&[u8]) -> RespParseResult<T>,
+) -> Result<T, Error> {
+    let mut num_of_timeouts = 0;
 
-    println!("listening for requests...");
+    loop {
+        if let Some((value, len)) = parse(buffer)? {
+            buffer.advance(len);
 
-    while num_of_retries <= TCP_READ_TIMEOUT_MAX_RETRIES && !expected_response_predicate(&response)
-    {
-        num_of_retries += 1;
-        let mut request_buffer = [0; TCP_RESPONSE_BUFFER_SIZE];
+            return Ok(value);
+        }
 
-        match tokio::time::timeout(TCP_READ_TIMEOUT, tcp_stream.read(&mut request_buffer)).await {
+        let mut read_chunk = [0; TCP_RESPONSE_BUFFER_SIZE];
+
+        match tokio::time::timeout(TCP_READ_TIMEOUT, tcp_stream.read(&mut read_chunk)).await {
             Err(e) => {
-                println!("timeout while reading request - {}", e);
-                break;
+                println!("timeout while reading response - {}", e);
+
+                num_of_timeouts += 1;
+                if num_of_timeouts > TCP_READ_TIMEOUT_MAX_RETRIES {
+                    return Err(Error::msg(
+                        "Error while reading handshake response: timeout.",
+                    ));
+                }
             }
             Result::Ok(read_result) => {
-                let request_byte_count = match read_result {
-                    Err(e) => {
-                        const BASE_MESSAGE: &str = "Error while reading handshake response";
-                        println!("{} - {:?}", BASE_MESSAGE, e);
+                let response_byte_count = read_result?;
 
-                        return Err(Error::msg(format!("{}: timeout.", BASE_MESSAGE)));
-                    }
-                    Result::Ok(len) => len,
-                };
+                println!("response received of len {}", response_byte_count);
 
-                println!("request received of len {}", request_byte_count);
-
-                if request_byte_count == 0 {
-                    break;
+                if response_byte_count == 0 {
+                    return Err(Error::msg(
+                        "Error while reading handshake response: connection closed by master.",
+                    ));
                 }
 
-                response = resp_parser::parse_redis_resp_proc_response(&InternalRequest {
-                    buffer: request_buffer,
-                    byte_count: request_byte_count,
-                })?
-                .get_value_string();
+                buffer.extend_from_slice(&read_chunk[..response_byte_count]);
             }
         };
     }
-
-    Ok(())
 }
@@ -1,134 +1,397 @@
 use crate::{
-    models::connection_context::ConnectionContext,
-    resp_parser::data_types::move_resp_bulk_string,
-    utils::{return_err, split_u8_slice_once, u8_slice_into_char_slice, LineEndings},
-    TCP_RESPONSE_BUFFER_SIZE,
+    models::{command_error::CommandError, connection_context::ConnectionContext},
+    resp_parser::data_types::{
+        parse_resp_bulk_string, read_bounded_crlf_line, RespParseResult, PROTO_INLINE_MAX_SIZE,
+    },
 };
 
-use super::{
-    data_types::move_to_crlf_end,
-    shared::{RespCommand, RespCommandType, RespDataTypesFirstByte},
-};
+use super::shared::{RespCommand, RespDataTypesFirstByte, RespProtocolLimits};
 
 use anyhow::{Error, Result};
+use bytes::{Buf, Bytes};
 
-/// Parses the raw command buffer in `context.request.buffer` into a [`RespCommand`] and
+/// Parses the first complete command in `context.request.buffer` into a [`RespCommand`] and
 /// populates `context.request.resp_command` with it.
 ///
+/// The bytes of the parsed command are moved out of the buffer into `context.request.raw_command`,
+/// so whatever was read after them stays in the buffer for the next call. <br/>
+/// Returns `false` if the buffer does not hold a complete command yet, in which case nothing is
+/// consumed and the caller should read more bytes from the stream before trying again.
+///
+/// Requests over the limits in `context.request.protocol_limits` are rejected with a protocol
+/// error, without waiting for the rest of them.
+///
 /// Input examples: <br/>
 /// "*1\r\n$4\r\nping\r\n" <br/>
 /// "*2\r\n$4\r\necho\r\n$3\r\nhey\r\n" <br/>
-pub(crate) fn parse_resp_proc_command(context: &mut ConnectionContext<'_>) -> Result<(), Error> {
-    if context.get_request_ref().byte_count == 0 {
-        return return_err("Could not parse command: Request has byte count of 0.".to_owned());
-    }
+/// "echo \"hey you\"\r\n" (inline command, e.g. typed in telnet or nc) <br/>
+pub(crate) fn parse_resp_proc_command(context: &mut ConnectionContext<'_>) -> Result<bool, Error> {
+    loop {
+        let parsed = parse_resp_command(&context.request.buffer, &context.request.protocol_limits)?;
 
-    let raw_command = &context.get_request_ref().buffer[..context.get_request_ref().byte_count];
+        match parsed {
+            None => return Ok(false),
+            // Empty requests (e.g. "*0\r\n" or an empty line) are skipped, like Redis does.
+            Some((None, request_len)) => context.request.buffer.advance(request_len),
+            Some((Some(resp_command), command_len)) => {
+                context.request.raw_command = context.request.buffer.split_to(command_len).freeze();
+                context.set_request_resp_command(resp_command);
 
-    if !raw_command.starts_with(RespDataTypesFirstByte::ARRAYS_BYTE) {
-        return return_err("Could not parse command: Command malformed - not an array.".to_owned());
+                return Ok(true);
+            }
+        };
     }
+}
 
-    let raw_command = split_u8_slice_once(raw_command, LineEndings::CRLF_BYTES);
+/// Parses the command at the start of `buffer`, which is `None` for empty or null arrays, and for
+/// empty inline commands.
+fn parse_resp_command(
+    buffer: &[u8],
+    limits: &RespProtocolLimits,
+) -> RespParseResult<Option<RespCommand>> {
+    if buffer.is_empty() {
+        return Ok(None);
+    }
 
-    if raw_command.is_none() {
-        return return_err("Could not parse command: Command array malformed.".to_owned());
+    if buffer[0] != RespDataTypesFirstByte::ARRAYS_BYTE {
+        return parse_inline_command(buffer);
     }
 
-    let raw_command = raw_command.unwrap();
+    let (num_of_parts, position) = match read_bounded_crlf_line(buffer, 1, "mbulk count")? {
+        None => return Ok(None),
+        Some(line) => line,
+    };
 
-    if raw_command.0.len() < 2 {
-        return Err(Error::msg("Could not parse command: Command malformed."));
+    let num_of_parts = match std::str::from_utf8(num_of_parts)
+        .ok()
+        .and_then(|num_of_parts| num_of_parts.parse::<i64>().ok())
+    {
+        Some(num_of_parts) if num_of_parts <= limits.max_multibulk_len as i64 => num_of_parts,
+        _ => return Err(CommandError::Protocol("invalid multibulk length".to_owned()).into()),
+    };
+
+    if num_of_parts <= 0 {
+        return Ok(Some((None, position)));
     }
 
-    let (array_type, num_of_parts) = raw_command.0.split_at(1);
+    let (command_name, position) =
+        match parse_resp_bulk_string(buffer, position, limits.max_bulk_len)? {
+            None => return Ok(None),
+            Some(parsed) => parsed,
+        };
+    let command_name = command_name.get_value_string().to_ascii_uppercase();
 
-    if array_type != RespDataTypesFirstByte::ARRAYS_BYTE {
-        return Err(Error::msg(
-            "Could not parse command: Command malformed, the command is not an array.",
-        ));
-    }
+    Ok(parse_resp_multi_param_command_body(
+        &command_name,
+        num_of_parts as usize - 1,
+        buffer,
+        position,
+        limits,
+    )?
+    .map(|(resp_command, command_len)| (Some(resp_command), command_len)))
+}
+
+/// Parses a command sent as a single line of space separated arguments, terminated by LF or CRLF,
+/// which is `None` for empty lines.
+///
+/// E.g.: "SET foo \"hello world\"\r\n"
+fn parse_inline_command(buffer: &[u8]) -> RespParseResult<Option<RespCommand>> {
+    let line_len = match buffer.iter().position(|byte| *byte == b'\n') {
+        None if buffer.len() > PROTO_INLINE_MAX_SIZE => {
+            return Err(CommandError::Protocol("too big inline request".to_owned()).into())
+        }
+        None => return Ok(None),
+        Some(line_len) => line_len,
+    };
 
-    let mut raw_command_chars = [0 as char; TCP_RESPONSE_BUFFER_SIZE];
-    u8_slice_into_char_slice(raw_command.1, &mut raw_command_chars);
-    let raw_command_chars = raw_command_chars;
+    let line = buffer[..line_len]
+        .strip_suffix(b"\r")
+        .unwrap_or(&buffer[..line_len]);
 
-    // TODO: Refactor this to use the raw byte slice instead of the iterator.
-    let mut command_body_iter = raw_command_chars.iter().enumerate().peekable();
-    let current_char: Option<(usize, &char)> = command_body_iter.next();
+    let mut arguments = match split_inline_arguments(line) {
+        None => {
+            return Err(CommandError::Protocol("unbalanced quotes in request".to_owned()).into())
+        }
+        Some(arguments) => arguments.into_iter(),
+    };
 
-    let command_name = move_resp_bulk_string(&mut command_body_iter, &current_char)?;
-    let command_name = command_name.get_value_string().to_ascii_uppercase();
+    let resp_command = arguments.next().map(|command_name| RespCommand {
+        name: String::from_utf8_lossy(&command_name).to_ascii_uppercase(),
+        parameters: arguments.collect(),
+    });
 
-    if command_name.is_empty() {
-        return Err(Error::msg("Could not parse command: Command is empty."));
-    }
+    Ok(Some((resp_command, line_len + 1)))
+}
 
-    let current_char: Option<(usize, &char)> = command_body_iter.next();
+/// Splits an inline command into its arguments, the same way Redis does (`sdssplitargs`).
+///
+/// Arguments are separated by whitespace and can be quoted: <br/>
+/// - Within double quotes, `\n`, `\r`, `\t`, `\b`, `\a` and `\xHH` (hex byte) are unescaped, and a
+///   backslash before any other character escapes it. <br/>
+/// - Within single quotes, only `\'` is unescaped. <br/>
+///
+/// Returns `None` if a quote is not closed, or is not followed by whitespace.
+fn split_inline_arguments(line: &[u8]) -> Option<Vec<Bytes>> {
+    let mut arguments = Vec::<Bytes>::new();
+    let mut position = 0;
 
-    context.set_request_resp_command(parse_resp_multi_param_command_body(
-        &command_name,
-        std::str::from_utf8(num_of_parts)?.parse::<u8>()? - 1,
-        &mut command_body_iter,
-        &current_char,
-    )?);
+    loop {
+        while position < line.len() && line[position].is_ascii_whitespace() {
+            position += 1;
+        }
+
+        if position >= line.len() {
+            return Some(arguments);
+        }
+
+        let mut argument = Vec::<u8>::new();
+        let mut in_double_quotes = false;
+        let mut in_single_quotes = false;
+
+        loop {
+            let current = line.get(position).copied();
+
+            if in_double_quotes {
+                match current {
+                    None => return None,
+                    // Like Redis, "\x" only escapes two hex digits; otherwise the backslash
+                    // is dropped by the generic escape arm below.
+                    Some(b'\\')
+                        if line.get(position + 1) == Some(&b'x')
+                            && line
+                                .get(position + 2..position + 4)
+                                .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) =>
+                    {
+                        let hex = std::str::from_utf8(&line[position + 2..position + 4]).unwrap();
+                        argument.push(u8::from_str_radix(hex, 16).unwrap());
+                        position += 4;
+                        continue;
+                    }
+                    Some(b'\\') if position + 1 < line.len() => {
+                        position += 1;
+                        argument.push(match line[position] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            escaped => escaped,
+                        });
+                    }
+                    Some(b'"') => {
+                        // The closing quote must be followed by a space or nothing at all.
+                        if line
+                            .get(position + 1)
+                            .is_some_and(|next| !next.is_ascii_whitespace())
+                        {
+                            return None;
+                        }
+
+                        position += 1;
+                        break;
+                    }
+                    Some(byte) => argument.push(byte),
+                }
+            } else if in_single_quotes {
+                match current {
+                    None => return None,
+                    Some(b'\\') if line.get(position + 1) == Some(&b'\'') => {
+                        position += 1;
+                        argument.push(b'\'');
+                    }
+                    Some(b'\'') => {
+                        if line
+                            .get(position + 1)
+                            .is_some_and(|next| !next.is_ascii_whitespace())
+                        {
+                            return None;
+                        }
+
+                        position += 1;
+                        break;
+                    }
+                    Some(byte) => argument.push(byte),
+                }
+            } else {
+                match current {
+                    None => break,
+                    Some(byte) if byte.is_ascii_whitespace() => break,
+                    Some(b'"') => in_double_quotes = true,
+                    Some(b'\'') => in_single_quotes = true,
+                    Some(byte) => argument.push(byte),
+                }
+            }
+
+            position += 1;
+        }
 
-    Ok(())
+        arguments.push(Bytes::from(argument));
+    }
 }
 
-fn parse_resp_multi_param_command_body<'a>(
-    command_name: &'a String,
-    parameter_count: u8,
-    command_iter: &mut std::iter::Peekable<std::iter::Enumerate<std::slice::Iter<'_, char>>>,
-    current_char: &Option<(usize, &char)>,
-) -> Result<RespCommand, Error> {
-    let mut curr_char = *current_char;
-    let mut parameters = Vec::<String>::new();
+fn parse_resp_multi_param_command_body(
+    command_name: &str,
+    parameter_count: usize,
+    buffer: &[u8],
+    start: usize,
+    limits: &RespProtocolLimits,
+) -> RespParseResult<RespCommand> {
+    let mut position = start;
+    // The count comes from the client, so it's not trusted for the initial allocation.
+    let mut parameters = Vec::<Bytes>::with_capacity(parameter_count.min(1024));
 
     for _ in 0..parameter_count {
-        let param = move_resp_bulk_string(command_iter, &curr_char)?
-            .get_value_string()
-            .to_owned();
+        let (param, next) = match parse_resp_bulk_string(buffer, position, limits.max_bulk_len)? {
+            None => return Ok(None),
+            Some(parsed) => parsed,
+        };
 
-        parameters.push(param);
-        curr_char = command_iter.next();
+        parameters.push(param.get_value_bytes());
+        position = next;
     }
 
-    move_to_crlf_end(command_iter);
-
-    Ok(RespCommand {
-        name: command_name.to_owned(),
-        command_type: RespCommandType::from_command_name(command_name),
-        parameters,
-    })
+    Ok(Some((
+        RespCommand {
+            name: command_name.to_owned(),
+            parameters,
+        },
+        position,
+    )))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         models::connection_context::ConnectionContext,
-        resp_parser::{parse_resp_proc_command, shared::RespCommandNames},
+        resp_parser::{
+            parse_resp_proc_command,
+            shared::{RespCommandNames, RespProtocolLimits},
+        },
         test_helpers::utils::{create_test_mem_db, create_test_tstream},
-        utils::copy_to_array_until,
     };
 
+    use bytes::Bytes;
+
     #[tokio::test]
     async fn parse_resp_proc_command_should_parse_known_commands() -> Result<(), anyhow::Error> {
         let fake_tcp_stream = &mut create_test_tstream();
         let fake_mem_db = create_test_mem_db()?;
 
-        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, &fake_tcp_stream)?;
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, fake_tcp_stream)?;
 
         let request_buffer = b"*1\r\n$4\r\npiNg\r\n";
-        copy_to_array_until(
-            &mut fake_app_context.request.buffer,
-            request_buffer,
-            0,
-            |_, _, source_idx| source_idx == request_buffer.len() - 1,
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(request_buffer);
+
+        assert!(parse_resp_proc_command(&mut fake_app_context)?);
+        assert_eq!(
+            fake_app_context
+                .get_request_resp_command_ref()
+                .unwrap()
+                .name,
+            RespCommandNames::PING
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_resp_proc_command_should_wait_for_values_split_across_reads(
+    ) -> Result<(), anyhow::Error> {
+        let fake_tcp_stream = &mut create_test_tstream();
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, fake_tcp_stream)?;
+
+        let value = "x".repeat(5000);
+        let request_buffer = format!(
+            "*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n${}\r\n{}\r\n*1\r\n$4\r\nPING\r\n",
+            value.len(),
+            value
         );
-        fake_app_context.request.byte_count = request_buffer.len();
+        let (first_read, second_read) = request_buffer.as_bytes().split_at(1024);
+
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(first_read);
+        assert!(!parse_resp_proc_command(&mut fake_app_context)?);
+        assert!(fake_app_context.get_request_resp_command_ref().is_none());
+        assert_eq!(fake_app_context.request.buffer.len(), first_read.len());
 
-        parse_resp_proc_command(&mut fake_app_context)?;
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(second_read);
+        assert!(parse_resp_proc_command(&mut fake_app_context)?);
+
+        let resp_command = fake_app_context.get_request_resp_command_ref().unwrap();
+        assert_eq!(resp_command.name, RespCommandNames::SET);
+        assert_eq!(
+            resp_command.parameters,
+            vec![Bytes::from("foo"), Bytes::from(value)]
+        );
+
+        // The bytes after the parsed command are kept for the next call.
+        assert_eq!(
+            &fake_app_context.request.buffer[..],
+            b"*1\r\n$4\r\nPING\r\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_resp_proc_command_should_parse_hundreds_of_parameters(
+    ) -> Result<(), anyhow::Error> {
+        let fake_tcp_stream = &mut create_test_tstream();
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, fake_tcp_stream)?;
+
+        let mut request_buffer = b"*601\r\n$4\r\nMSET\r\n".to_vec();
+        for i in 0..300 {
+            let (key, value) = (format!("key{}", i), format!("value{}", i));
+            request_buffer.extend_from_slice(
+                format!(
+                    "${}\r\n{}\r\n${}\r\n{}\r\n",
+                    key.len(),
+                    key,
+                    value.len(),
+                    value
+                )
+                .as_bytes(),
+            );
+        }
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(&request_buffer);
+
+        assert!(parse_resp_proc_command(&mut fake_app_context)?);
+
+        let resp_command = fake_app_context.get_request_resp_command_ref().unwrap();
+        assert_eq!(resp_command.parameters.len(), 600);
+        assert_eq!(resp_command.parameters[599], Bytes::from("value299"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_resp_proc_command_should_skip_empty_requests() -> Result<(), anyhow::Error> {
+        let fake_tcp_stream = &mut create_test_tstream();
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, fake_tcp_stream)?;
+
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(b"*0\r\n*-1\r\n*1\r\n$4\r\nPING\r\n");
+
+        assert!(parse_resp_proc_command(&mut fake_app_context)?);
         assert_eq!(
             fake_app_context
                 .get_request_resp_command_ref()
@@ -136,6 +399,117 @@ mod tests {
                 .name,
             RespCommandNames::PING
         );
+        assert_eq!(
+            &fake_app_context.request.raw_command[..],
+            b"*1\r\n$4\r\nPING\r\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_resp_proc_command_should_reject_requests_over_the_limits(
+    ) -> Result<(), anyhow::Error> {
+        let fake_tcp_stream = &mut create_test_tstream();
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, fake_tcp_stream)?;
+        fake_app_context.request.protocol_limits = RespProtocolLimits {
+            max_bulk_len: 8,
+            max_multibulk_len: 3,
+        };
+
+        // Rejected as soon as the length is read, without waiting for the value.
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$9\r\n");
+        assert_eq!(
+            parse_resp_proc_command(&mut fake_app_context)
+                .unwrap_err()
+                .to_string(),
+            "ERR Protocol error: invalid bulk length"
+        );
+
+        fake_app_context.request.buffer.clear();
+        fake_app_context
+            .request
+            .buffer
+            .extend_from_slice(b"*4\r\n$3\r\nDEL\r\n");
+        assert_eq!(
+            parse_resp_proc_command(&mut fake_app_context)
+                .unwrap_err()
+                .to_string(),
+            "ERR Protocol error: invalid multibulk length"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_resp_proc_command_should_parse_inline_commands() -> Result<(), anyhow::Error> {
+        let fake_tcp_stream = &mut create_test_tstream();
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, fake_tcp_stream)?;
+
+        fake_app_context.request.buffer.extend_from_slice(
+            b"ping\r\n\r\n  set foo \"hello \\\"world\\\"\\x41\\n\" 'it\\'s' \"\\x+f\" \"\\xzz\"\nGET",
+        );
+
+        assert!(parse_resp_proc_command(&mut fake_app_context)?);
+        let resp_command = fake_app_context.get_request_resp_command_ref().unwrap();
+        assert_eq!(resp_command.name, RespCommandNames::PING);
+        assert!(resp_command.parameters.is_empty());
+        assert_eq!(&fake_app_context.request.raw_command[..], b"ping\r\n");
+
+        // The empty line is skipped.
+        fake_app_context.reset();
+        assert!(parse_resp_proc_command(&mut fake_app_context)?);
+        let resp_command = fake_app_context.get_request_resp_command_ref().unwrap();
+        assert_eq!(resp_command.name, RespCommandNames::SET);
+        assert_eq!(
+            resp_command.parameters,
+            vec![
+                Bytes::from("foo"),
+                Bytes::from("hello \"world\"A\n"),
+                Bytes::from("it's"),
+                Bytes::from("x+f"),
+                Bytes::from("xzz")
+            ]
+        );
+
+        // A line without its line ending is not complete yet.
+        fake_app_context.reset();
+        assert!(!parse_resp_proc_command(&mut fake_app_context)?);
+        assert_eq!(&fake_app_context.request.buffer[..], b"GET");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_resp_proc_command_should_reject_unbalanced_quotes() -> Result<(), anyhow::Error>
+    {
+        let fake_tcp_stream = &mut create_test_tstream();
+        let fake_mem_db = create_test_mem_db()?;
+
+        let mut fake_app_context = ConnectionContext::new(&fake_mem_db, fake_tcp_stream)?;
+
+        for request in [
+            &b"SET foo \"bar\r\n"[..],
+            b"SET foo \"bar\"baz\r\n",
+            b"ECHO 'a\n",
+        ] {
+            fake_app_context.request.buffer.clear();
+            fake_app_context.request.buffer.extend_from_slice(request);
+
+            assert_eq!(
+                parse_resp_proc_command(&mut fake_app_context)
+                    .unwrap_err()
+                    .to_string(),
+                "ERR Protocol error: unbalanced quotes in request"
+            );
+        }
 
         Ok(())
     }
@@ -1,162 +1,179 @@
 use super::shared::{RespDataType, RespDataTypesFirstByte};
-use crate::utils::{concat_u32, return_err, LineEndings};
+use crate::{
+    models::command_error::CommandError,
+    utils::{split_u8_slice_once, LineEndings},
+};
 
 use anyhow::Error;
-
-// E.g.: "*$4\r\nPING\r\n"
-pub(crate) fn move_resp_bulk_string(
-    command_iter: &mut std::iter::Peekable<std::iter::Enumerate<std::slice::Iter<'_, char>>>,
-    current_char: &Option<(usize, &char)>,
-) -> Result<RespDataType, Error> {
-    if current_char.is_none()
-        || current_char.unwrap().1 != &RespDataTypesFirstByte::BULK_STRINGS_CHAR
-    {
-        return return_err(
-            "Could not parse command: Command malformed, expected a bulk string.".to_owned(),
-        );
+use bytes::Bytes;
+
+/// The result of parsing a single RESP element out of a buffer that may only hold part of it.
+///
+/// `None` means the buffer ends before the element does and more bytes need to be read. <br/>
+/// `Some((value, next))` holds the parsed value and the index right after its last byte.
+pub(crate) type RespParseResult<T> = Result<Option<(T, usize)>, Error>;
+
+/// Lines holding a number (e.g. "*2\r\n" or "$4\r\n") or an inline command are never this long,
+/// so a buffer with no line ending after this many bytes is rejected instead of waiting for it
+/// forever.
+pub(crate) const PROTO_INLINE_MAX_SIZE: usize = 64 * 1024;
+
+/// Parses the bulk string that starts at `start`, which can't be longer than `max_len` bytes.
+///
+/// E.g.: "$4\r\nPING\r\n"
+pub(crate) fn parse_resp_bulk_string(
+    buffer: &[u8],
+    start: usize,
+    max_len: usize,
+) -> RespParseResult<RespDataType> {
+    if start >= buffer.len() {
+        return Ok(None);
     }
 
-    let current_char = command_iter.next();
-
-    let string_length = move_collect_data_len_number(command_iter, &current_char)?;
-
-    let mut bulk_string = String::new();
-    let mut current_char;
+    if buffer[start] != RespDataTypesFirstByte::BULK_STRINGS_BYTE {
+        return Err(CommandError::Protocol(format!(
+            "expected '{}', got '{}'",
+            RespDataTypesFirstByte::BULK_STRINGS_BYTE as char,
+            buffer[start] as char
+        ))
+        .into());
+    }
 
-    for _ in 0..string_length {
-        current_char = command_iter.next();
+    let (length_line, value_start) = match read_bounded_crlf_line(buffer, start + 1, "bulk count")?
+    {
+        None => return Ok(None),
+        Some(line) => line,
+    };
 
-        if current_char.is_none() {
-            break;
-        }
+    let string_length = parse_data_length_number(length_line, max_len)?;
+    let value_end = value_start + string_length;
 
-        bulk_string.push(*current_char.unwrap().1);
+    if buffer.len() < value_end + LineEndings::CRLF_BYTES.len() {
+        return Ok(None);
     }
 
-    move_to_crlf_end(command_iter);
+    if &buffer[value_end..value_end + LineEndings::CRLF_BYTES.len()] != LineEndings::CRLF_BYTES {
+        return Err(CommandError::Protocol(
+            "expected CRLF at the end of the bulk string".to_owned(),
+        )
+        .into());
+    }
 
-    Ok(RespDataType::BulkString {
-        size: string_length,
-        value: bulk_string,
-    })
+    Ok(Some((
+        RespDataType::BulkString {
+            value: Bytes::copy_from_slice(&buffer[value_start..value_end]),
+        },
+        value_end + LineEndings::CRLF_BYTES.len(),
+    )))
 }
 
-// E.g.: "*+OK\r\n"
-pub(crate) fn move_resp_simple_string(
-    command_iter: &mut std::iter::Peekable<std::iter::Enumerate<std::slice::Iter<'_, char>>>,
-    current_char: &Option<(usize, &char)>,
-) -> Result<RespDataType, Error> {
-    if current_char.is_none()
-        || current_char.unwrap().1 != &RespDataTypesFirstByte::SIMPLE_STRINGS_CHAR
-    {
-        return Err(Error::msg(
-            "Could not parse command: Command malformed, expected a simple string.",
-        ));
+/// Parses the simple string that starts at `start`.
+///
+/// E.g.: "+OK\r\n"
+pub(crate) fn parse_resp_simple_string(
+    buffer: &[u8],
+    start: usize,
+) -> RespParseResult<RespDataType> {
+    if start >= buffer.len() {
+        return Ok(None);
     }
 
-    let mut current_char = command_iter.next();
-    let mut simple_string = String::new();
-
-    while current_char.is_some() {
-        let (_, char) = current_char.unwrap();
-
-        if char != &LineEndings::CR_CHAR {
-            simple_string.push(*char);
-            current_char = command_iter.next();
-            continue;
-        }
-
-        current_char = command_iter.next();
-
-        match current_char {
-            None => return Err(Error::msg(
-                "Could not parse command: Command malformed, reached end without CRLF termination.",
-            )),
-
-            Some((_, char)) => {
-                if char == &LineEndings::LF_CHAR {
-                    break;
-                }
-
-                simple_string.push(*char);
-            }
-        };
+    if buffer[start] != RespDataTypesFirstByte::SIMPLE_STRINGS_BYTE {
+        return Err(CommandError::Protocol(format!(
+            "expected '{}', got '{}'",
+            RespDataTypesFirstByte::SIMPLE_STRINGS_BYTE as char,
+            buffer[start] as char
+        ))
+        .into());
     }
 
-    Ok(RespDataType::SimpleString {
-        value: simple_string,
-    })
+    Ok(read_crlf_line(buffer, start + 1).map(|(line, next)| {
+        (
+            RespDataType::SimpleString {
+                value: String::from_utf8_lossy(line).into_owned(),
+            },
+            next,
+        )
+    }))
 }
 
-/// Extracts the data length and moves the iterator to the `\n` char in `\r\n`.
-fn move_collect_data_len_number(
-    command_iter: &mut std::iter::Peekable<std::iter::Enumerate<std::slice::Iter<'_, char>>>,
-    current_char: &Option<(usize, &char)>,
-) -> Result<u32, Error> {
-    if current_char.is_none() || !current_char.unwrap().1.is_ascii_digit() {
-        return Err(Error::msg(
-            "Could not parse command: Command malformed, expected a number describing data length.",
-        ));
+/// Returns the bytes from `start` up to the next CRLF (exclusive), and the index right after
+/// that CRLF, or `None` if the buffer has no CRLF after `start` yet.
+pub(crate) fn read_crlf_line(buffer: &[u8], start: usize) -> Option<(&[u8], usize)> {
+    if start > buffer.len() {
+        return None;
     }
 
-    let mut num = get_data_length_number(current_char.unwrap().1)?;
-
-    while current_char.is_some() {
-        match command_iter.peek() {
-            None => break,
-
-            Some((_, next_char)) => {
-                if !next_char.is_ascii_digit() {
-                    break;
-                }
-
-                let current_char = command_iter.next();
+    split_u8_slice_once(&buffer[start..], LineEndings::CRLF_BYTES)
+        .map(|(line, _)| (line, start + line.len() + LineEndings::CRLF_BYTES.len()))
+}
 
-                num = concat_u32(num, get_data_length_number(current_char.unwrap().1)?).unwrap();
-            }
-        };
+/// Like [`read_crlf_line`], but fails with a protocol error once the line is longer than any
+/// valid line describing `line_description` could be.
+pub(crate) fn read_bounded_crlf_line<'a>(
+    buffer: &'a [u8],
+    start: usize,
+    line_description: &str,
+) -> Result<Option<(&'a [u8], usize)>, Error> {
+    match read_crlf_line(buffer, start) {
+        None if buffer.len().saturating_sub(start) > PROTO_INLINE_MAX_SIZE => {
+            Err(CommandError::Protocol(format!("too big {} string", line_description)).into())
+        }
+        line => Ok(line),
     }
+}
 
-    move_to_crlf_end(command_iter);
+/// Parses the number describing the length of a data type (e.g. the `4` in "$4\r\nPING\r\n").
+pub(crate) fn parse_data_length_number(raw_number: &[u8], max_len: usize) -> Result<usize, Error> {
+    let invalid_length_error = || CommandError::Protocol("invalid bulk length".to_owned());
 
-    Ok(num)
-}
+    if raw_number.is_empty() || !raw_number.iter().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid_length_error().into());
+    }
 
-fn get_data_length_number(current_char: &char) -> Result<u32, Error> {
-    match current_char.to_digit(10) {
-        None => Err(Error::msg(
-            "Could not parse command: Command malformed, invalid number describing data length.",
-        )),
-        Some(num) => Ok(num),
+    match std::str::from_utf8(raw_number)?.parse::<usize>() {
+        Ok(num) if num <= max_len => Ok(num),
+        _ => Err(invalid_length_error().into()),
     }
 }
 
-/// Moves the iterator to the `\n` char in `\r\n`.
-pub(crate) fn move_to_crlf_end<'a>(
-    command_iter: &mut std::iter::Peekable<std::iter::Enumerate<std::slice::Iter<'a, char>>>,
-) -> Option<(usize, &'a char)> {
-    let mut current_char = command_iter.next();
+#[cfg(test)]
+mod tests {
+    use super::{parse_resp_bulk_string, read_crlf_line};
 
-    while current_char.is_some() {
-        let (_, char) = current_char.unwrap();
+    #[test]
+    fn parse_resp_bulk_string_waits_for_the_whole_value() -> Result<(), anyhow::Error> {
+        let buffer = b"$5\r\nhello\r\n";
 
-        if char != &LineEndings::CR_CHAR {
-            current_char = command_iter.next();
-            continue;
+        for partial_len in 0..buffer.len() {
+            assert!(parse_resp_bulk_string(&buffer[..partial_len], 0, usize::MAX)?.is_none());
         }
 
-        current_char = command_iter.next();
+        let (value, next) = parse_resp_bulk_string(buffer, 0, usize::MAX)?.unwrap();
+        assert_eq!(value.get_value_string(), "hello");
+        assert_eq!(next, buffer.len());
 
-        match current_char {
-            None => return current_char,
+        Ok(())
+    }
 
-            Some((_, char)) => {
-                if char == &LineEndings::LF_CHAR {
-                    break;
-                }
-            }
-        };
+    #[test]
+    fn parse_resp_bulk_string_rejects_missing_crlf() {
+        assert!(parse_resp_bulk_string(b"$5\r\nhello!!", 0, usize::MAX).is_err());
     }
 
-    current_char
+    #[test]
+    fn parse_resp_bulk_string_rejects_lengths_over_the_limit() {
+        assert!(parse_resp_bulk_string(b"$5\r\n", 0, 4).is_err());
+        assert!(parse_resp_bulk_string(b"$-1\r\n", 0, 4).is_err());
+        assert!(parse_resp_bulk_string(b"$99999999999999999999999\r\n", 0, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn read_crlf_line_passes() {
+        assert_eq!(
+            read_crlf_line(b"*2\r\n$4\r\n", 0),
+            Some((b"*2" as &[u8], 4))
+        );
+        assert_eq!(read_crlf_line(b"*2\r", 0), None);
+    }
 }
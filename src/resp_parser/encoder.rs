@@ -0,0 +1,286 @@
+use super::shared::{RespDataTypesFirstByte, RespProtocolVersion, RespValue};
+use crate::utils::LineEndings;
+
+/// Serializes a [`RespValue`] into its wire format for `protocol_version`. <br/>
+/// RESP3 only types are serialized as their closest RESP2 type to RESP2 connections, and all
+/// nulls are serialized as the RESP3 null to RESP3 connections.
+///
+/// E.g.: <br/>
+/// `RespValue::SimpleString("OK")` -> "+OK\r\n" <br/>
+/// `RespValue::Array(vec![RespValue::Integer(1), RespValue::NullBulkString])` -> "*2\r\n:1\r\n$-1\r\n"
+/// <br/>
+/// `RespValue::Boolean(true)` -> "#t\r\n" in RESP3, ":1\r\n" in RESP2
+pub(crate) fn encode_resp_value(
+    value: &RespValue,
+    protocol_version: RespProtocolVersion,
+) -> Vec<u8> {
+    let mut encoded = Vec::<u8>::new();
+    encode_resp_value_into(value, protocol_version, &mut encoded);
+
+    encoded
+}
+
+fn encode_resp_value_into(
+    value: &RespValue,
+    protocol_version: RespProtocolVersion,
+    target: &mut Vec<u8>,
+) {
+    let is_resp3 = protocol_version == RespProtocolVersion::Resp3;
+
+    match value {
+        RespValue::SimpleString(message) => {
+            encode_line(RespDataTypesFirstByte::SIMPLE_STRINGS_BYTE, message, target)
+        }
+        RespValue::Error(message) => {
+            encode_line(RespDataTypesFirstByte::ERRORS_BYTE, message, target)
+        }
+        RespValue::Integer(number) => encode_line(
+            RespDataTypesFirstByte::INTEGERS_BYTE,
+            &number.to_string(),
+            target,
+        ),
+        RespValue::BulkString(bytes) => encode_bulk_string(bytes, target),
+        RespValue::NullBulkString | RespValue::NullArray | RespValue::Null if is_resp3 => {
+            encode_line(RespDataTypesFirstByte::NULLS_BYTE, "", target)
+        }
+        RespValue::NullBulkString | RespValue::Null => {
+            encode_line(RespDataTypesFirstByte::BULK_STRINGS_BYTE, "-1", target)
+        }
+        RespValue::NullArray => encode_line(RespDataTypesFirstByte::ARRAYS_BYTE, "-1", target),
+        RespValue::Boolean(boolean) if is_resp3 => encode_line(
+            RespDataTypesFirstByte::BOOLEANS_BYTE,
+            if *boolean { "t" } else { "f" },
+            target,
+        ),
+        RespValue::Boolean(boolean) => encode_line(
+            RespDataTypesFirstByte::INTEGERS_BYTE,
+            if *boolean { "1" } else { "0" },
+            target,
+        ),
+        RespValue::Double(number) if is_resp3 => encode_line(
+            RespDataTypesFirstByte::DOUBLES_BYTE,
+            &format_double(*number),
+            target,
+        ),
+        RespValue::Double(number) => encode_bulk_string(format_double(*number).as_bytes(), target),
+        RespValue::Map(entries) => {
+            encode_line(
+                if is_resp3 {
+                    RespDataTypesFirstByte::MAPS_BYTE
+                } else {
+                    RespDataTypesFirstByte::ARRAYS_BYTE
+                },
+                &(if is_resp3 {
+                    entries.len()
+                } else {
+                    entries.len() * 2
+                })
+                .to_string(),
+                target,
+            );
+
+            for (key, value) in entries {
+                encode_resp_value_into(key, protocol_version, target);
+                encode_resp_value_into(value, protocol_version, target);
+            }
+        }
+        RespValue::Array(items) => encode_aggregate(
+            RespDataTypesFirstByte::ARRAYS_BYTE,
+            items,
+            protocol_version,
+            target,
+        ),
+        RespValue::Set(items) => encode_aggregate(
+            if is_resp3 {
+                RespDataTypesFirstByte::SETS_BYTE
+            } else {
+                RespDataTypesFirstByte::ARRAYS_BYTE
+            },
+            items,
+            protocol_version,
+            target,
+        ),
+        RespValue::Push(items) => encode_aggregate(
+            if is_resp3 {
+                RespDataTypesFirstByte::PUSHES_BYTE
+            } else {
+                RespDataTypesFirstByte::ARRAYS_BYTE
+            },
+            items,
+            protocol_version,
+            target,
+        ),
+    }
+}
+
+fn encode_bulk_string(bytes: &[u8], target: &mut Vec<u8>) {
+    encode_line(
+        RespDataTypesFirstByte::BULK_STRINGS_BYTE,
+        &bytes.len().to_string(),
+        target,
+    );
+    target.extend_from_slice(bytes);
+    target.extend_from_slice(LineEndings::CRLF_BYTES);
+}
+
+/// Writes the number of `items` after `first_byte`, then each item.
+fn encode_aggregate(
+    first_byte: u8,
+    items: &[RespValue],
+    protocol_version: RespProtocolVersion,
+    target: &mut Vec<u8>,
+) {
+    encode_line(first_byte, &items.len().to_string(), target);
+
+    for item in items {
+        encode_resp_value_into(item, protocol_version, target);
+    }
+}
+
+/// Formats a double the way Redis replies it: the shortest representation that parses back to
+/// the same number, in exponent notation for very large or small numbers, and "inf", "-inf" and
+/// "nan" for the special values.
+fn format_double(number: f64) -> String {
+    if number.is_nan() {
+        return "nan".to_owned();
+    }
+
+    if number.is_infinite() {
+        return if number > 0.0 { "inf" } else { "-inf" }.to_owned();
+    }
+
+    let magnitude = number.abs();
+
+    if magnitude != 0.0 && !(1e-4..1e17).contains(&magnitude) {
+        // Rust writes "1e21" and "1e-7", Redis "1e+21" and "1e-07".
+        let formatted = format!("{:e}", number);
+        let (mantissa, exponent) = formatted.split_once('e').unwrap();
+        let (sign, digits) = match exponent.strip_prefix('-') {
+            None => ("+", exponent),
+            Some(digits) => ("-", digits),
+        };
+
+        return format!("{}e{}{:0>2}", mantissa, sign, digits);
+    }
+
+    number.to_string()
+}
+
+/// Writes `first_byte`, then `line`, then CRLF.
+fn encode_line(first_byte: u8, line: &str, target: &mut Vec<u8>) {
+    target.push(first_byte);
+    target.extend_from_slice(line.as_bytes());
+    target.extend_from_slice(LineEndings::CRLF_BYTES);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode_resp_value;
+    use crate::resp_parser::shared::{RespProtocolVersion, RespValue};
+
+    use bytes::Bytes;
+
+    #[test]
+    fn encode_resp_value_encodes_scalars() {
+        assert_eq!(
+            encode_resp_value(&RespValue::ok(), RespProtocolVersion::Resp2),
+            b"+OK\r\n"
+        );
+        assert_eq!(
+            encode_resp_value(
+                &RespValue::Error("ERR oops".to_owned()),
+                RespProtocolVersion::Resp2
+            ),
+            b"-ERR oops\r\n"
+        );
+        assert_eq!(
+            encode_resp_value(&RespValue::Integer(-42), RespProtocolVersion::Resp2),
+            b":-42\r\n"
+        );
+        assert_eq!(
+            encode_resp_value(
+                &RespValue::BulkString(Bytes::from_static(b"h\xffllo")),
+                RespProtocolVersion::Resp2
+            ),
+            b"$5\r\nh\xffllo\r\n"
+        );
+        assert_eq!(
+            encode_resp_value(&RespValue::NullBulkString, RespProtocolVersion::Resp2),
+            b"$-1\r\n"
+        );
+        assert_eq!(
+            encode_resp_value(&RespValue::NullArray, RespProtocolVersion::Resp2),
+            b"*-1\r\n"
+        );
+    }
+
+    #[test]
+    fn encode_resp_value_encodes_nested_arrays() {
+        let value = RespValue::Array(vec![
+            RespValue::Integer(1),
+            RespValue::Array(vec![
+                RespValue::BulkString(Bytes::from_static(b"a")),
+                RespValue::NullBulkString,
+            ]),
+            RespValue::Array(vec![]),
+        ]);
+
+        assert_eq!(
+            encode_resp_value(&value, RespProtocolVersion::Resp2),
+            b"*3\r\n:1\r\n*2\r\n$1\r\na\r\n$-1\r\n*0\r\n"
+        );
+    }
+
+    #[test]
+    fn encode_resp_value_encodes_resp3_types() {
+        let value = RespValue::Map(vec![
+            (
+                RespValue::BulkString(Bytes::from_static(b"set")),
+                RespValue::Set(vec![RespValue::Integer(1)]),
+            ),
+            (
+                RespValue::BulkString(Bytes::from_static(b"flags")),
+                RespValue::Array(vec![
+                    RespValue::Boolean(true),
+                    RespValue::Double(1.5),
+                    RespValue::NullBulkString,
+                    RespValue::Null,
+                ]),
+            ),
+        ]);
+
+        assert_eq!(
+            encode_resp_value(&value, RespProtocolVersion::Resp3),
+            b"%2\r\n$3\r\nset\r\n~1\r\n:1\r\n$5\r\nflags\r\n*4\r\n#t\r\n,1.5\r\n_\r\n_\r\n"
+        );
+        assert_eq!(
+            encode_resp_value(&value, RespProtocolVersion::Resp2),
+            b"*4\r\n$3\r\nset\r\n*1\r\n:1\r\n$5\r\nflags\r\n*4\r\n:1\r\n$3\r\n1.5\r\n$-1\r\n$-1\r\n"
+        );
+        assert_eq!(
+            encode_resp_value(
+                &RespValue::Push(vec![RespValue::SimpleString("a".to_owned())]),
+                RespProtocolVersion::Resp3
+            ),
+            b">1\r\n+a\r\n"
+        );
+    }
+
+    #[test]
+    fn encode_resp_value_formats_doubles_like_redis() {
+        for (number, expected) in [
+            (3.0, ",3\r\n"),
+            (-0.25, ",-0.25\r\n"),
+            (1e21, ",1e+21\r\n"),
+            (1.5e-7, ",1.5e-07\r\n"),
+            (f64::INFINITY, ",inf\r\n"),
+            (f64::NEG_INFINITY, ",-inf\r\n"),
+            (f64::NAN, ",nan\r\n"),
+        ] {
+            assert_eq!(
+                encode_resp_value(&RespValue::Double(number), RespProtocolVersion::Resp3),
+                expected.as_bytes()
+            );
+        }
+    }
+}
@@ -2,7 +2,10 @@ mod commands;
 pub(crate) use commands::parse_resp_proc_command;
 
 mod responses;
-pub(crate) use responses::parse_redis_resp_proc_response;
+pub(crate) use responses::{parse_redis_resp_proc_response, parse_resp_rdb_payload};
 
-mod data_types;
+mod encoder;
+pub(crate) use encoder::encode_resp_value;
+
+pub(crate) mod data_types;
 pub(crate) mod shared;
@@ -1,88 +1,327 @@
 use super::{
-    data_types::{move_resp_bulk_string, move_resp_simple_string},
-    shared::{RespDataType, RespDataTypesFirstByte},
-};
-use crate::{
-    models::connection_context::InternalRequest, utils::u8_slice_into_char_slice,
-    TCP_RESPONSE_BUFFER_SIZE,
+    data_types::{
+        parse_data_length_number, parse_resp_bulk_string, parse_resp_simple_string,
+        read_bounded_crlf_line, RespParseResult,
+    },
+    shared::{RespDataTypesFirstByte, RespProtocolLimits, RespValue},
 };
+use crate::models::command_error::CommandError;
 
 use anyhow::Error;
+use bytes::Bytes;
 
+/// Parses the first complete reply in `buffer`, e.g. one sent by a master to a replica. <br/>
+/// Both RESP2 and RESP3 replies are understood, and nulls are parsed into the null of their type
+/// (`$-1` into [`RespValue::NullBulkString`], `*-1` into [`RespValue::NullArray`] and `_` into
+/// [`RespValue::Null`]).
+///
+/// Like when parsing commands, `None` means the buffer does not hold the whole reply yet, and
+/// replies over `limits` are rejected with a protocol error.
+///
+/// Input examples: <br/>
+/// "+OK\r\n" <br/>
+/// "-ERR unknown command\r\n" <br/>
+/// "*2\r\n:1\r\n$-1\r\n" <br/>
 pub(crate) fn parse_redis_resp_proc_response(
-    request: &InternalRequest,
-) -> Result<RespDataType, Error> {
-    if request.buffer.starts_with(&[0]) {
-        return Err(Error::msg("Could not parse response: Command is empty."));
+    buffer: &[u8],
+    limits: &RespProtocolLimits,
+) -> RespParseResult<RespValue> {
+    parse_resp_reply(buffer, 0, limits)
+}
+
+/// Parses the RDB file a master sends after replying FULLRESYNC, which is sent like a bulk string
+/// but without the trailing CRLF, since it is streamed rather than built in memory first.
+///
+/// E.g.: "$88\r\nREDIS0011..."
+pub(crate) fn parse_resp_rdb_payload(buffer: &[u8], max_len: usize) -> RespParseResult<Bytes> {
+    if buffer.is_empty() {
+        return Ok(None);
     }
 
-    let mut raw_command_chars = [0 as char; TCP_RESPONSE_BUFFER_SIZE];
-    u8_slice_into_char_slice(
-        &request.buffer[0..request.byte_count],
-        &mut raw_command_chars,
-    );
-    let raw_command_chars = raw_command_chars;
+    if buffer[0] != RespDataTypesFirstByte::BULK_STRINGS_BYTE {
+        return Err(CommandError::Protocol(format!(
+            "expected '{}', got '{}'",
+            RespDataTypesFirstByte::BULK_STRINGS_BYTE as char,
+            buffer[0] as char
+        ))
+        .into());
+    }
 
-    // TODO: Refactor this to use the raw byte slice instead of the iterator.
-    let mut command_body_iter = raw_command_chars[..request.byte_count]
-        .iter()
-        .enumerate()
-        .peekable();
+    let (length_line, payload_start) = match read_bounded_crlf_line(buffer, 1, "bulk count")? {
+        None => return Ok(None),
+        Some(line) => line,
+    };
 
-    let current_char: Option<(usize, &char)> = command_body_iter.next();
+    let payload_end = payload_start + parse_data_length_number(length_line, max_len)?;
+
+    if buffer.len() < payload_end {
+        return Ok(None);
+    }
+
+    Ok(Some((
+        Bytes::copy_from_slice(&buffer[payload_start..payload_end]),
+        payload_end,
+    )))
+}
 
-    Ok(match current_char.unwrap().1 {
-        &RespDataTypesFirstByte::BULK_STRINGS_CHAR => {
-            move_resp_bulk_string(&mut command_body_iter, &current_char)?
+fn parse_resp_reply(
+    buffer: &[u8],
+    start: usize,
+    limits: &RespProtocolLimits,
+) -> RespParseResult<RespValue> {
+    let first_byte = match buffer.get(start) {
+        None => return Ok(None),
+        Some(first_byte) => *first_byte,
+    };
+
+    match first_byte {
+        RespDataTypesFirstByte::SIMPLE_STRINGS_BYTE => Ok(parse_resp_simple_string(buffer, start)?
+            .map(|(value, next)| (RespValue::SimpleString(value.get_value_string()), next))),
+        RespDataTypesFirstByte::BULK_STRINGS_BYTE => {
+            match read_bounded_crlf_line(buffer, start + 1, "bulk count")? {
+                None => Ok(None),
+                Some((b"-1", next)) => Ok(Some((RespValue::NullBulkString, next))),
+                Some(_) => Ok(parse_resp_bulk_string(buffer, start, limits.max_bulk_len)?
+                    .map(|(value, next)| (RespValue::BulkString(value.get_value_bytes()), next))),
+            }
         }
-        &RespDataTypesFirstByte::SIMPLE_STRINGS_CHAR => {
-            move_resp_simple_string(&mut command_body_iter, &current_char)?
+        RespDataTypesFirstByte::ARRAYS_BYTE
+        | RespDataTypesFirstByte::SETS_BYTE
+        | RespDataTypesFirstByte::PUSHES_BYTE => {
+            let (count_line, next) = match read_bounded_crlf_line(buffer, start + 1, "mbulk count")?
+            {
+                None => return Ok(None),
+                Some(line) => line,
+            };
+
+            if first_byte == RespDataTypesFirstByte::ARRAYS_BYTE && count_line == b"-1" {
+                return Ok(Some((RespValue::NullArray, next)));
+            }
+
+            let count = parse_aggregate_count(count_line, limits)?;
+
+            Ok(
+                parse_resp_replies(buffer, next, count, limits)?.map(|(items, next)| {
+                    (
+                        match first_byte {
+                            RespDataTypesFirstByte::SETS_BYTE => RespValue::Set(items),
+                            RespDataTypesFirstByte::PUSHES_BYTE => RespValue::Push(items),
+                            _ => RespValue::Array(items),
+                        },
+                        next,
+                    )
+                }),
+            )
         }
+        RespDataTypesFirstByte::MAPS_BYTE => {
+            let (count_line, next) = match read_bounded_crlf_line(buffer, start + 1, "mbulk count")?
+            {
+                None => return Ok(None),
+                Some(line) => line,
+            };
+
+            let count = parse_aggregate_count(count_line, limits)?;
+
+            Ok(
+                parse_resp_replies(buffer, next, count.saturating_mul(2), limits)?.map(
+                    |(items, next)| {
+                        let mut items = items.into_iter();
+                        let mut entries = Vec::with_capacity(count);
+
+                        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                            entries.push((key, value));
+                        }
 
+                        (RespValue::Map(entries), next)
+                    },
+                ),
+            )
+        }
         _ => {
-            return Err(Error::msg(
-                "Could not parse response: Unknown or not implemented data type.",
-            ))
+            let (line, next) = match read_bounded_crlf_line(buffer, start + 1, "reply")? {
+                None => return Ok(None),
+                Some(line) => line,
+            };
+
+            parse_resp_line_reply(first_byte, line).map(|value| Some((value, next)))
+        }
+    }
+}
+
+/// Parses the replies that fit in a single line, i.e. all but strings and aggregates.
+fn parse_resp_line_reply(first_byte: u8, line: &[u8]) -> Result<RespValue, Error> {
+    let invalid_reply_error = |description: &str| {
+        CommandError::Protocol(format!(
+            "invalid {} reply '{}'",
+            description,
+            String::from_utf8_lossy(line)
+        ))
+    };
+
+    match first_byte {
+        RespDataTypesFirstByte::ERRORS_BYTE => {
+            Ok(RespValue::Error(String::from_utf8_lossy(line).into_owned()))
+        }
+        RespDataTypesFirstByte::INTEGERS_BYTE => std::str::from_utf8(line)
+            .ok()
+            .and_then(|number| number.parse::<i64>().ok())
+            .map(RespValue::Integer)
+            .ok_or_else(|| invalid_reply_error("integer").into()),
+        RespDataTypesFirstByte::NULLS_BYTE if line.is_empty() => Ok(RespValue::Null),
+        RespDataTypesFirstByte::BOOLEANS_BYTE if line == b"t" => Ok(RespValue::Boolean(true)),
+        RespDataTypesFirstByte::BOOLEANS_BYTE if line == b"f" => Ok(RespValue::Boolean(false)),
+        RespDataTypesFirstByte::DOUBLES_BYTE => std::str::from_utf8(line)
+            .ok()
+            .and_then(|number| number.parse::<f64>().ok())
+            .map(RespValue::Double)
+            .ok_or_else(|| invalid_reply_error("double").into()),
+        RespDataTypesFirstByte::NULLS_BYTE => Err(invalid_reply_error("null").into()),
+        RespDataTypesFirstByte::BOOLEANS_BYTE => Err(invalid_reply_error("boolean").into()),
+        _ => Err(
+            CommandError::Protocol(format!("unexpected reply type '{}'", first_byte as char))
+                .into(),
+        ),
+    }
+}
+
+/// Parses the `count` replies of an aggregate, starting at `start`.
+fn parse_resp_replies(
+    buffer: &[u8],
+    start: usize,
+    count: usize,
+    limits: &RespProtocolLimits,
+) -> RespParseResult<Vec<RespValue>> {
+    // The count is not trusted to allocate upfront, since it may be large.
+    let mut items = Vec::with_capacity(count.min(1024));
+    let mut position = start;
+
+    for _ in 0..count {
+        match parse_resp_reply(buffer, position, limits)? {
+            None => return Ok(None),
+            Some((item, next)) => {
+                items.push(item);
+                position = next;
+            }
         }
-    })
+    }
+
+    Ok(Some((items, position)))
+}
+
+fn parse_aggregate_count(count_line: &[u8], limits: &RespProtocolLimits) -> Result<usize, Error> {
+    std::str::from_utf8(count_line)
+        .ok()
+        .and_then(|count| count.parse::<usize>().ok())
+        .filter(|count| *count <= limits.max_multibulk_len)
+        .ok_or_else(|| CommandError::Protocol("invalid multibulk length".to_owned()).into())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{
-        models::connection_context::InternalRequest,
-        resp_parser::{parse_redis_resp_proc_response, shared::RespCommandResponseNames},
-        utils::copy_to_array_until,
+    use super::parse_resp_rdb_payload;
+    use crate::resp_parser::{
+        parse_redis_resp_proc_response,
+        shared::{RespCommandResponseNames, RespProtocolLimits, RespValue},
     };
 
-    #[tokio::test]
-    async fn parse_redis_resp_proc_response_should_parse_simple_strings(
-    ) -> Result<(), anyhow::Error> {
-        let mut request_buffer = [0; 1024];
-        copy_to_array_until(&mut request_buffer, b"+OK\r\n\0", 0, |byte, _, _| byte == 0);
+    use bytes::Bytes;
+
+    #[test]
+    fn parse_redis_resp_proc_response_should_parse_simple_strings() -> Result<(), anyhow::Error> {
+        let limits = RespProtocolLimits::default();
+
+        assert_eq!(
+            parse_redis_resp_proc_response(b"+OK\r\n", &limits)?,
+            Some((
+                RespValue::SimpleString(RespCommandResponseNames::OK.to_owned()),
+                5
+            ))
+        );
+        assert_eq!(
+            parse_redis_resp_proc_response(b"+PONG\r\n+OK", &limits)?,
+            Some((
+                RespValue::SimpleString(RespCommandResponseNames::PONG.to_owned()),
+                7
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_redis_resp_proc_response_should_parse_every_reply_type() -> Result<(), anyhow::Error> {
+        let limits = RespProtocolLimits::default();
+        let buffer = b"*7\r\n-ERR oops\r\n:-12\r\n$3\r\nfoo\r\n$-1\r\n*-1\r\n*1\r\n*0\r\n_\r\n";
+
+        assert_eq!(
+            parse_redis_resp_proc_response(buffer, &limits)?,
+            Some((
+                RespValue::Array(vec![
+                    RespValue::Error("ERR oops".to_owned()),
+                    RespValue::Integer(-12),
+                    RespValue::BulkString(Bytes::from("foo")),
+                    RespValue::NullBulkString,
+                    RespValue::NullArray,
+                    RespValue::Array(vec![RespValue::Array(vec![])]),
+                    RespValue::Null,
+                ]),
+                buffer.len()
+            ))
+        );
 
+        let buffer = b"%2\r\n+a\r\n#t\r\n+b\r\n~1\r\n,1.5\r\n";
         assert_eq!(
-            parse_redis_resp_proc_response(&InternalRequest {
-                buffer: request_buffer,
-                byte_count: 50,
-            })?
-            .get_value_string(),
-            RespCommandResponseNames::OK
+            parse_redis_resp_proc_response(buffer, &limits)?,
+            Some((
+                RespValue::Map(vec![
+                    (
+                        RespValue::SimpleString("a".to_owned()),
+                        RespValue::Boolean(true)
+                    ),
+                    (
+                        RespValue::SimpleString("b".to_owned()),
+                        RespValue::Set(vec![RespValue::Double(1.5)])
+                    ),
+                ]),
+                buffer.len()
+            ))
         );
 
-        let mut request_buffer = [0; 1024];
-        copy_to_array_until(&mut request_buffer, b"+PONG\r\n\0", 0, |byte, _, _| {
-            byte == 0
-        });
+        Ok(())
+    }
+
+    #[test]
+    fn parse_redis_resp_proc_response_should_wait_for_the_whole_reply() -> Result<(), anyhow::Error>
+    {
+        let limits = RespProtocolLimits::default();
+        let buffer = b"*2\r\n:1\r\n$5\r\nhello\r\n";
+
+        for partial_len in 0..buffer.len() {
+            assert!(parse_redis_resp_proc_response(&buffer[..partial_len], &limits)?.is_none());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_redis_resp_proc_response_should_reject_invalid_replies() {
+        let limits = RespProtocolLimits::default();
+
+        for buffer in [&b"?what\r\n"[..], b":abc\r\n", b"*x\r\n", b"#x\r\n"] {
+            assert!(parse_redis_resp_proc_response(buffer, &limits).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_resp_rdb_payload_should_not_expect_a_trailing_crlf() -> Result<(), anyhow::Error> {
+        let buffer = b"$5\r\nREDIS*1\r\n$4\r\nPING\r\n";
 
         assert_eq!(
-            parse_redis_resp_proc_response(&InternalRequest {
-                buffer: request_buffer,
-                byte_count: 50,
-            })?
-            .get_value_string(),
-            RespCommandResponseNames::PONG
+            parse_resp_rdb_payload(buffer, usize::MAX)?,
+            Some((Bytes::from("REDIS"), 9))
         );
+        assert!(parse_resp_rdb_payload(&buffer[..8], usize::MAX)?.is_none());
+        assert!(parse_resp_rdb_payload(b"+OK\r\n", usize::MAX).is_err());
 
         Ok(())
     }
@@ -1,13 +1,38 @@
+use super::encode_resp_value;
+use crate::{
+    models::command_error::CommandError,
+    utils::{parse_strict_f64, parse_strict_i64},
+};
+
+use anyhow::Error;
+use bytes::Bytes;
+
 pub struct RespDataTypesFirstByte {}
 
 impl RespDataTypesFirstByte {
-    pub const ARRAYS_STR: &'static str = "*";
-    pub const ARRAYS_BYTE: &'static [u8] = b"*";
+    pub const ARRAYS_BYTE: u8 = b'*';
 
-    pub const BULK_STRINGS_CHAR: char = '$';
+    pub const BULK_STRINGS_BYTE: u8 = b'$';
 
-    pub const SIMPLE_STRINGS_CHAR: char = '+';
     pub const SIMPLE_STRINGS_BYTE: u8 = b'+';
+
+    pub const ERRORS_BYTE: u8 = b'-';
+
+    pub const INTEGERS_BYTE: u8 = b':';
+
+    // RESP3 only.
+
+    pub const NULLS_BYTE: u8 = b'_';
+
+    pub const BOOLEANS_BYTE: u8 = b'#';
+
+    pub const DOUBLES_BYTE: u8 = b',';
+
+    pub const MAPS_BYTE: u8 = b'%';
+
+    pub const SETS_BYTE: u8 = b'~';
+
+    pub const PUSHES_BYTE: u8 = b'>';
 }
 
 pub struct RespCommandNames {}
@@ -20,19 +45,168 @@ impl RespCommandNames {
     pub const INFO: &'static str = "INFO";
     pub const GET: &'static str = "GET";
     pub const SET: &'static str = "SET";
+    pub const DEL: &'static str = "DEL";
+    pub const MGET: &'static str = "MGET";
+    pub const MSET: &'static str = "MSET";
+    pub const MSETNX: &'static str = "MSETNX";
+    pub const GETSET: &'static str = "GETSET";
+    pub const GETDEL: &'static str = "GETDEL";
+    pub const GETEX: &'static str = "GETEX";
+    pub const APPEND: &'static str = "APPEND";
+    pub const STRLEN: &'static str = "STRLEN";
+    pub const GETRANGE: &'static str = "GETRANGE";
+    pub const SETRANGE: &'static str = "SETRANGE";
+    pub const LCS: &'static str = "LCS";
+    pub const HELLO: &'static str = "HELLO";
+    pub const INCR: &'static str = "INCR";
+    pub const DECR: &'static str = "DECR";
+    pub const INCRBY: &'static str = "INCRBY";
+    pub const DECRBY: &'static str = "DECRBY";
+    pub const INCRBYFLOAT: &'static str = "INCRBYFLOAT";
+    pub const EXPIRE: &'static str = "EXPIRE";
+    pub const PEXPIRE: &'static str = "PEXPIRE";
+    pub const EXPIREAT: &'static str = "EXPIREAT";
+    pub const PEXPIREAT: &'static str = "PEXPIREAT";
+    pub const TTL: &'static str = "TTL";
+    pub const PTTL: &'static str = "PTTL";
+    pub const EXPIRETIME: &'static str = "EXPIRETIME";
+    pub const PEXPIRETIME: &'static str = "PEXPIRETIME";
+    pub const PERSIST: &'static str = "PERSIST";
+    pub const UNLINK: &'static str = "UNLINK";
+    pub const EXISTS: &'static str = "EXISTS";
+    pub const TYPE: &'static str = "TYPE";
+    pub const RENAME: &'static str = "RENAME";
+    pub const RENAMENX: &'static str = "RENAMENX";
+    pub const COPY: &'static str = "COPY";
+    pub const TOUCH: &'static str = "TOUCH";
+    pub const KEYS: &'static str = "KEYS";
+    pub const SCAN: &'static str = "SCAN";
+    pub const OBJECT: &'static str = "OBJECT";
+    pub const SELECT: &'static str = "SELECT";
+    pub const SWAPDB: &'static str = "SWAPDB";
+    pub const MOVE: &'static str = "MOVE";
+    pub const DBSIZE: &'static str = "DBSIZE";
+    pub const FLUSHDB: &'static str = "FLUSHDB";
+    pub const FLUSHALL: &'static str = "FLUSHALL";
+    pub const LPUSH: &'static str = "LPUSH";
+    pub const RPUSH: &'static str = "RPUSH";
+    pub const LPOP: &'static str = "LPOP";
+    pub const RPOP: &'static str = "RPOP";
+    pub const LRANGE: &'static str = "LRANGE";
+    pub const LLEN: &'static str = "LLEN";
+    pub const LINDEX: &'static str = "LINDEX";
+    pub const LSET: &'static str = "LSET";
+    pub const LREM: &'static str = "LREM";
+    pub const LTRIM: &'static str = "LTRIM";
+    pub const LINSERT: &'static str = "LINSERT";
+    pub const LPOS: &'static str = "LPOS";
+    pub const LMOVE: &'static str = "LMOVE";
+    pub const BLPOP: &'static str = "BLPOP";
+    pub const BRPOP: &'static str = "BRPOP";
+    pub const BLMOVE: &'static str = "BLMOVE";
+    pub const BLMPOP: &'static str = "BLMPOP";
+    pub const HSET: &'static str = "HSET";
+    pub const HGET: &'static str = "HGET";
+    pub const HMGET: &'static str = "HMGET";
+    pub const HGETALL: &'static str = "HGETALL";
+    pub const HDEL: &'static str = "HDEL";
+    pub const HEXISTS: &'static str = "HEXISTS";
+    pub const HINCRBY: &'static str = "HINCRBY";
+    pub const HINCRBYFLOAT: &'static str = "HINCRBYFLOAT";
+    pub const HKEYS: &'static str = "HKEYS";
+    pub const HVALS: &'static str = "HVALS";
+    pub const HLEN: &'static str = "HLEN";
+    pub const HSCAN: &'static str = "HSCAN";
+    pub const HRANDFIELD: &'static str = "HRANDFIELD";
+    pub const HEXPIRE: &'static str = "HEXPIRE";
+    pub const HPEXPIRE: &'static str = "HPEXPIRE";
+    pub const HEXPIREAT: &'static str = "HEXPIREAT";
+    pub const HPEXPIREAT: &'static str = "HPEXPIREAT";
+    pub const HTTL: &'static str = "HTTL";
+    pub const HPTTL: &'static str = "HPTTL";
+    pub const HEXPIRETIME: &'static str = "HEXPIRETIME";
+    pub const HPEXPIRETIME: &'static str = "HPEXPIRETIME";
+    pub const HPERSIST: &'static str = "HPERSIST";
+    pub const SADD: &'static str = "SADD";
+    pub const SREM: &'static str = "SREM";
+    pub const SMEMBERS: &'static str = "SMEMBERS";
+    pub const SISMEMBER: &'static str = "SISMEMBER";
+    pub const SMISMEMBER: &'static str = "SMISMEMBER";
+    pub const SCARD: &'static str = "SCARD";
+    pub const SPOP: &'static str = "SPOP";
+    pub const SRANDMEMBER: &'static str = "SRANDMEMBER";
+    pub const SINTER: &'static str = "SINTER";
+    pub const SINTERCARD: &'static str = "SINTERCARD";
+    pub const SINTERSTORE: &'static str = "SINTERSTORE";
+    pub const SUNION: &'static str = "SUNION";
+    pub const SUNIONSTORE: &'static str = "SUNIONSTORE";
+    pub const SDIFF: &'static str = "SDIFF";
+    pub const SDIFFSTORE: &'static str = "SDIFFSTORE";
+    pub const SMOVE: &'static str = "SMOVE";
+    pub const SSCAN: &'static str = "SSCAN";
+    pub const ZADD: &'static str = "ZADD";
+    pub const ZCARD: &'static str = "ZCARD";
+    pub const ZSCORE: &'static str = "ZSCORE";
+    pub const ZINCRBY: &'static str = "ZINCRBY";
+    pub const ZREM: &'static str = "ZREM";
+    pub const ZRANK: &'static str = "ZRANK";
+    pub const ZREVRANK: &'static str = "ZREVRANK";
+    pub const ZCOUNT: &'static str = "ZCOUNT";
+    pub const ZRANGE: &'static str = "ZRANGE";
+    pub const ZPOPMIN: &'static str = "ZPOPMIN";
+    pub const ZPOPMAX: &'static str = "ZPOPMAX";
+    pub const ZUNIONSTORE: &'static str = "ZUNIONSTORE";
+    pub const ZINTERSTORE: &'static str = "ZINTERSTORE";
+    pub const ZDIFFSTORE: &'static str = "ZDIFFSTORE";
+}
+
+/// The RESP version a connection talks, negotiated with `HELLO`. <br/>
+/// Connections start with RESP2, in which the RESP3 only types are replied as their closest RESP2
+/// type (e.g. maps as flat arrays).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RespProtocolVersion {
+    #[default]
+    Resp2,
+    Resp3,
 }
 
-#[derive(Debug, PartialEq)]
-pub enum RespCommandType {
-    Read,
-    Write,
+impl RespProtocolVersion {
+    pub fn from_number(number: i64) -> Option<Self> {
+        match number {
+            2 => Some(RespProtocolVersion::Resp2),
+            3 => Some(RespProtocolVersion::Resp3),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> i64 {
+        match self {
+            RespProtocolVersion::Resp2 => 2,
+            RespProtocolVersion::Resp3 => 3,
+        }
+    }
 }
 
-impl RespCommandType {
-    pub fn from_command_name(command_name: &str) -> RespCommandType {
-        match command_name {
-            RespCommandNames::SET => RespCommandType::Write,
-            _ => RespCommandType::Read,
+/// Sanity limits for the requests sent by clients, so that a malformed or malicious request
+/// can't make the server buffer an unbounded amount of data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RespProtocolLimits {
+    /// Maximum size in bytes of a single bulk string, like Redis' `proto-max-bulk-len`.
+    pub max_bulk_len: usize,
+    /// Maximum number of bulk strings in a single request, counting the command name.
+    pub max_multibulk_len: usize,
+}
+
+impl RespProtocolLimits {
+    pub const DEFAULT_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+    pub const DEFAULT_MAX_MULTIBULK_LEN: usize = i32::MAX as usize;
+}
+
+impl Default for RespProtocolLimits {
+    fn default() -> Self {
+        RespProtocolLimits {
+            max_bulk_len: Self::DEFAULT_MAX_BULK_LEN,
+            max_multibulk_len: Self::DEFAULT_MAX_MULTIBULK_LEN,
         }
     }
 }
@@ -47,7 +221,157 @@ impl RespCommandResponseNames {
 pub struct RespCommandSetOptions {}
 
 impl RespCommandSetOptions {
-    pub const EXPIRY: &'static str = "PX";
+    pub const IF_NOT_EXISTS: &'static str = "NX";
+    pub const IF_EXISTS: &'static str = "XX";
+    pub const GET: &'static str = "GET";
+    pub const EXPIRY_SECONDS: &'static str = "EX";
+    pub const EXPIRY_MILLISECONDS: &'static str = "PX";
+    pub const EXPIRY_AT_SECONDS: &'static str = "EXAT";
+    pub const EXPIRY_AT_MILLISECONDS: &'static str = "PXAT";
+    pub const KEEP_TTL: &'static str = "KEEPTTL";
+}
+
+pub struct RespCommandGetExOptions {}
+
+impl RespCommandGetExOptions {
+    pub const PERSIST: &'static str = "PERSIST";
+}
+
+pub struct RespCommandExpireOptions {}
+
+impl RespCommandExpireOptions {
+    pub const NX: &'static str = "NX";
+    pub const XX: &'static str = "XX";
+    pub const GT: &'static str = "GT";
+    pub const LT: &'static str = "LT";
+}
+
+pub struct RespCommandCopyOptions {}
+
+impl RespCommandCopyOptions {
+    pub const DB: &'static str = "DB";
+    pub const REPLACE: &'static str = "REPLACE";
+}
+
+pub struct RespCommandScanOptions {}
+
+impl RespCommandScanOptions {
+    pub const MATCH: &'static str = "MATCH";
+    pub const COUNT: &'static str = "COUNT";
+    pub const TYPE: &'static str = "TYPE";
+    pub const NOVALUES: &'static str = "NOVALUES";
+}
+
+pub struct RespCommandFlushOptions {}
+
+impl RespCommandFlushOptions {
+    pub const ASYNC: &'static str = "ASYNC";
+    pub const SYNC: &'static str = "SYNC";
+}
+
+pub struct RespCommandListDirections {}
+
+impl RespCommandListDirections {
+    pub const LEFT: &'static str = "LEFT";
+    pub const RIGHT: &'static str = "RIGHT";
+}
+
+pub struct RespCommandLInsertOptions {}
+
+impl RespCommandLInsertOptions {
+    pub const BEFORE: &'static str = "BEFORE";
+    pub const AFTER: &'static str = "AFTER";
+}
+
+pub struct RespCommandLPosOptions {}
+
+impl RespCommandLPosOptions {
+    pub const RANK: &'static str = "RANK";
+    pub const COUNT: &'static str = "COUNT";
+    pub const MAXLEN: &'static str = "MAXLEN";
+}
+
+pub struct RespCommandLMPopOptions {}
+
+impl RespCommandLMPopOptions {
+    pub const COUNT: &'static str = "COUNT";
+}
+
+pub struct RespCommandHashFieldsOptions {}
+
+impl RespCommandHashFieldsOptions {
+    pub const FIELDS: &'static str = "FIELDS";
+}
+
+pub struct RespCommandHRandFieldOptions {}
+
+impl RespCommandHRandFieldOptions {
+    pub const WITHVALUES: &'static str = "WITHVALUES";
+}
+
+pub struct RespCommandSInterCardOptions {}
+
+impl RespCommandSInterCardOptions {
+    pub const LIMIT: &'static str = "LIMIT";
+}
+
+pub struct RespCommandObjectSubcommands {}
+
+impl RespCommandObjectSubcommands {
+    pub const ENCODING: &'static str = "ENCODING";
+}
+
+pub struct RespCommandZAddOptions {}
+
+impl RespCommandZAddOptions {
+    pub const NX: &'static str = "NX";
+    pub const XX: &'static str = "XX";
+    pub const GT: &'static str = "GT";
+    pub const LT: &'static str = "LT";
+    pub const CH: &'static str = "CH";
+    pub const INCR: &'static str = "INCR";
+}
+
+pub struct RespCommandZRangeOptions {}
+
+impl RespCommandZRangeOptions {
+    pub const BYSCORE: &'static str = "BYSCORE";
+    pub const BYLEX: &'static str = "BYLEX";
+    pub const REV: &'static str = "REV";
+    pub const LIMIT: &'static str = "LIMIT";
+    pub const WITHSCORES: &'static str = "WITHSCORES";
+}
+
+pub struct RespCommandZRankOptions {}
+
+impl RespCommandZRankOptions {
+    pub const WITHSCORE: &'static str = "WITHSCORE";
+}
+
+pub struct RespCommandZStoreOptions {}
+
+impl RespCommandZStoreOptions {
+    pub const WEIGHTS: &'static str = "WEIGHTS";
+    pub const AGGREGATE: &'static str = "AGGREGATE";
+    pub const SUM: &'static str = "SUM";
+    pub const MIN: &'static str = "MIN";
+    pub const MAX: &'static str = "MAX";
+}
+
+pub struct RespCommandLcsOptions {}
+
+impl RespCommandLcsOptions {
+    pub const LEN: &'static str = "LEN";
+    pub const IDX: &'static str = "IDX";
+    pub const MIN_MATCH_LEN: &'static str = "MINMATCHLEN";
+    pub const WITH_MATCH_LEN: &'static str = "WITHMATCHLEN";
+}
+
+pub struct RespCommandHelloOptions {}
+
+impl RespCommandHelloOptions {
+    pub const AUTH: &'static str = "AUTH";
+    pub const SETNAME: &'static str = "SETNAME";
 }
 
 pub struct RespCommandReplConfOption {}
@@ -56,26 +380,125 @@ impl RespCommandReplConfOption {
     pub const LISTENING_PORT: &'static str = "listening-port";
 }
 
+/// A RESP reply, as returned by the command handlers. <br/>
+/// It is serialized into the wire format by [`crate::resp_parser::encode_resp_value`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RespValue {
+    SimpleString(String),
+    /// The message should start with the error prefix, e.g. "ERR" or "WRONGTYPE".
+    Error(String),
+    Integer(i64),
+    BulkString(Bytes),
+    NullBulkString,
+    Array(Vec<RespValue>),
+    NullArray,
+
+    // RESP3 types, replied as their closest RESP2 type to RESP2 connections.
+    /// Replied as a null bulk string in RESP2.
+    Null,
+    /// Replied as the integer 1 or 0 in RESP2.
+    Boolean(bool),
+    /// Replied as a bulk string in RESP2.
+    Double(f64),
+    /// Replied as a flat array of keys and values in RESP2.
+    Map(Vec<(RespValue, RespValue)>),
+    /// Replied as an array in RESP2.
+    Set(Vec<RespValue>),
+    /// Out of band data, like Pub/Sub messages. Replied as an array in RESP2.
+    Push(Vec<RespValue>),
+}
+
+impl RespValue {
+    pub fn ok() -> Self {
+        RespValue::SimpleString(RespCommandResponseNames::OK.to_owned())
+    }
+}
+
 #[derive(Debug)]
 pub enum RespDataType {
-    BulkString { size: u32, value: String },
+    BulkString { value: Bytes },
     SimpleString { value: String },
 }
 
 impl RespDataType {
+    /// Bulk strings are binary safe, so any bytes that are not valid UTF-8 are replaced.
     pub fn get_value_string(&self) -> String {
         match self {
-            RespDataType::BulkString { size: _, value } => value.to_owned(),
+            RespDataType::BulkString { value } => String::from_utf8_lossy(value).into_owned(),
             RespDataType::SimpleString { value } => value.to_owned(),
         }
     }
+
+    pub fn get_value_bytes(&self) -> Bytes {
+        match self {
+            RespDataType::BulkString { value } => value.clone(),
+            RespDataType::SimpleString { value } => Bytes::from(value.to_owned()),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RespCommand {
     pub name: String,
 
-    pub command_type: RespCommandType,
+    /// Parameters are kept as the raw bytes received, since keys and values are binary safe.
+    pub parameters: Vec<Bytes>,
+}
 
-    pub parameters: Vec<String>,
+impl RespCommand {
+    pub fn new(name: &str, parameters: Vec<Bytes>) -> Self {
+        RespCommand {
+            name: name.to_owned(),
+            parameters,
+        }
+    }
+
+    /// Returns the parameter at `index` as text, for parameters that are expected to be options
+    /// or numbers rather than arbitrary data.
+    pub fn get_parameter_str(&self, index: usize) -> Result<&str, Error> {
+        match self.parameters.get(index) {
+            None => Err(Error::msg(format!(
+                "Could not parse command: {} is missing parameter {}.",
+                self.name,
+                index + 1
+            ))),
+            Some(parameter) => match std::str::from_utf8(parameter) {
+                Err(_) => Err(Error::msg(format!(
+                    "Could not parse command: {} parameter {} is not valid UTF-8.",
+                    self.name,
+                    index + 1
+                ))),
+                Ok(parameter) => Ok(parameter),
+            },
+        }
+    }
+
+    /// Returns the parameter at `index` as an integer, or [`CommandError::NotAnInteger`].
+    pub fn get_parameter_i64(&self, index: usize) -> Result<i64, Error> {
+        self.parameters
+            .get(index)
+            .and_then(|parameter| parse_strict_i64(parameter))
+            .ok_or_else(|| CommandError::NotAnInteger.into())
+    }
+
+    /// Returns the parameter at `index` as a float, or [`CommandError::NotAFloat`].
+    pub fn get_parameter_f64(&self, index: usize) -> Result<f64, Error> {
+        self.parameters
+            .get(index)
+            .and_then(|parameter| parse_strict_f64(parameter))
+            .ok_or_else(|| CommandError::NotAFloat.into())
+    }
+
+    /// Encodes the command the way clients send it, as an array of bulk strings.
+    pub fn to_resp_bytes(&self) -> Vec<u8> {
+        encode_resp_value(
+            &RespValue::Array(
+                std::iter::once(Bytes::from(self.name.clone()))
+                    .chain(self.parameters.iter().cloned())
+                    .map(RespValue::BulkString)
+                    .collect(),
+            ),
+            RespProtocolVersion::Resp2,
+        )
+    }
 }
@@ -2,9 +2,12 @@
 pub(crate) mod utils {
     use crate::{
         models::{
+            connection_context::{ConnectionContext, Response},
             db::{app_data::AppData, in_memory_db::InMemoryDb},
             t_stream::TStream,
         },
+        node::command_listener::handle_buffered_commands,
+        resp_parser::shared::RespValue,
         DEFAULT_LISTENING_PORT,
     };
 
@@ -15,19 +18,71 @@ pub(crate) mod utils {
     };
 
     use anyhow::Error;
+    use bytes::Bytes;
     use tokio::{
         io::{AsyncRead, AsyncWrite},
         sync::Mutex,
     };
 
-    pub(crate) fn create_test_mem_db<'a>() -> Result<Arc<Mutex<InMemoryDb>>, Error> {
-        Ok(InMemoryDb::new(AppData::new_master(
-            DEFAULT_LISTENING_PORT,
-        )?)?)
+    pub(crate) fn create_test_mem_db() -> Result<Arc<Mutex<InMemoryDb>>, Error> {
+        InMemoryDb::new(AppData::new_master(DEFAULT_LISTENING_PORT)?)
     }
 
     pub(crate) fn create_test_tstream() -> Arc<Mutex<dyn TStream>> {
-        Arc::new(Mutex::new(FakeTStream {}))
+        Arc::new(Mutex::new(FakeTStream::default()))
+    }
+
+    /// Handles all commands in `request` on a new connection to `mem_db`, and returns their
+    /// replies. <br/>
+    /// Inline commands make requests easy to read, e.g.: `b"SET foo bar\r\nGET foo\r\n"`
+    pub(crate) async fn send_test_request(
+        mem_db: &Arc<Mutex<InMemoryDb>>,
+        request: &[u8],
+    ) -> Result<Vec<RespValue>, Error> {
+        let fake_tcp_stream = create_test_tstream();
+        let mut fake_app_context = ConnectionContext::new(mem_db, &fake_tcp_stream)?;
+        fake_app_context.request.buffer.extend_from_slice(request);
+
+        let mut responses = Vec::new();
+        loop {
+            let handled_commands = handle_buffered_commands(&mut fake_app_context).await?;
+            responses.extend(handled_commands.responses);
+
+            if !handled_commands.has_pending_command {
+                break;
+            }
+        }
+
+        Ok(responses
+            .into_iter()
+            .map(|response| match response {
+                Response::Value(value) => value,
+                Response::Raw(bytes) => panic!("unexpected raw response: {:?}", bytes),
+            })
+            .collect())
+    }
+
+    pub(crate) fn bulk_string(value: &str) -> RespValue {
+        RespValue::BulkString(Bytes::from(value.to_owned()))
+    }
+
+    /// An array of bulk strings, e.g. the reply of LRANGE.
+    pub(crate) fn bulk_strings(values: &[&str]) -> RespValue {
+        RespValue::Array(values.iter().map(|value| bulk_string(value)).collect())
+    }
+
+    /// An array of integers, e.g. the reply of SMISMEMBER.
+    pub(crate) fn integers(values: &[i64]) -> RespValue {
+        RespValue::Array(
+            values
+                .iter()
+                .map(|value| RespValue::Integer(*value))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn error(message: &str) -> RespValue {
+        RespValue::Error(message.to_owned())
     }
 
     pub(crate) fn create_fake_socket_addr() -> std::net::SocketAddr {
@@ -37,8 +92,11 @@ pub(crate) mod utils {
         ))
     }
 
-    #[derive(Debug)]
-    pub(crate) struct FakeTStream {}
+    #[derive(Debug, Default)]
+    pub(crate) struct FakeTStream {
+        /// Everything written to the stream, e.g. the commands propagated to a fake replica.
+        pub written: Vec<u8>,
+    }
 
     impl TStream for FakeTStream {
         fn local_addr(&self) -> tokio::io::Result<SocketAddr> {
@@ -52,11 +110,13 @@ pub(crate) mod utils {
 
     impl AsyncWrite for FakeTStream {
         fn poll_write(
-            self: std::pin::Pin<&mut Self>,
+            mut self: std::pin::Pin<&mut Self>,
             _: &mut std::task::Context<'_>,
-            _: &[u8],
+            buf: &[u8],
         ) -> std::task::Poll<Result<usize, std::io::Error>> {
-            Poll::Ready(Ok(0))
+            self.written.extend_from_slice(buf);
+
+            Poll::Ready(Ok(buf.len()))
         }
 
         fn poll_flush(
@@ -74,13 +134,15 @@ pub(crate) mod utils {
         }
     }
 
+    /// Reads never complete, like a client that stays connected without sending anything else,
+    /// e.g. while it is blocked by BLPOP.
     impl AsyncRead for FakeTStream {
         fn poll_read(
             self: std::pin::Pin<&mut Self>,
             _: &mut std::task::Context<'_>,
             _: &mut tokio::io::ReadBuf<'_>,
         ) -> std::task::Poll<std::io::Result<()>> {
-            Poll::Ready(Ok(()))
+            Poll::Pending
         }
     }
 }
@@ -1,4 +1,8 @@
-use std::{iter::Map, ops::Index, time::SystemTime};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::SystemTime,
+};
 
 use anyhow::Error;
 
@@ -25,18 +29,172 @@ impl U128number {
 pub struct LineEndings {}
 
 impl LineEndings {
-    pub const CRLF_STR: &'static str = "\r\n";
     pub const CRLF_BYTES: &'static [u8] = b"\r\n";
+}
 
-    pub const LF_CHAR: char = '\n';
-    pub const LF_BYTE: u8 = b'\n';
+pub fn return_err<T>(message: String) -> Result<T, Error> {
+    Err(Error::msg(message))
+}
 
-    pub const CR_CHAR: char = '\r';
-    pub const CR_BYTE: u8 = b'\r';
+/// Milliseconds since the Unix epoch.
+pub fn unix_time_millis() -> Result<u128, Error> {
+    Ok(SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_millis())
 }
 
-pub fn return_err<T>(message: String) -> Result<T, Error> {
-    return Err(Error::msg(message));
+/// Parses a 64 bit integer as strictly as Redis does: no sign other than a leading '-', no
+/// leading zeros and no spaces, so that only the canonical representation of a number parses.
+pub fn parse_strict_i64(raw_number: &[u8]) -> Option<i64> {
+    let digits = raw_number.strip_prefix(b"-").unwrap_or(raw_number);
+
+    let is_canonical = match digits {
+        [] => false,
+        [b'0'] => digits.len() == raw_number.len(),
+        [first, ..] => *first != b'0' && digits.iter().all(|byte| byte.is_ascii_digit()),
+    };
+
+    if !is_canonical {
+        return None;
+    }
+
+    std::str::from_utf8(raw_number).ok()?.parse::<i64>().ok()
+}
+
+/// Parses a float like Redis does: no spaces around it, and never into NaN.
+pub fn parse_strict_f64(raw_number: &[u8]) -> Option<f64> {
+    std::str::from_utf8(raw_number)
+        .ok()?
+        .parse::<f64>()
+        .ok()
+        .filter(|number| !number.is_nan())
+}
+
+/// Formats a float like Redis replies and stores the result of INCRBYFLOAT (`LD_STR_HUMAN`): in
+/// decimal notation, never with an exponent, and without trailing zeros. <br/>
+/// Redis rounds its long double to 17 significant digits, which hides the error of the last
+/// operation. An `f64` carries fewer digits, so it is rounded to `f64::DIGITS` (15) for the same
+/// effect, e.g. 0.1 + 0.2 is "0.3" rather than "0.30000000000000004".
+pub fn format_float_value(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_owned();
+    }
+
+    let exponent = value.abs().log10().floor() as i32;
+    let decimals = (f64::DIGITS as i32 - 1 - exponent).max(0) as usize;
+    let formatted = format!("{:.*}", decimals, value);
+
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned()
+    } else {
+        formatted
+    }
+}
+
+/// A random number below `bound`, e.g. to pick random fields for HRANDFIELD. <br/>
+/// Every `RandomState` is seeded differently, which is random enough to sample values.
+pub fn random_below(bound: usize) -> usize {
+    (RandomState::new().build_hasher().finish() % bound as u64) as usize
+}
+
+/// Matches `string` against a glob-style pattern like Redis does for KEYS and SCAN: <br/>
+/// `*` matches any bytes, `?` any single byte, `[abc]` or `[a-z]` a byte of the class (`[^a]` a
+/// byte not in it), and `\` escapes the next byte, so that it matches literally.
+pub fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut pattern_idx, mut string_idx) = (0, 0);
+    // Where to resume from if the bytes after the last `*` do not match: the pattern after the
+    // star, and the string byte the star should swallow next.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while string_idx < string.len() {
+        let byte = string[string_idx];
+
+        let next_pattern_idx = match pattern.get(pattern_idx) {
+            Some(b'*') => {
+                while pattern.get(pattern_idx) == Some(&b'*') {
+                    pattern_idx += 1;
+                }
+
+                if pattern_idx == pattern.len() {
+                    return true;
+                }
+
+                backtrack = Some((pattern_idx, string_idx));
+                continue;
+            }
+            Some(b'?') => Some(pattern_idx + 1),
+            Some(b'[') => match match_glob_class(pattern, pattern_idx + 1, byte) {
+                (true, next_pattern_idx) => Some(next_pattern_idx),
+                (false, _) => None,
+            },
+            Some(b'\\') if pattern_idx + 1 < pattern.len() => {
+                Some(pattern_idx + 2).filter(|_| pattern[pattern_idx + 1] == byte)
+            }
+            Some(pattern_byte) => Some(pattern_idx + 1).filter(|_| *pattern_byte == byte),
+            None => None,
+        };
+
+        match (next_pattern_idx, backtrack) {
+            (Some(next_pattern_idx), _) => {
+                pattern_idx = next_pattern_idx;
+                string_idx += 1;
+            }
+            (None, Some((star_pattern_idx, star_string_idx))) => {
+                pattern_idx = star_pattern_idx;
+                string_idx = star_string_idx + 1;
+                backtrack = Some((star_pattern_idx, string_idx));
+            }
+            (None, None) => return false,
+        }
+    }
+
+    pattern[pattern_idx..]
+        .iter()
+        .all(|pattern_byte| *pattern_byte == b'*')
+}
+
+/// Matches `byte` against the class starting at `start`, right after its `[`, and returns
+/// whether it matched and where the pattern continues after the class. <br/>
+/// Like Redis, a class that is not closed takes the rest of the pattern.
+fn match_glob_class(pattern: &[u8], start: usize, byte: u8) -> (bool, usize) {
+    let mut idx = start;
+    let is_negated = pattern.get(idx) == Some(&b'^');
+    if is_negated {
+        idx += 1;
+    }
+
+    let mut is_match = false;
+
+    while let Some(class_byte) = pattern.get(idx) {
+        match class_byte {
+            b']' => {
+                idx += 1;
+                break;
+            }
+            b'\\' if idx + 1 < pattern.len() => {
+                is_match |= pattern[idx + 1] == byte;
+                idx += 2;
+            }
+            _ if idx + 2 < pattern.len() && pattern[idx + 1] == b'-' => {
+                let (start, end) = (
+                    pattern[idx].min(pattern[idx + 2]),
+                    pattern[idx].max(pattern[idx + 2]),
+                );
+
+                is_match |= (start..=end).contains(&byte);
+                idx += 3;
+            }
+            _ => {
+                is_match |= *class_byte == byte;
+                idx += 1;
+            }
+        }
+    }
+
+    (is_match != is_negated, idx)
 }
 
 pub fn hex_to_utf8_bytes(hex_buff: &[u8]) -> Result<Vec<u8>, Error> {
@@ -84,60 +242,14 @@ pub fn split_u8_slice_once<'a>(
     }
 }
 
-/// If not found it returns `source.len()`.
 pub fn find_first_index_in_u8_slice(source: &[u8], query: &[u8]) -> Option<usize> {
-    for i in 0..source.len() {
-        if &source[i..i + query.len()] == query {
-            return Some(i);
-        }
-    }
-
-    None
-}
-
-pub fn u8_slice_into_char_slice(source: &[u8], target: &mut [char]) {
-    for i in 0..source.len() {
-        target[i] = source[i] as char;
-    }
-}
-
-/// It will stop when it reaches a 0 value byte. At the moment this has no overflow protection whatsoever.
-pub fn delete_bytes_after_first_crlf(buff: &mut [u8]) -> &[u8] {
-    let mut i = 0;
-    let mut delete = false;
-
-    loop {
-        if buff[i] == 0 {
-            return buff;
-        }
-
-        if delete {
-            buff[i] = 0;
-            i = i + 1;
-            continue;
-        }
-
-        if buff[i] != LineEndings::CR_BYTE {
-            i = i + 1;
-            continue;
-        }
-
-        if buff[i + 1] == LineEndings::LF_BYTE {
-            delete = true;
-            i = i + 1;
-            continue;
-        }
-    }
-}
-
-pub fn concat_u32(left: u32, right: u32) -> Option<u32> {
-    let pow_result = 10u32.checked_pow(u32_count(right));
-
-    if pow_result.is_none() {
+    if query.is_empty() {
         return None;
     }
 
-    Some((left * pow_result.unwrap()) + right)
+    source
+        .windows(query.len())
+        .position(|window| window == query)
 }
 
 pub fn u32_count(value: u32) -> u32 {
@@ -152,32 +264,6 @@ pub fn u32_count(value: u32) -> u32 {
     counter
 }
 
-/// Copies `source` from index 0 into `target` from the inclusive `start` and stops when the result of the `until` closure is true. <br/>
-/// This function does not offer overflow protection, so the caller has to account for that.
-///
-/// `start`: start index of target to copy into.
-/// `until`: closure with a predicate to stop the copy. Receives (current item, current target index, current source index).
-pub fn copy_to_array_until<T, F>(target: &mut [T], source: &[T], start: usize, until: F)
-where
-    T: Copy,
-    F: Fn(T, usize, usize) -> bool,
-{
-    let mut i_target: usize = start;
-    let mut i_source: usize = 0;
-
-    loop {
-        let item = source[i_source];
-
-        if until(item, i_target, i_source) {
-            break;
-        }
-
-        target[i_target] = item;
-        i_target += 1;
-        i_source += 1;
-    }
-}
-
 // const ALPHANUMERIC_BYTES: &[u8; 62] = b"1234567890abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
 const ALPHANUMERIC_BYTES: &[u8; 62] =
     b"1a2b3c4d5e6f7g8h9i0jklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
@@ -277,7 +363,10 @@ pub fn pseudo_random_number(size: u8, seed: u32) -> Result<u32, Error> {
 mod tests {
     use anyhow::{Error, Result};
 
-    use super::{find_first_index_in_u8_slice, pseudo_random_number};
+    use super::{
+        find_first_index_in_u8_slice, format_float_value, glob_match, parse_strict_f64,
+        parse_strict_i64, pseudo_random_number,
+    };
     use crate::utils::{
         pseudo_random_ascii, pseudo_random_ascii_alphanumeric, split_u8_slice_once, u32_count,
     };
@@ -309,6 +398,93 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_strict_i64_passes() {
+        assert_eq!(parse_strict_i64(b"0"), Some(0));
+        assert_eq!(parse_strict_i64(b"-42"), Some(-42));
+        assert_eq!(parse_strict_i64(b"-9223372036854775808"), Some(i64::MIN));
+
+        for raw_number in [
+            &b""[..],
+            b"-",
+            b"-0",
+            b"007",
+            b"+1",
+            b" 1",
+            b"1 ",
+            b"1.0",
+            b"9223372036854775808",
+        ] {
+            assert_eq!(parse_strict_i64(raw_number), None);
+        }
+    }
+
+    #[test]
+    fn parse_strict_f64_passes() {
+        assert_eq!(parse_strict_f64(b"1.5"), Some(1.5));
+        assert_eq!(parse_strict_f64(b"-5e3"), Some(-5000.0));
+        assert_eq!(parse_strict_f64(b"inf"), Some(f64::INFINITY));
+
+        for raw_number in [&b""[..], b"nan", b" 1", b"1 ", b"abc"] {
+            assert_eq!(parse_strict_f64(raw_number), None);
+        }
+    }
+
+    #[test]
+    fn format_float_value_passes() {
+        assert_eq!(format_float_value(0.1 + 0.2), "0.3");
+        assert_eq!(format_float_value(10.5), "10.5");
+        assert_eq!(format_float_value(-5.0), "-5");
+        assert_eq!(format_float_value(0.0), "0");
+        assert_eq!(format_float_value(1e20), "100000000000000000000");
+        assert_eq!(format_float_value(1.5e-10), "0.00000000015");
+        assert_eq!(format_float_value(3.0 * 1.1), "3.3");
+    }
+
+    #[test]
+    fn glob_match_passes() {
+        for (pattern, string) in [
+            (&b"*"[..], &b""[..]),
+            (b"*", b"anything"),
+            (b"user:*", b"user:42"),
+            (b"*:*:name", b"user:42:name"),
+            (b"h?llo", b"hello"),
+            (b"h*llo", b"heeeello"),
+            (b"h[ae]llo", b"hallo"),
+            (b"h[^e]llo", b"hallo"),
+            (b"h[a-b]llo", b"hbllo"),
+            (b"h[z-a]llo", b"hbllo"),
+            (b"h\\*llo", b"h*llo"),
+            (b"h[\\]]llo", b"h]llo"),
+            (b"a*b*c", b"aXbYbZc"),
+            (b"[abc", b"c"),
+        ] {
+            assert!(
+                glob_match(pattern, string),
+                "{:?} should match {:?}",
+                String::from_utf8_lossy(pattern),
+                String::from_utf8_lossy(string)
+            );
+        }
+
+        for (pattern, string) in [
+            (&b""[..], &b"a"[..]),
+            (b"user:*", b"users"),
+            (b"h?llo", b"hllo"),
+            (b"h[ae]llo", b"hillo"),
+            (b"h[^e]llo", b"hello"),
+            (b"h\\*llo", b"hello"),
+            (b"a*b*c", b"aXbYbZ"),
+        ] {
+            assert!(
+                !glob_match(pattern, string),
+                "{:?} should not match {:?}",
+                String::from_utf8_lossy(pattern),
+                String::from_utf8_lossy(string)
+            );
+        }
+    }
+
     #[test]
     fn u32_count_passes() {
         assert_eq!(u32_count(0), 1);